@@ -0,0 +1,115 @@
+//! Audio- and control-rate rendering of patterns.
+//!
+//! [`render_lfo`] turns a pattern into a modulation signal that restarts on
+//! every onset, the usual way to get sidechain-style pumping or rhythmic
+//! filter sweeps out of a rhythm.
+
+use std::f32::consts::TAU;
+
+/// How fast [`LfoShape::Decay`] falls: `e^-5` (under 1%) at the next onset.
+const DECAY_RATE: f32 = 5.0;
+
+/// The waveform [`render_lfo`] draws from each onset to the next.
+///
+/// Every shape spans exactly one gap between onsets and stays within
+/// `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LfoShape {
+    /// Jumps to 1 and falls exponentially towards 0, like an envelope.
+    #[default]
+    Decay,
+    /// Jumps to 1 and falls linearly to 0.
+    Saw,
+    /// Rises smoothly from 0 to 1 and back to 0 (one raised-cosine cycle),
+    /// with no jump at the onset.
+    Sine,
+}
+
+impl LfoShape {
+    /// The value at `phase` (`0.0..1.0`) of the way from one onset to the next.
+    pub fn value(self, phase: f32) -> f32 {
+        match self {
+            LfoShape::Decay => (-DECAY_RATE * phase).exp(),
+            LfoShape::Saw => 1.0 - phase,
+            LfoShape::Sine => 0.5 - 0.5 * (TAU * phase).cos(),
+        }
+    }
+}
+
+/// Renders one loop of `pattern` as a modulation buffer of `sample_rate`
+/// values per second, with sixteenth-note steps at `bpm`.
+///
+/// The waveform restarts at each onset and lasts until the next one,
+/// wrapping around the loop, so the buffer can be played back seamlessly.
+/// A pattern without onsets renders silence (all zeros). Returns an empty
+/// buffer for an empty pattern or a tempo or sample rate that is not
+/// positive.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{LfoShape, euclidean, render_lfo};
+///
+/// // Tresillo at 120 BPM: 8 sixteenths = 1 s, at a 1 kHz control rate.
+/// let lfo = render_lfo(&euclidean(8, 3, 0), LfoShape::Saw, 120.0, 1000);
+/// assert_eq!(lfo.len(), 1000);
+/// assert_eq!(lfo[0], 1.0);
+/// assert_eq!(lfo[375], 1.0); // second onset, 3 steps in
+/// assert!(lfo[374] < 0.01);
+/// ```
+pub fn render_lfo(pattern: &[bool], shape: LfoShape, bpm: f64, sample_rate: u32) -> Vec<f32> {
+    if pattern.is_empty() || bpm.is_nan() || bpm <= 0.0 || sample_rate == 0 {
+        return Vec::new();
+    }
+    let steps = pattern.len();
+    let samples_per_step = f64::from(sample_rate) * 60.0 / bpm / 4.0;
+    let len = (steps as f64 * samples_per_step).round() as usize;
+    let onsets: Vec<usize> = (0..steps).filter(|&i| pattern[i]).collect();
+    let Some(&last) = onsets.last() else {
+        return vec![0.0; len];
+    };
+
+    (0..len)
+        .map(|n| {
+            let position = n as f64 / samples_per_step;
+            // The segment starts at the latest onset at or before `position`,
+            // or at the last onset of the previous loop.
+            let index = onsets.partition_point(|&o| o as f64 <= position);
+            let (start, end) = match index {
+                0 => (last as f64 - steps as f64, onsets[0] as f64),
+                i => {
+                    let next = onsets.get(i).map_or(onsets[0] + steps, |&o| o);
+                    (onsets[i - 1] as f64, next as f64)
+                }
+            };
+            shape.value(((position - start) / (end - start)) as f32)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn restarts_on_every_onset_and_loops() {
+        // Onsets at steps 2 and 6 of 8; one sample per step at 120 BPM.
+        let pattern = euclidean(8, 2, 6);
+        let lfo = render_lfo(&pattern, LfoShape::Saw, 120.0, 8);
+        assert_eq!(lfo, [0.5, 0.25, 1.0, 0.75, 0.5, 0.25, 1.0, 0.75]);
+
+        let sine = render_lfo(&pattern, LfoShape::Sine, 120.0, 8);
+        assert!(sine[2].abs() < 1e-6 && (sine[4] - 1.0).abs() < 1e-6);
+        let decay = render_lfo(&pattern, LfoShape::Decay, 120.0, 8);
+        assert!(decay[2] == 1.0 && decay[3] < decay[2] && decay[5] > 0.0);
+    }
+
+    #[test]
+    fn degenerate_inputs() {
+        assert_eq!(render_lfo(&[false; 4], LfoShape::Decay, 120.0, 8), [0.0; 4]);
+        assert!(render_lfo(&[], LfoShape::Decay, 120.0, 8).is_empty());
+        assert!(render_lfo(&[true], LfoShape::Decay, 0.0, 8).is_empty());
+        assert!(render_lfo(&[true], LfoShape::Decay, f64::NAN, 8).is_empty());
+        assert!(render_lfo(&[true], LfoShape::Decay, 120.0, 0).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+//! Named collections of patterns.
+//!
+//! A [`PatternBank`] groups several patterns under track names, such as the
+//! voices of a drum kit. Exporters and renderers take a bank so that a whole
+//! groove can be handled in one call.
+//!
+//! A bank can also carry a [`Resolution`], the note value of one step for
+//! every track at once. Switching it turns a straight groove into a triplet
+//! one (or doubles its speed) without touching the patterns, and exporters
+//! that take a bank, such as `to_html`, follow it.
+
+use crate::role::TrackRole;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A single named pattern inside a [`PatternBank`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    /// Display name of the track (e.g. `"kick"`).
+    pub name: String,
+    /// Step data, `true` for a pulse and `false` for a rest.
+    pub pattern: Vec<bool>,
+}
+
+/// The note value of one step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    /// Eighth notes: 2 steps per beat.
+    Eighths,
+    /// Eighth-note triplets: 3 steps per beat.
+    EighthTriplets,
+    /// Sixteenth notes: 4 steps per beat.
+    #[default]
+    Sixteenths,
+    /// Sixteenth-note triplets: 6 steps per beat.
+    SixteenthTriplets,
+    /// Thirty-second notes: 8 steps per beat.
+    ThirtySeconds,
+}
+
+impl Resolution {
+    /// Steps per quarter-note beat.
+    pub const fn steps_per_beat(self) -> u16 {
+        match self {
+            Resolution::Eighths => 2,
+            Resolution::EighthTriplets => 3,
+            Resolution::Sixteenths => 4,
+            Resolution::SixteenthTriplets => 6,
+            Resolution::ThirtySeconds => 8,
+        }
+    }
+
+    /// Returns `true` for the triplet resolutions.
+    pub const fn is_triplet(self) -> bool {
+        matches!(
+            self,
+            Resolution::EighthTriplets | Resolution::SixteenthTriplets
+        )
+    }
+
+    /// Duration of one step in seconds at `bpm` (infinite for a
+    /// non-positive tempo).
+    pub fn step_seconds(self, bpm: f64) -> f64 {
+        if bpm <= 0.0 {
+            return f64::INFINITY;
+        }
+        60.0 / bpm / f64::from(self.steps_per_beat())
+    }
+}
+
+/// An ordered collection of named patterns.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, PatternBank};
+///
+/// let bank = PatternBank::new()
+///     .with_track("kick", euclidean(16, 4, 0))
+///     .with_track("hat", euclidean(16, 11, 2));
+/// assert_eq!(bank.len(), 2);
+/// assert_eq!(bank.steps(), 16);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatternBank {
+    tracks: Vec<Track>,
+    resolution: Option<Resolution>,
+}
+
+impl PatternBank {
+    /// Creates an empty bank.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a track and returns the bank, for builder-style construction.
+    #[must_use]
+    pub fn with_track(mut self, name: impl Into<String>, pattern: Vec<bool>) -> Self {
+        self.push(name, pattern);
+        self
+    }
+
+    /// Appends a track with the default pattern of `role`, named after it.
+    #[must_use]
+    pub fn with_role(mut self, role: TrackRole) -> Self {
+        self.push(role.name(), role.pattern());
+        self
+    }
+
+    /// Sets the step resolution of every track, for builder-style
+    /// construction.
+    #[must_use]
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// The step resolution of the kit, or `None` to leave it to each
+    /// exporter's own options.
+    pub fn resolution(&self) -> Option<Resolution> {
+        self.resolution
+    }
+
+    /// Sets or clears the step resolution. The patterns are not changed,
+    /// only how long their steps last.
+    pub fn set_resolution(&mut self, resolution: Option<Resolution>) {
+        self.resolution = resolution;
+    }
+
+    /// Appends a track.
+    pub fn push(&mut self, name: impl Into<String>, pattern: Vec<bool>) {
+        self.tracks.push(Track {
+            name: name.into(),
+            pattern,
+        });
+    }
+
+    /// Returns the tracks in insertion order.
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Looks up a track by name.
+    pub fn get(&self, name: &str) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.name == name)
+    }
+
+    /// Number of tracks in the bank.
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Returns `true` if the bank has no tracks.
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Length of the longest track, or 0 for an empty bank.
+    pub fn steps(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|t| t.pattern.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn builds_and_looks_up_tracks() {
+        let bank = PatternBank::new()
+            .with_track("kick", euclidean(8, 3, 0))
+            .with_track("hat", euclidean(12, 5, 0));
+
+        assert_eq!(bank.len(), 2);
+        assert_eq!(bank.steps(), 12);
+        assert_eq!(bank.get("kick").unwrap().pattern, euclidean(8, 3, 0));
+        assert!(bank.get("snare").is_none());
+        assert!(PatternBank::new().is_empty());
+
+        let triplets = bank.clone().with_resolution(Resolution::EighthTriplets);
+        assert_eq!(triplets.tracks(), bank.tracks());
+        assert_eq!(
+            triplets.resolution().map(Resolution::steps_per_beat),
+            Some(3)
+        );
+        assert_eq!(Resolution::ThirtySeconds.step_seconds(120.0), 0.0625);
+        assert!(Resolution::SixteenthTriplets.is_triplet());
+    }
+}
@@ -0,0 +1,91 @@
+//! Command-line front end.
+//!
+//! `euclid repl [bpm]` runs a sequencer in real time and reads live-coding
+//! commands (see [`LiveCommand`]) from standard input. Changes land on the
+//! next bar, and every step that fires is printed with the tracks it
+//! triggered.
+
+use euclidean_rhythm::{LiveCommand, LiveQueue, Sequencer};
+use std::io::BufRead;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: euclid repl [bpm]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let bpm = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["repl"] => 120.0,
+        ["repl", bpm] => match bpm.parse::<f64>() {
+            Ok(bpm) if bpm.is_finite() && bpm > 0.0 => bpm,
+            _ => {
+                eprintln!("bpm must be a positive number");
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    repl(bpm);
+    ExitCode::SUCCESS
+}
+
+/// Plays until `quit` or the end of input.
+fn repl(bpm: f64) {
+    // Reading blocks, so lines arrive from a thread while the clock runs.
+    let (lines, typed) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    eprintln!("euclid repl at {bpm} BPM. Try `kick = e(4,16)`, `swing 55` or `bpm 124`;");
+    eprintln!("`hat = e(5,12) clustered` picks another algorithm. Changes land on the");
+    eprintln!("next bar. `quit` or end of input stops.");
+    let mut seq = Sequencer::new(bpm);
+    let steps_per_bar = u64::from(seq.steps_per_beat()) * 4;
+    let mut queue = LiveQueue::new(steps_per_bar);
+    let mut last = Instant::now();
+    loop {
+        loop {
+            match typed.try_recv() {
+                Ok(line) if matches!(line.trim(), "quit" | "exit") => return,
+                Ok(line) => match line.parse::<LiveCommand>() {
+                    Ok(command) => queue.push(command),
+                    Err(_) if line.trim().is_empty() => {}
+                    Err(err) => eprintln!("error: {err}"),
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        queue.apply(&mut seq);
+        let now = Instant::now();
+        let events = seq.advance((now - last).as_secs_f64());
+        last = now;
+        // Events come in tick order, one run per step.
+        for step in events.chunk_by(|a, b| a.tick == b.tick) {
+            let tick = step[0].tick;
+            let names: Vec<&str> = step
+                .iter()
+                .map(|event| seq.tracks()[event.track].name.as_str())
+                .collect();
+            println!(
+                "{:>4}.{:<2} {}",
+                tick / steps_per_bar + 1,
+                tick % steps_per_bar + 1,
+                names.join(" ")
+            );
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,211 @@
+//! Clock utilities for PPQN tick streams.
+//!
+//! Hardware and MIDI clocks send a fixed number of pulses per quarter note
+//! (PPQN): 24 for MIDI clock, 48 or 96 for many modular and drum machine
+//! clocks. [`Ppqn`] converts between those resolutions and the crate's own
+//! units, beats and steps (4 steps per beat = sixteenths), and
+//! [`ClockDivider`] derives slower or faster clocks from the ticks, like a
+//! clock divider module in a rack.
+//!
+//! Everything counts whole ticks with integer arithmetic, so derived clocks
+//! never drift against their source.
+
+/// Pulses per quarter note of a tick clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Ppqn {
+    /// 24 PPQN, the MIDI clock and DIN sync rate.
+    #[default]
+    P24,
+    /// 48 PPQN.
+    P48,
+    /// 96 PPQN.
+    P96,
+}
+
+impl Ppqn {
+    /// Ticks per quarter note.
+    pub const fn ticks(self) -> u32 {
+        match self {
+            Ppqn::P24 => 24,
+            Ppqn::P48 => 48,
+            Ppqn::P96 => 96,
+        }
+    }
+
+    /// Converts a tick count at this resolution to one at `to`, rounding
+    /// down when `to` is coarser.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::Ppqn;
+    ///
+    /// assert_eq!(Ppqn::P24.convert(6, Ppqn::P96), 24);
+    /// assert_eq!(Ppqn::P96.convert(23, Ppqn::P24), 5);
+    /// ```
+    pub fn convert(self, tick: u64, to: Ppqn) -> u64 {
+        (u128::from(tick) * u128::from(to.ticks()) / u128::from(self.ticks())) as u64
+    }
+
+    /// The beat position of `tick`, as used by the sequencer's
+    /// `ClockSnapshot`.
+    pub fn beat(self, tick: u64) -> f64 {
+        tick as f64 / f64::from(self.ticks())
+    }
+
+    /// Ticks per step at `steps_per_beat` steps per beat, or `None` if the
+    /// steps do not fall on whole ticks (or `steps_per_beat` is zero).
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::Ppqn;
+    ///
+    /// assert_eq!(Ppqn::P24.ticks_per_step(4), Some(6)); // sixteenths
+    /// assert_eq!(Ppqn::P24.ticks_per_step(5), None);
+    /// ```
+    pub fn ticks_per_step(self, steps_per_beat: u32) -> Option<u32> {
+        let ticks = self.ticks();
+        (steps_per_beat != 0 && ticks.is_multiple_of(steps_per_beat))
+            .then(|| ticks / steps_per_beat)
+    }
+}
+
+/// Derives a clock from a PPQN tick stream, firing at a rational multiple of
+/// the quarter-note rate.
+///
+/// A new divider fires once per quarter note; [`divide`](Self::divide) and
+/// [`multiply`](Self::multiply) combine into one ratio, so
+/// `.multiply(4).divide(3)` fires four times every three beats. When the
+/// ratio does not land on whole ticks the output alternates between the two
+/// nearest spacings, with at most one tick of jitter. It never fires more than once per tick.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{ClockDivider, Ppqn};
+///
+/// // Sixteenths from MIDI clock: every sixth tick.
+/// let mut sixteenths = ClockDivider::new(Ppqn::P24).multiply(4);
+/// let fired: Vec<bool> = (0..12).map(|_| sixteenths.tick()).collect();
+/// assert_eq!(fired.iter().filter(|&&f| f).count(), 2);
+/// assert!(fired[0] && fired[6]);
+///
+/// // Half notes, starting over on a reset.
+/// let mut halves = ClockDivider::new(Ppqn::P24).divide(2);
+/// assert!(halves.tick());
+/// halves.reset();
+/// assert!(halves.tick());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClockDivider {
+    ppqn: Ppqn,
+    multiply: u32,
+    divide: u32,
+    position: u64,
+}
+
+impl ClockDivider {
+    /// Creates a divider firing once per quarter note of `ppqn` ticks.
+    pub fn new(ppqn: Ppqn) -> Self {
+        Self {
+            ppqn,
+            multiply: 1,
+            divide: 1,
+            position: 0,
+        }
+    }
+
+    /// Slows the output by `factor` (at least 1), for builder-style
+    /// construction.
+    #[must_use]
+    pub fn divide(mut self, factor: u32) -> Self {
+        self.divide = self.divide.saturating_mul(factor.max(1));
+        self
+    }
+
+    /// Speeds the output up by `factor` (at least 1), for builder-style
+    /// construction.
+    #[must_use]
+    pub fn multiply(mut self, factor: u32) -> Self {
+        self.multiply = self.multiply.saturating_mul(factor.max(1));
+        self
+    }
+
+    /// Consumes one input tick and returns whether the output fires on it.
+    pub fn tick(&mut self) -> bool {
+        let fires = self.fires_at(self.position);
+        self.position += 1;
+        fires
+    }
+
+    /// Returns whether the output fires on input tick `tick`, counted from
+    /// the last reset.
+    pub fn fires_at(&self, tick: u64) -> bool {
+        // Fire whenever tick * multiply / (ppqn * divide) reaches a new
+        // whole number.
+        let period = u128::from(self.ppqn.ticks()) * u128::from(self.divide);
+        let multiply = u128::from(self.multiply);
+        u128::from(tick) * multiply % period < multiply
+    }
+
+    /// Restarts the phase: the next tick fires, as on a downbeat.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+
+    /// Moves to input tick `tick`, e.g. after a MIDI Song Position Pointer,
+    /// keeping the phase the output would have had running from tick 0.
+    pub fn set_position(&mut self, tick: u64) {
+        self.position = tick;
+    }
+
+    /// Input ticks consumed since the last reset.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The tick resolution of the input.
+    pub fn ppqn(&self) -> Ppqn {
+        self.ppqn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn fired(divider: &mut ClockDivider, ticks: usize) -> Vec<usize> {
+        (0..ticks).filter(|_| divider.tick()).collect()
+    }
+
+    #[test]
+    fn ratios_fire_evenly() {
+        let mut eighths = ClockDivider::new(Ppqn::P96).multiply(2);
+        assert_eq!(fired(&mut eighths, 192), [0, 48, 96, 144]);
+
+        // Five per beat at 24 PPQN: spacings of 4 and 5 ticks, five per beat.
+        let mut quintuplets = ClockDivider::new(Ppqn::P24).multiply(5);
+        let ticks = fired(&mut quintuplets, 48);
+        assert_eq!(ticks.len(), 10);
+        assert!(ticks.windows(2).all(|w| matches!(w[1] - w[0], 4 | 5)));
+
+        let mut bars = ClockDivider::new(Ppqn::P48).divide(4);
+        assert_eq!(fired(&mut bars, 400), [0, 192, 384]);
+
+        let mut saturated = ClockDivider::new(Ppqn::P24).multiply(100);
+        assert_eq!(fired(&mut saturated, 3), [0, 1, 2]);
+    }
+
+    #[test]
+    fn reset_and_position_control_the_phase() {
+        let mut halves = ClockDivider::new(Ppqn::P24).divide(2);
+        halves.set_position(47);
+        assert!(!halves.tick());
+        assert!(halves.tick());
+        assert_eq!(halves.position(), 49);
+        halves.reset();
+        assert!(halves.tick());
+        assert_eq!(Ppqn::P48.beat(72), 1.5);
+        assert_eq!(Ppqn::P96.ticks_per_step(3), Some(32));
+        assert_eq!(Ppqn::P96.ticks_per_step(0), None);
+    }
+}
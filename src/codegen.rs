@@ -0,0 +1,162 @@
+//! Rust source generation for baked pattern tables.
+//!
+//! Firmware that cannot afford to run Bjorklund's algorithm (or lacks a
+//! `const` path for it) can bake every E(k,n) into `static` arrays at build
+//! time instead. [`pattern_table`] writes the tables as Rust source from the
+//! same implementation the crate uses at runtime, ready for a build script:
+//!
+//! ```no_run
+//! // build.rs, with euclidean-rhythm as a build-dependency
+//! use euclidean_rhythm::{TableFormat, pattern_table};
+//!
+//! let out = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//! std::fs::write(out.join("euclidean.rs"), pattern_table(16, TableFormat::Packed)).unwrap();
+//! ```
+//!
+//! and in the firmware: `include!(concat!(env!("OUT_DIR"), "/euclidean.rs"));`
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use crate::euclidean;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// How [`pattern_table`] stores each pattern.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TableFormat {
+    /// One `bool` per step: `EUCLIDEAN_8: [[bool; 8]; 9]`.
+    #[default]
+    Bool,
+    /// One unsigned integer per pattern, bit `i` set for a pulse on step `i`
+    /// (as the `embedded` trigger scheduler takes them), using the smallest
+    /// of `u8`, `u16`, `u32` and `u64` that fits: `EUCLIDEAN_8: [u8; 9]`.
+    /// Supports up to 64 steps.
+    Packed,
+}
+
+/// Generates Rust source declaring one `pub static EUCLIDEAN_<n>` table for
+/// every step count `n` from 1 to `max_steps`, indexed by pulse count, so
+/// `EUCLIDEAN_8[3]` is the tresillo.
+///
+/// # Panics
+/// Panics if `max_steps == 0`, or if `max_steps > 64` with
+/// [`TableFormat::Packed`].
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{TableFormat, pattern_table};
+///
+/// let source = pattern_table(8, TableFormat::Packed);
+/// assert!(source.contains("pub static EUCLIDEAN_8: [u8; 9] = ["));
+/// assert!(source.contains("0x49, // E(3,8)"));
+/// ```
+pub fn pattern_table(max_steps: u8, format: TableFormat) -> String {
+    unwrap_or_panic(try_pattern_table(max_steps, format))
+}
+
+/// Like [`pattern_table`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `max_steps == 0` and
+/// [`Error::TooManySteps`] if `max_steps > 64` with [`TableFormat::Packed`].
+pub fn try_pattern_table(max_steps: u8, format: TableFormat) -> Result<String> {
+    if max_steps == 0 {
+        return Err(Error::ZeroSteps);
+    }
+    if format == TableFormat::Packed && max_steps > 64 {
+        return Err(Error::TooManySteps {
+            steps: usize::from(max_steps),
+            max: 64,
+        });
+    }
+
+    let mut out =
+        String::from("// Euclidean rhythm tables generated by euclidean-rhythm. Do not edit.\n");
+    // Writing to a String cannot fail.
+    for steps in 1..=max_steps {
+        let rows = usize::from(steps) + 1;
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "/// E(k,{steps}) for every pulse count k, indexed by k."
+        );
+        let ty = match format {
+            TableFormat::Bool => format!("[bool; {steps}]"),
+            TableFormat::Packed => String::from(match steps {
+                1..=8 => "u8",
+                9..=16 => "u16",
+                17..=32 => "u32",
+                _ => "u64",
+            }),
+        };
+        let _ = writeln!(out, "pub static EUCLIDEAN_{steps}: [{ty}; {rows}] = [");
+        for pulses in 0..=steps {
+            let pattern = euclidean(steps, pulses, 0);
+            let value = match format {
+                TableFormat::Bool => {
+                    let cells: Vec<&str> = pattern
+                        .iter()
+                        .map(|&pulse| if pulse { "true" } else { "false" })
+                        .collect();
+                    format!("[{}]", cells.join(", "))
+                }
+                TableFormat::Packed => {
+                    let bits = pattern
+                        .iter()
+                        .enumerate()
+                        .fold(0u64, |acc, (i, &pulse)| acc | (u64::from(pulse) << i));
+                    let digits = usize::from(steps).div_ceil(4);
+                    format!("0x{bits:0digits$X}")
+                }
+            };
+            let _ = writeln!(out, "    {value}, // E({pulses},{steps})");
+        }
+        let _ = writeln!(out, "];");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_every_table() {
+        assert_eq!(
+            pattern_table(2, TableFormat::Bool),
+            "// Euclidean rhythm tables generated by euclidean-rhythm. Do not edit.
+
+/// E(k,1) for every pulse count k, indexed by k.
+pub static EUCLIDEAN_1: [[bool; 1]; 2] = [
+    [false], // E(0,1)
+    [true], // E(1,1)
+];
+
+/// E(k,2) for every pulse count k, indexed by k.
+pub static EUCLIDEAN_2: [[bool; 2]; 3] = [
+    [false, false], // E(0,2)
+    [true, false], // E(1,2)
+    [true, true], // E(2,2)
+];
+"
+        );
+        let packed = pattern_table(64, TableFormat::Packed);
+        assert!(packed.contains("pub static EUCLIDEAN_64: [u64; 65] = ["));
+        assert!(packed.contains("    0xFFFFFFFFFFFFFFFF, // E(64,64)\n"));
+        assert!(packed.contains("pub static EUCLIDEAN_17: [u32; 18] = ["));
+    }
+
+    #[test]
+    fn rejects_unsupported_sizes() {
+        assert_eq!(
+            try_pattern_table(0, TableFormat::Bool),
+            Err(Error::ZeroSteps)
+        );
+        assert_eq!(
+            try_pattern_table(65, TableFormat::Packed),
+            Err(Error::TooManySteps { steps: 65, max: 64 })
+        );
+        assert!(try_pattern_table(65, TableFormat::Bool).is_ok());
+    }
+}
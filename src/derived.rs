@@ -0,0 +1,94 @@
+//! Rhythms derived from other rhythms.
+//!
+//! Toussaint's shadow and contour describe a rhythm by its gaps rather than
+//! its onsets. Both treat the pattern as a cycle, so the gap from the last
+//! onset round to the first counts like any other.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Onset indices and the gap from each onset to the next, wrapping round.
+fn gaps(pattern: &[bool]) -> Vec<(usize, usize)> {
+    let onsets: Vec<usize> = (0..pattern.len()).filter(|&i| pattern[i]).collect();
+    (0..onsets.len())
+        .map(|i| {
+            let next = onsets.get(i + 1).map_or(onsets[0] + pattern.len(), |&o| o);
+            (onsets[i], next - onsets[i])
+        })
+        .collect()
+}
+
+/// The shadow of `pattern`: one onset at the midpoint between each pair of
+/// consecutive onsets, including the last and first.
+///
+/// A midpoint that falls between two steps (an odd gap) is placed on the
+/// earlier one. For an exact shadow, double the resolution first with
+/// [`regrid`](fn@crate::regrid). A pattern without onsets has an empty shadow.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string, shadow};
+///
+/// // The son clave's shadow.
+/// let clave = [true, false, false, true, false, false, true, false,
+///              false, false, true, false, true, false, false, false];
+/// assert_eq!(pattern_to_string(&shadow(&clave), 'x', '.'), ".x..x...x..x..x.");
+///
+/// // Euclidean rhythms with even gaps are their own shadows, rotated.
+/// assert_eq!(shadow(&euclidean(8, 4, 0)), euclidean(8, 4, 1));
+/// ```
+pub fn shadow(pattern: &[bool]) -> Vec<bool> {
+    let mut result = vec![false; pattern.len()];
+    for (onset, gap) in gaps(pattern) {
+        result[(onset + gap / 2) % pattern.len()] = true;
+    }
+    result
+}
+
+/// The contour of `pattern`: for each onset, how the gap after the next
+/// onset compares with the gap after this one.
+///
+/// Entry `i` is [`Ordering::Greater`] when the rhythm lengthens at onset
+/// `i + 1`, [`Ordering::Less`] when it shortens and [`Ordering::Equal`] when
+/// it holds, with the last entry comparing the final gap against the first.
+/// Returns one entry per onset.
+///
+/// # Examples
+/// ```
+/// use core::cmp::Ordering::{Equal, Greater, Less};
+/// use euclidean_rhythm::{contour, euclidean};
+///
+/// // Tresillo gaps 3 3 2.
+/// assert_eq!(contour(&euclidean(8, 3, 0)), [Equal, Less, Greater]);
+/// ```
+pub fn contour(pattern: &[bool]) -> Vec<Ordering> {
+    let gaps = gaps(pattern);
+    (0..gaps.len())
+        .map(|i| gaps[(i + 1) % gaps.len()].1.cmp(&gaps[i].1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn shadow_and_contour_are_cyclic() {
+        // Onsets at 1 and 7 of 8: gaps 6 and 2, the second wrapping round.
+        let pattern = [false, true, false, false, false, false, false, true];
+        assert_eq!(
+            shadow(&pattern),
+            [true, false, false, false, true, false, false, false]
+        );
+        assert_eq!(contour(&pattern), [Ordering::Less, Ordering::Greater]);
+
+        let single = euclidean(6, 1, 0);
+        assert_eq!(shadow(&single), euclidean(6, 1, 3));
+        assert_eq!(contour(&single), [Ordering::Equal]);
+        assert_eq!(shadow(&[false; 4]), [false; 4]);
+        assert!(contour(&[false; 4]).is_empty());
+        assert!(shadow(&[]).is_empty());
+    }
+}
@@ -0,0 +1,384 @@
+//! Differences between two versions of a pattern.
+//!
+//! [`diff`] reports which onsets were added and removed and, heuristically,
+//! which of those pairs are really one onset that moved, so an editor can
+//! show "snare nudged one step later" instead of a delete and an insert.
+//!
+//! [`morph_path`] goes the other way: it plans a gradual transition from one
+//! pattern to another, one onset at a time, and [`morph`] picks the pattern
+//! at any point along it.
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use crate::pattern::Pattern;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// An onset that moved from one step to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MovedOnset {
+    /// Step of the onset in the old pattern.
+    pub from: usize,
+    /// Step of the onset in the new pattern.
+    pub to: usize,
+    /// Signed shortest distance around the cycle from `from` to `to`;
+    /// positive means later.
+    pub displacement: isize,
+}
+
+/// The result of [`diff`]. Step indices are sorted in ascending order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PatternDiff {
+    /// Onsets present only in the new pattern and not explained by a move.
+    pub added: Vec<usize>,
+    /// Onsets present only in the old pattern and not explained by a move.
+    pub removed: Vec<usize>,
+    /// Onsets that moved, sorted by `from`.
+    pub moved: Vec<MovedOnset>,
+}
+
+impl PatternDiff {
+    /// Returns `true` if the patterns have the same onsets.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+/// Compares `old` against `new`.
+///
+/// Onsets only in `old` and onsets only in `new` are paired into moves,
+/// closest pairs first, as long as they are at most a quarter of the cycle
+/// apart; the rest are reported as removed and added. Distances wrap around
+/// the cycle. If the lengths differ, the shorter pattern is treated as
+/// padded with rests.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MovedOnset, diff, euclidean};
+///
+/// // Tresillo to the first half of the clave son: the last onset moves.
+/// let changes = diff(&euclidean(8, 3, 0), &[true, false, false, true, false, false, false, true]);
+/// assert!(changes.added.is_empty() && changes.removed.is_empty());
+/// assert_eq!(changes.moved, vec![MovedOnset { from: 6, to: 7, displacement: 1 }]);
+/// ```
+pub fn diff(old: &[bool], new: &[bool]) -> PatternDiff {
+    let len = old.len().max(new.len());
+    let at = |pattern: &[bool], i: usize| pattern.get(i).copied().unwrap_or(false);
+    let mut removed: Vec<usize> = (0..len).filter(|&i| at(old, i) && !at(new, i)).collect();
+    let mut added: Vec<usize> = (0..len).filter(|&i| !at(old, i) && at(new, i)).collect();
+
+    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+    for &from in &removed {
+        for &to in &added {
+            let distance = cyclic_displacement(from, to, len).unsigned_abs();
+            if distance * 4 <= len {
+                candidates.push((distance, from, to));
+            }
+        }
+    }
+    candidates.sort_unstable();
+
+    let mut moved = Vec::new();
+    for (_, from, to) in candidates {
+        if removed.contains(&from) && added.contains(&to) {
+            removed.retain(|&i| i != from);
+            added.retain(|&i| i != to);
+            moved.push(MovedOnset {
+                from,
+                to,
+                displacement: cyclic_displacement(from, to, len),
+            });
+        }
+    }
+    moved.sort_unstable_by_key(|m| m.from);
+
+    PatternDiff {
+        added,
+        removed,
+        moved,
+    }
+}
+
+/// Plans a transition from `from` to `to` that changes exactly one onset per
+/// pattern, for example one per bar.
+///
+/// Onsets shared by both patterns stay put. The others are paired so that
+/// the total distance moved around the cycle is as small as possible; each
+/// pair becomes one move, and any left over are removed or added. Moves come
+/// first, in step order, then removals, then additions. The returned
+/// patterns exclude `from` and end with `to`, so identical patterns give an
+/// empty path.
+///
+/// # Panics
+/// Panics if the patterns have different lengths.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, morph_path};
+///
+/// // Tresillo to E(4,8): one onset slides over, then one is added.
+/// let path = morph_path(&euclidean(8, 3, 0), &euclidean(8, 4, 0));
+/// assert_eq!(path.len(), 2);
+/// assert_eq!(path[0].to_string_with('x', '.'), "x.x...x.");
+/// assert_eq!(path[1].to_string_with('x', '.'), "x.x.x.x.");
+/// ```
+pub fn morph_path(from: &[bool], to: &[bool]) -> Vec<Pattern> {
+    unwrap_or_panic(try_morph_path(from, to))
+}
+
+/// Like [`morph_path`], but reports a length mismatch as an error.
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn try_morph_path(from: &[bool], to: &[bool]) -> Result<Vec<Pattern>> {
+    if from.len() != to.len() {
+        return Err(Error::LengthMismatch {
+            expected: from.len(),
+            actual: to.len(),
+        });
+    }
+    let len = from.len();
+    let removed: Vec<usize> = (0..len).filter(|&i| from[i] && !to[i]).collect();
+    let added: Vec<usize> = (0..len).filter(|&i| !from[i] && to[i]).collect();
+
+    let distance = |a: usize, b: usize| cyclic_displacement(a, b, len).unsigned_abs();
+    let mut pairs: Vec<(usize, usize)> = if removed.len() <= added.len() {
+        min_cost_assignment(removed.len(), added.len(), |r, a| {
+            distance(removed[r], added[a])
+        })
+        .into_iter()
+        .map(|(r, a)| (removed[r], added[a]))
+        .collect()
+    } else {
+        min_cost_assignment(added.len(), removed.len(), |a, r| {
+            distance(removed[r], added[a])
+        })
+        .into_iter()
+        .map(|(a, r)| (removed[r], added[a]))
+        .collect()
+    };
+
+    pairs.sort_unstable();
+    let removals = removed
+        .iter()
+        .filter(|&&r| !pairs.iter().any(|p| p.0 == r))
+        .map(|&r| (r, false));
+    let additions = added
+        .iter()
+        .filter(|&&a| !pairs.iter().any(|p| p.1 == a))
+        .map(|&a| (a, true));
+
+    let mut current = from.to_vec();
+    let mut path = Vec::new();
+    for &(old, new) in &pairs {
+        current[old] = false;
+        current[new] = true;
+        path.push(Pattern::from(current.as_slice()));
+    }
+    for (step, state) in removals.chain(additions) {
+        current[step] = state;
+        path.push(Pattern::from(current.as_slice()));
+    }
+    Ok(path)
+}
+
+/// The pattern a fraction `t` of the way along [`morph_path`] from `from`
+/// to `to`: `from` at 0, `to` at 1, and the nearest step of the path in
+/// between. `t` is clamped to 0-1, and NaN counts as 0. Sweeping `t` over a
+/// set gives a smooth density transition instead of an abrupt switch.
+///
+/// # Panics
+/// Panics if the patterns have different lengths.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, morph};
+///
+/// let (sparse, dense) = (euclidean(16, 3, 0), euclidean(16, 9, 0));
+/// assert_eq!(morph(&sparse, &dense, 0.0).pulse_count(), 3);
+/// assert_eq!(morph(&sparse, &dense, 0.5).pulse_count(), 6);
+/// assert_eq!(morph(&sparse, &dense, 1.0).as_slice(), dense);
+/// ```
+pub fn morph(from: &[bool], to: &[bool], t: f32) -> Pattern {
+    unwrap_or_panic(try_morph(from, to, t))
+}
+
+/// Like [`morph`], but reports a length mismatch as an error.
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn try_morph(from: &[bool], to: &[bool], t: f32) -> Result<Pattern> {
+    let mut path = try_morph_path(from, to)?;
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    // Rounds to the nearest step of the path; `from` is step 0.
+    let step = (t * path.len() as f32 + 0.5) as usize;
+    Ok(match step {
+        0 => Pattern::from(from),
+        _ => path.swap_remove(step.min(path.len()) - 1),
+    })
+}
+
+/// Pairs each of `rows` items with a distinct one of `cols >= rows` items,
+/// minimizing the summed `cost`, using the Hungarian algorithm. Returns
+/// `(row, col)` pairs.
+fn min_cost_assignment(
+    rows: usize,
+    cols: usize,
+    cost: impl Fn(usize, usize) -> usize,
+) -> Vec<(usize, usize)> {
+    // Potentials and matching are 1-based; index 0 is a sentinel.
+    let mut u = vec![0i64; rows + 1];
+    let mut v = vec![0i64; cols + 1];
+    let mut row_of = vec![0usize; cols + 1];
+    let mut way = vec![0usize; cols + 1];
+    for row in 1..=rows {
+        row_of[0] = row;
+        let mut col = 0;
+        let mut min_slack = vec![i64::MAX; cols + 1];
+        let mut used = vec![false; cols + 1];
+        loop {
+            used[col] = true;
+            let r = row_of[col];
+            let (mut delta, mut next) = (i64::MAX, 0);
+            for c in 1..=cols {
+                if used[c] {
+                    continue;
+                }
+                let slack = cost(r - 1, c - 1) as i64 - u[r] - v[c];
+                if slack < min_slack[c] {
+                    min_slack[c] = slack;
+                    way[c] = col;
+                }
+                if min_slack[c] < delta {
+                    delta = min_slack[c];
+                    next = c;
+                }
+            }
+            for c in 0..=cols {
+                if used[c] {
+                    u[row_of[c]] += delta;
+                    v[c] -= delta;
+                } else {
+                    min_slack[c] -= delta;
+                }
+            }
+            col = next;
+            if row_of[col] == 0 {
+                break;
+            }
+        }
+        while col != 0 {
+            let prev = way[col];
+            row_of[col] = row_of[prev];
+            col = prev;
+        }
+    }
+    (1..=cols)
+        .filter(|&c| row_of[c] != 0)
+        .map(|c| (row_of[c] - 1, c - 1))
+        .collect()
+}
+
+/// Signed shortest step from `from` to `to` on a cycle of `len` steps.
+fn cyclic_displacement(from: usize, to: usize, len: usize) -> isize {
+    let forward = (to + len - from) % len;
+    if forward * 2 > len {
+        forward as isize - len as isize
+    } else {
+        forward as isize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{euclidean, rotate_pattern};
+
+    #[test]
+    fn identical_patterns_have_no_changes() {
+        let pattern = euclidean(16, 5, 0);
+        assert!(diff(&pattern, &pattern).is_empty());
+    }
+
+    #[test]
+    fn reports_moves_across_the_loop_point() {
+        let old = euclidean(8, 3, 0);
+        let new = rotate_pattern(&old, 1);
+        let changes = diff(&old, &new);
+        // x..x..x. -> ..x..x.x: every onset moves one step earlier.
+        assert_eq!(changes.moved.len(), 3);
+        assert!(changes.moved.iter().all(|m| m.displacement == -1));
+        assert_eq!(
+            changes.moved[0],
+            MovedOnset {
+                from: 0,
+                to: 7,
+                displacement: -1
+            }
+        );
+    }
+
+    #[test]
+    fn morph_walks_the_path() {
+        let (from, to) = (euclidean(12, 2, 0), euclidean(12, 7, 1));
+        let path = morph_path(&from, &to);
+        let mut previous = 0;
+        for i in 0..=20 {
+            let pattern = morph(&from, &to, i as f32 / 20.0);
+            let index = path.iter().position(|p| *p == pattern).map_or(0, |i| i + 1);
+            assert!(index >= previous);
+            previous = index;
+        }
+        assert_eq!(previous, path.len());
+        assert_eq!(morph(&from, &to, f32::NAN).as_slice(), from);
+        assert_eq!(morph(&from, &to, 7.0).as_slice(), to);
+        assert!(try_morph(&from, &to[1..], 0.5).is_err());
+    }
+
+    #[test]
+    fn morph_path_minimizes_displacement() {
+        // x.x.x... -> ...x.x.x: three one-step moves (0 wraps round to 7)
+        // beat shifting every onset three steps later.
+        let from = [true, false, true, false, true, false, false, false];
+        let to = [false, false, false, true, false, true, false, true];
+        let path = morph_path(&from, &to);
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.last().map(Pattern::as_slice), Some(&to[..]));
+        for (before, after) in core::iter::once(&from[..])
+            .chain(path.iter().map(Pattern::as_slice))
+            .zip(path.iter().map(Pattern::as_slice))
+        {
+            let changes = diff(before, after);
+            assert_eq!(
+                changes.moved.len() + changes.added.len() + changes.removed.len(),
+                1
+            );
+        }
+
+        assert!(morph_path(&from, &from).is_empty());
+        assert_eq!(
+            try_morph_path(&from, &[true]),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn far_changes_are_adds_and_removes() {
+        // Different lengths: the old pattern is padded with a rest.
+        let old = [true, true, false, false, false, false, false, false];
+        let new = [false, false, false, false, true, false, false, false, true];
+        let changes = diff(&old, &new);
+        assert_eq!(changes.removed, vec![1]);
+        assert_eq!(changes.added, vec![4]);
+        assert_eq!(
+            changes.moved,
+            vec![MovedOnset {
+                from: 0,
+                to: 8,
+                displacement: -1
+            }]
+        );
+    }
+}
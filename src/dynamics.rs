@@ -0,0 +1,109 @@
+//! Velocity layers locked to rhythm onsets.
+//!
+//! [`velocity_contour`] gives every pulse of a pattern a MIDI velocity, so a
+//! plain on/off rhythm plays with accents and dynamics.
+
+use std::f32::consts::TAU;
+
+/// Velocity of an accented onset, and the top of every contour.
+const ACCENT: u8 = 127;
+/// Velocity of an unaccented onset.
+const NORMAL: u8 = 80;
+/// The bottom of [`VelocityContour::Decay`] and [`VelocityContour::Sine`].
+const SOFT: u8 = 40;
+
+/// The dynamic shape [`velocity_contour`] applies across a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VelocityContour {
+    /// The first onset is accented (127), the rest play at 80.
+    AccentFirst,
+    /// Falls evenly from 127 on the first onset to 40 on the last.
+    Decay,
+    /// One cosine cycle over the pattern: 127 on the downbeat, 40 halfway
+    /// through, rising again towards the end. Follows step position, not
+    /// onset order.
+    Sine,
+    /// This many onsets are accented (127), spread evenly over the onsets as
+    /// a Euclidean rhythm starting on the first; the rest play at 80.
+    EuclideanAccents(usize),
+}
+
+/// Assigns a velocity to every onset of `pattern` following `contour`.
+///
+/// Returns `(step, velocity)` pairs for every pulse, in step order.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{VelocityContour, euclidean, velocity_contour};
+///
+/// // Accent 2 of the 5 onsets of x.xx.xx.
+/// let hits = velocity_contour(&euclidean(8, 5, 0), VelocityContour::EuclideanAccents(2));
+/// assert_eq!(hits, vec![(0, 127), (2, 80), (3, 80), (5, 127), (6, 80)]);
+/// ```
+pub fn velocity_contour(pattern: &[bool], contour: VelocityContour) -> Vec<(usize, u8)> {
+    let onsets: Vec<usize> = (0..pattern.len()).filter(|&i| pattern[i]).collect();
+    let count = onsets.len();
+    let between = |t: f32| SOFT + (f32::from(ACCENT - SOFT) * t).round() as u8;
+    onsets
+        .iter()
+        .enumerate()
+        .map(|(i, &step)| {
+            let velocity = match contour {
+                VelocityContour::AccentFirst if i == 0 => ACCENT,
+                VelocityContour::AccentFirst => NORMAL,
+                VelocityContour::Decay if count == 1 => ACCENT,
+                VelocityContour::Decay => between(1.0 - i as f32 / (count - 1) as f32),
+                VelocityContour::Sine => {
+                    let phase = step as f32 / pattern.len() as f32;
+                    between(0.5 + 0.5 * (TAU * phase).cos())
+                }
+                VelocityContour::EuclideanAccents(accents) => {
+                    // Onset i is accented when i * k / n crosses a whole
+                    // number, which spreads k accents as E(k, n).
+                    let accents = accents.min(count);
+                    if i * accents % count < accents {
+                        ACCENT
+                    } else {
+                        NORMAL
+                    }
+                }
+            };
+            (step, velocity)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    fn velocities(pattern: &[bool], contour: VelocityContour) -> Vec<u8> {
+        velocity_contour(pattern, contour)
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    #[test]
+    fn contours_shape_the_onsets() {
+        let pattern = euclidean(8, 4, 0);
+        assert_eq!(
+            velocities(&pattern, VelocityContour::AccentFirst),
+            [127, 80, 80, 80]
+        );
+        assert_eq!(
+            velocities(&pattern, VelocityContour::Decay),
+            [127, 98, 69, 40]
+        );
+        let sine = velocities(&pattern, VelocityContour::Sine);
+        assert_eq!((sine[0], sine[2]), (127, 40));
+        assert!(sine[1].abs_diff(sine[3]) <= 1);
+        assert_eq!(
+            velocities(&pattern, VelocityContour::EuclideanAccents(9)),
+            [127; 4]
+        );
+        assert_eq!(velocities(&[false, true], VelocityContour::Decay), [127]);
+        assert!(velocity_contour(&[false; 4], VelocityContour::EuclideanAccents(1)).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+//! Interactive pattern editing with undo and redo.
+//!
+//! A [`PatternEditor`] holds the pattern a user is working on and records a
+//! snapshot before every edit. Each edit returns the [`EditEvent`] it caused,
+//! so a frontend can redraw or notify collaborators without diffing.
+
+use crate::error::{Result, unwrap_or_panic};
+use crate::pattern::Pattern;
+use crate::try_euclidean;
+use alloc::vec::Vec;
+
+/// What changed in a [`PatternEditor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditEvent {
+    /// A single step was flipped.
+    StepToggled {
+        /// Index of the step.
+        step: usize,
+        /// New state of the step.
+        is_pulse: bool,
+    },
+    /// The pattern was regenerated from Euclidean parameters.
+    ParamsChanged {
+        /// New step count.
+        steps: u8,
+        /// New pulse count.
+        pulses: u8,
+        /// New rotation.
+        rotation: u8,
+    },
+    /// The last edit was undone.
+    Undone,
+    /// The last undone edit was redone.
+    Redone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    pattern: Pattern,
+    params: Option<(u8, u8, u8)>,
+}
+
+/// A pattern with an undo/redo history.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{EditEvent, PatternEditor};
+///
+/// let mut editor = PatternEditor::euclidean(8, 3, 0);
+/// assert_eq!(
+///     editor.toggle_step(1),
+///     Some(EditEvent::StepToggled { step: 1, is_pulse: true })
+/// );
+/// assert_eq!(editor.pattern().to_string_with('x', '.'), "xx.x..x.");
+///
+/// editor.undo();
+/// assert_eq!(editor.pattern().to_string_with('x', '.'), "x..x..x.");
+/// editor.redo();
+/// assert!(editor.pattern()[1]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternEditor {
+    current: Snapshot,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    history_limit: usize,
+}
+
+impl PatternEditor {
+    /// Starts editing `pattern` with an empty history of up to 100 edits.
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            current: Snapshot {
+                pattern,
+                params: None,
+            },
+            undo: Vec::new(),
+            redo: Vec::new(),
+            history_limit: 100,
+        }
+    }
+
+    /// Starts editing E(`pulses`, `steps`) rotated by `rotation`.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `pulses > steps`.
+    pub fn euclidean(steps: u8, pulses: u8, rotation: u8) -> Self {
+        let mut editor = Self::new(Pattern::default());
+        editor.current = unwrap_or_panic(snapshot(steps, pulses, rotation));
+        editor
+    }
+
+    /// Sets how many edits can be undone; older ones are forgotten.
+    #[must_use]
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self.trim();
+        self
+    }
+
+    /// The pattern being edited.
+    pub fn pattern(&self) -> &Pattern {
+        &self.current.pattern
+    }
+
+    /// The Euclidean parameters the pattern was generated from, or `None`
+    /// once individual steps have been edited.
+    pub fn params(&self) -> Option<(u8, u8, u8)> {
+        self.current.params
+    }
+
+    /// Flips step `step` between pulse and rest.
+    ///
+    /// Returns `None`, recording nothing, if `step` is out of range.
+    pub fn toggle_step(&mut self, step: usize) -> Option<EditEvent> {
+        let is_pulse = !*self.current.pattern.get(step)?;
+        let mut steps = self.current.pattern.as_slice().to_vec();
+        steps[step] = is_pulse;
+        self.commit(Snapshot {
+            pattern: Pattern::new(steps),
+            params: None,
+        });
+        Some(EditEvent::StepToggled { step, is_pulse })
+    }
+
+    /// Replaces the pattern with E(`pulses`, `steps`) rotated by `rotation`.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `pulses > steps`.
+    pub fn set_params(&mut self, steps: u8, pulses: u8, rotation: u8) -> EditEvent {
+        unwrap_or_panic(self.try_set_params(steps, pulses, rotation))
+    }
+
+    /// Like [`set_params`](Self::set_params), but reports invalid parameters
+    /// as an error and records nothing.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `pulses > steps`.
+    pub fn try_set_params(&mut self, steps: u8, pulses: u8, rotation: u8) -> Result<EditEvent> {
+        self.commit(snapshot(steps, pulses, rotation)?);
+        Ok(EditEvent::ParamsChanged {
+            steps,
+            pulses,
+            rotation,
+        })
+    }
+
+    /// Reverts the most recent edit. Returns `None` if there is nothing to
+    /// undo.
+    pub fn undo(&mut self) -> Option<EditEvent> {
+        let previous = self.undo.pop()?;
+        self.redo
+            .push(core::mem::replace(&mut self.current, previous));
+        Some(EditEvent::Undone)
+    }
+
+    /// Reapplies the most recently undone edit. Returns `None` if there is
+    /// nothing to redo; any new edit clears the redo history.
+    pub fn redo(&mut self) -> Option<EditEvent> {
+        let next = self.redo.pop()?;
+        self.undo.push(core::mem::replace(&mut self.current, next));
+        Some(EditEvent::Redone)
+    }
+
+    /// Returns `true` if [`undo`](Self::undo) would change the pattern.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns `true` if [`redo`](Self::redo) would change the pattern.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    fn commit(&mut self, next: Snapshot) {
+        self.undo.push(core::mem::replace(&mut self.current, next));
+        self.redo.clear();
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        if self.undo.len() > self.history_limit {
+            let excess = self.undo.len() - self.history_limit;
+            self.undo.drain(..excess);
+        }
+    }
+}
+
+fn snapshot(steps: u8, pulses: u8, rotation: u8) -> Result<Snapshot> {
+    Ok(Snapshot {
+        pattern: Pattern::new(try_euclidean(steps, pulses, rotation)?),
+        params: Some((steps, pulses, rotation)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn undo_redo_round_trip() {
+        let mut editor = PatternEditor::euclidean(8, 3, 0);
+        let original = editor.pattern().clone();
+        assert_eq!(editor.params(), Some((8, 3, 0)));
+
+        editor.toggle_step(0);
+        assert_eq!(editor.params(), None);
+        editor.set_params(16, 5, 2);
+        assert_eq!(editor.pattern().len(), 16);
+
+        assert_eq!(editor.undo(), Some(EditEvent::Undone));
+        assert_eq!(editor.undo(), Some(EditEvent::Undone));
+        assert_eq!(editor.pattern(), &original);
+        assert_eq!(editor.undo(), None);
+
+        assert_eq!(editor.redo(), Some(EditEvent::Redone));
+        editor.toggle_step(3);
+        assert!(!editor.can_redo());
+        assert_eq!(editor.toggle_step(8), None);
+    }
+
+    #[test]
+    fn history_limit_and_invalid_params() {
+        let mut editor = PatternEditor::euclidean(4, 1, 0).with_history_limit(2);
+        for step in 0..4 {
+            editor.toggle_step(step);
+        }
+        assert!(editor.undo().is_some() && editor.undo().is_some());
+        assert!(!editor.can_undo());
+
+        let before = editor.clone();
+        assert_eq!(
+            editor.try_set_params(4, 5, 0),
+            Err(Error::PulsesExceedSteps {
+                pulses: 5,
+                steps: 4
+            })
+        );
+        assert_eq!(editor, before);
+    }
+}
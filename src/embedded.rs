@@ -0,0 +1,251 @@
+//! Trigger scheduling for firmware (enabled with the `embedded` feature).
+//!
+//! A [`TriggerScheduler`] is called from a periodic hardware timer interrupt
+//! and answers one question per tick: which trigger outputs fire now? The
+//! answer is a bitmask (bit `n` = channel `n`) that can be written straight to
+//! a GPIO port. Timing uses integer arithmetic, so there is no drift between
+//! the timer rate and the tempo however long the patch runs. Nothing here
+//! allocates, so the scheduler can be set up and reprogrammed from interrupt
+//! context.
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use crate::try_euclidean_u64;
+
+/// Per-tick trigger scheduler for up to `N` (≤ 32) channels of up to 64 steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::embedded::TriggerScheduler;
+///
+/// // 1 kHz timer, 120 BPM sixteenths: one step every 125 ticks.
+/// let mut sched: TriggerScheduler<4> = TriggerScheduler::new(1000, 120);
+/// sched.set_channel(0, 8, 3, 0);
+/// sched.set_channel(1, 8, 8, 0);
+///
+/// assert_eq!(sched.tick(), 0b11); // step 0: both channels fire
+/// for _ in 1..125 {
+///     assert_eq!(sched.tick(), 0); // between steps
+/// }
+/// assert_eq!(sched.tick(), 0b10); // step 1: only channel 1
+/// ```
+#[derive(Debug, Clone)]
+pub struct TriggerScheduler<const N: usize> {
+    patterns: [u64; N],
+    lengths: [u8; N],
+    positions: [u8; N],
+    tick_rate_hz: u32,
+    bpm: u32,
+    steps_per_beat: u32,
+    /// Accumulates `bpm * steps_per_beat` per tick; a step is due whenever it
+    /// reaches `tick_rate_hz * 60`.
+    phase: u64,
+    trigger_ticks: u32,
+    gate_remaining: [u32; N],
+}
+
+impl<const N: usize> TriggerScheduler<N> {
+    /// Creates a scheduler for a timer firing `tick_rate_hz` times per second,
+    /// at `bpm` with sixteenth-note steps and 1-tick triggers. All channels
+    /// start empty.
+    ///
+    /// # Panics
+    /// Panics if `N > 32` or `tick_rate_hz == 0`.
+    pub fn new(tick_rate_hz: u32, bpm: u32) -> Self {
+        if N > 32 {
+            panic!("at most 32 channels fit the trigger mask");
+        }
+        if tick_rate_hz == 0 {
+            panic!("tick_rate_hz == 0");
+        }
+        Self {
+            patterns: [0; N],
+            lengths: [1; N],
+            positions: [0; N],
+            tick_rate_hz,
+            bpm,
+            steps_per_beat: 4,
+            phase: Self::step_threshold(tick_rate_hz),
+            trigger_ticks: 1,
+            gate_remaining: [0; N],
+        }
+    }
+
+    fn step_threshold(tick_rate_hz: u32) -> u64 {
+        u64::from(tick_rate_hz) * 60
+    }
+
+    /// Loads E(`pulses`, `steps`) rotated by `rotation` into a channel.
+    /// Ignored if `channel >= N`.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0`, `steps > 64` or `pulses > steps`.
+    pub fn set_channel(&mut self, channel: usize, steps: u8, pulses: u8, rotation: u8) {
+        unwrap_or_panic(self.try_set_channel(channel, steps, pulses, rotation));
+    }
+
+    /// Like [`set_channel`](Self::set_channel), but reports invalid
+    /// parameters as an error and leaves the channel unchanged.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0`, `steps > 64` or `pulses > steps`.
+    pub fn try_set_channel(
+        &mut self,
+        channel: usize,
+        steps: u8,
+        pulses: u8,
+        rotation: u8,
+    ) -> Result<()> {
+        let bits = try_euclidean_u64(steps, pulses, rotation)?;
+        self.try_set_channel_bits(channel, bits, steps)
+    }
+
+    /// Loads a raw pattern (bit `i` = step `i`) of `steps` steps into a
+    /// channel. Ignored if `channel >= N`.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `steps > 64`.
+    pub fn set_channel_bits(&mut self, channel: usize, bits: u64, steps: u8) {
+        unwrap_or_panic(self.try_set_channel_bits(channel, bits, steps));
+    }
+
+    /// Like [`set_channel_bits`](Self::set_channel_bits), but reports an
+    /// invalid length as an error.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `steps > 64`.
+    pub fn try_set_channel_bits(&mut self, channel: usize, bits: u64, steps: u8) -> Result<()> {
+        check_steps(steps)?;
+        if channel < N {
+            self.patterns[channel] = bits;
+            self.lengths[channel] = steps;
+            self.positions[channel] %= steps;
+        }
+        Ok(())
+    }
+
+    /// Changes the tempo; takes effect from the next tick.
+    pub fn set_bpm(&mut self, bpm: u32) {
+        self.bpm = bpm;
+    }
+
+    /// Sets the number of steps per beat (4 = sixteenths).
+    pub fn set_steps_per_beat(&mut self, steps_per_beat: u32) {
+        self.steps_per_beat = steps_per_beat.max(1);
+    }
+
+    /// Sets how many ticks an output stays high after firing (at least 1).
+    pub fn set_trigger_ticks(&mut self, ticks: u32) {
+        self.trigger_ticks = ticks.max(1);
+    }
+
+    /// Restarts all channels at step 0; the next tick fires it.
+    pub fn reset(&mut self) {
+        self.positions = [0; N];
+        self.phase = Self::step_threshold(self.tick_rate_hz);
+        self.gate_remaining = [0; N];
+    }
+
+    /// Advances one timer tick and returns the mask of channels whose trigger
+    /// starts on this tick.
+    pub fn tick(&mut self) -> u32 {
+        for remaining in &mut self.gate_remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
+
+        let threshold = Self::step_threshold(self.tick_rate_hz);
+        if self.phase < threshold {
+            self.phase += u64::from(self.bpm) * u64::from(self.steps_per_beat);
+            if self.phase < threshold {
+                return 0;
+            }
+        }
+        self.phase -= threshold;
+
+        let mut mask = 0;
+        for channel in 0..N {
+            let step = self.positions[channel];
+            if self.patterns[channel] >> step & 1 == 1 {
+                mask |= 1 << channel;
+                self.gate_remaining[channel] = self.trigger_ticks;
+            }
+            self.positions[channel] = (step + 1) % self.lengths[channel];
+        }
+        mask
+    }
+
+    /// Mask of channels that should currently be held high, given the
+    /// configured trigger length. Call after [`tick`](Self::tick).
+    pub fn outputs_high(&self) -> u32 {
+        (0..N)
+            .filter(|&c| self.gate_remaining[c] > 0)
+            .fold(0, |mask, c| mask | (1 << c))
+    }
+}
+
+/// Checks that a channel length fits the 64-bit pattern word.
+fn check_steps(steps: u8) -> Result<()> {
+    match steps {
+        0 => Err(Error::ZeroSteps),
+        1..=64 => Ok(()),
+        _ => Err(Error::TooManySteps {
+            steps: usize::from(steps),
+            max: 64,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_drift_with_uneven_rates() {
+        // 1 kHz at 97 BPM: 1000 * 60 / (97 * 4) = 154.6... ticks per step.
+        let mut sched: TriggerScheduler<1> = TriggerScheduler::new(1000, 97);
+        sched.set_channel(0, 1, 1, 0);
+        let fired = (0..60_000).filter(|_| sched.tick() != 0).count();
+        // One minute at 97 BPM sixteenths is exactly 388 steps.
+        assert_eq!(fired, 388);
+    }
+
+    #[test]
+    fn trigger_width_and_wrapping() {
+        let mut sched: TriggerScheduler<2> = TriggerScheduler::new(100, 150);
+        // 100 * 60 / (150 * 4) = 10 ticks per step.
+        sched.set_channel(0, 3, 1, 0);
+        sched.set_trigger_ticks(3);
+        assert_eq!(sched.tick(), 1);
+        let high: Vec<u32> = (1..4)
+            .map(|_| {
+                sched.tick();
+                sched.outputs_high()
+            })
+            .collect();
+        // High for ticks 0-2, low again on tick 3.
+        assert_eq!(high, vec![1, 1, 0]);
+
+        // Ticks 4..70 cover steps 1-6; the 3-step pattern fires on steps 3 and 6.
+        let fired = (4..70).filter(|_| sched.tick() != 0).count();
+        assert_eq!(fired, 2);
+        sched.reset();
+        assert_eq!(sched.tick(), 1);
+    }
+
+    #[test]
+    fn try_setters_reject_bad_lengths() {
+        let mut sched: TriggerScheduler<1> = TriggerScheduler::new(1000, 120);
+        assert_eq!(
+            sched.try_set_channel(0, 65, 1, 0),
+            Err(Error::TooManySteps { steps: 65, max: 64 })
+        );
+        assert_eq!(sched.try_set_channel_bits(0, 1, 0), Err(Error::ZeroSteps));
+        assert_eq!(sched.try_set_channel(0, 8, 3, 0), Ok(()));
+        assert_eq!(
+            sched.try_set_channel(0, 8, 9, 0),
+            Err(Error::PulsesExceedSteps {
+                pulses: 9,
+                steps: 8
+            })
+        );
+    }
+}
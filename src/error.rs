@@ -0,0 +1,138 @@
+//! The crate-wide error type.
+//!
+//! Every fallible operation has a `try_` form (or returns [`Result`] directly)
+//! that reports invalid input as an [`Error`]. The panicking forms are thin
+//! wrappers that panic with the error's message.
+
+use core::fmt;
+
+/// The longest pattern built from text or file input, such as E(k,n)
+/// notation, run notation, Strudel or a MIDI file, so that a short input
+/// asking for a huge pattern is an error instead of exhausting memory.
+pub const MAX_INPUT_STEPS: usize = 1 << 20;
+
+/// Errors reported by the crate's fallible operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A pattern, scale or meter was requested with zero steps.
+    ZeroSteps,
+    /// More pulses were requested than there are steps.
+    PulsesExceedSteps {
+        /// Requested pulses.
+        pulses: usize,
+        /// Available steps.
+        steps: usize,
+    },
+    /// The step count exceeds what the operation supports.
+    TooManySteps {
+        /// Requested steps.
+        steps: usize,
+        /// Largest supported step count.
+        max: usize,
+    },
+    /// Two patterns that must have equal lengths do not.
+    LengthMismatch {
+        /// Length of the first pattern.
+        expected: usize,
+        /// Length of the second pattern.
+        actual: usize,
+    },
+    /// A character in a rhythm string is neither a pulse nor a rest symbol.
+    InvalidSymbol {
+        /// The offending character.
+        symbol: char,
+        /// Its character index in the input.
+        position: usize,
+    },
+    /// Any other argument outside its valid range.
+    InvalidParameter {
+        /// Name of the argument.
+        name: &'static str,
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+}
+
+/// `Result` with the crate's [`Error`] as the default error type.
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ZeroSteps => f.write_str("steps must be greater than zero"),
+            Error::PulsesExceedSteps { pulses, steps } => {
+                write!(f, "pulses ({pulses}) must not exceed steps ({steps})")
+            }
+            Error::TooManySteps { steps, max } => {
+                write!(f, "{steps} steps exceeds the maximum of {max}")
+            }
+            Error::LengthMismatch { expected, actual } => {
+                write!(f, "pattern lengths differ ({expected} vs {actual})")
+            }
+            Error::InvalidSymbol { symbol, position } => {
+                write!(f, "invalid rhythm symbol {symbol:?} at position {position}")
+            }
+            Error::InvalidParameter { name, reason } => write!(f, "invalid {name}: {reason}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Validates Euclidean parameters.
+pub(crate) fn check_params(steps: usize, pulses: usize) -> Result<()> {
+    if steps == 0 {
+        return Err(Error::ZeroSteps);
+    }
+    if pulses > steps {
+        return Err(Error::PulsesExceedSteps { pulses, steps });
+    }
+    Ok(())
+}
+
+/// Checks a step count read from input against [`MAX_INPUT_STEPS`].
+pub(crate) fn check_input_steps(steps: usize) -> Result<()> {
+    if steps > MAX_INPUT_STEPS {
+        return Err(Error::TooManySteps {
+            steps,
+            max: MAX_INPUT_STEPS,
+        });
+    }
+    Ok(())
+}
+
+/// Unwraps a result from a `try_` function, panicking with the error message.
+pub(crate) fn unwrap_or_panic<T>(result: Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            Error::PulsesExceedSteps {
+                pulses: 9,
+                steps: 8
+            }
+            .to_string(),
+            "pulses (9) must not exceed steps (8)"
+        );
+        assert_eq!(
+            Error::InvalidSymbol {
+                symbol: '?',
+                position: 3
+            }
+            .to_string(),
+            "invalid rhythm symbol '?' at position 3"
+        );
+        assert_eq!(check_params(0, 0), Err(Error::ZeroSteps));
+        assert_eq!(check_params(4, 4), Ok(()));
+    }
+}
@@ -0,0 +1,175 @@
+//! Fitting Euclidean parameters to an observed pattern.
+//!
+//! Patterns quantized from a performance or a recording are rarely exactly
+//! Euclidean. [`fit_euclidean`] finds the E(k,n,r) of the same length that
+//! needs the fewest step changes to match. To only recognize patterns that
+//! are exactly Euclidean, [`detect_euclidean`](crate::detect_euclidean) is
+//! cheaper.
+
+use crate::euclidean;
+
+/// The best Euclidean match for a pattern, as found by [`fit_euclidean`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EuclideanFit {
+    /// Step count; always the length of the fitted pattern.
+    pub steps: u8,
+    /// Pulse count.
+    pub pulses: u8,
+    /// Rotation, as passed to [`euclidean`].
+    pub rotation: u8,
+    /// Number of steps where the pattern and `euclidean(steps, pulses,
+    /// rotation)` differ; 0 for an exact match.
+    pub distance: usize,
+}
+
+impl EuclideanFit {
+    /// The fitted rhythm.
+    pub fn pattern(&self) -> Vec<bool> {
+        euclidean(self.steps, self.pulses, self.rotation)
+    }
+}
+
+/// Finds the Euclidean rhythm closest to `pattern` in Hamming distance.
+///
+/// Ties go to the pulse count closest to the pattern's own, then to the
+/// smallest rotation. Returns `None` for an empty pattern or one longer than
+/// 255 steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::fit_euclidean;
+///
+/// // A tresillo with one ghost note: x..x.xx.
+/// let played = [true, false, false, true, false, true, true, false];
+/// let fit = fit_euclidean(&played).unwrap();
+/// assert_eq!((fit.steps, fit.pulses, fit.rotation, fit.distance), (8, 3, 0, 1));
+/// ```
+pub fn fit_euclidean(pattern: &[bool]) -> Option<EuclideanFit> {
+    let steps = u8::try_from(pattern.len()).ok().filter(|&s| s > 0)?;
+    let onsets = pattern.iter().filter(|&&p| p).count();
+    let mut best: Option<(usize, usize, EuclideanFit)> = None;
+    for pulses in 0..=steps {
+        let base = euclidean(steps, pulses, 0);
+        for rotation in 0..steps {
+            let r = usize::from(rotation);
+            let candidate = base[r..].iter().chain(&base[..r]);
+            let distance = candidate.zip(pattern).filter(|(a, b)| a != b).count();
+            let key = (distance, usize::from(pulses).abs_diff(onsets));
+            if best.is_none_or(|(d, p, _)| key < (d, p)) {
+                let fit = EuclideanFit {
+                    steps,
+                    pulses,
+                    rotation,
+                    distance,
+                };
+                best = Some((key.0, key.1, fit));
+            }
+        }
+    }
+    best.map(|(_, _, fit)| fit)
+}
+
+/// Lists the Euclidean rhythms one edit away from `pattern`: a step toggled
+/// (distance 1) or an onset moved to another step (distance 2, same pulse
+/// count).
+///
+/// Each distinct rhythm appears once, with its smallest rotation. The
+/// pattern itself is never included. Results are ordered by distance, then
+/// pulse count, then rotation. Returns an empty list for an empty pattern or
+/// one longer than 255 steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean_neighbors, pattern_to_string};
+///
+/// // A tresillo with one ghost note: x..x.xx.
+/// let played = [true, false, false, true, false, true, true, false];
+/// let found: Vec<String> = euclidean_neighbors(&played)
+///     .iter()
+///     .map(|fit| pattern_to_string(&fit.pattern(), 'x', '.'))
+///     .collect();
+/// assert_eq!(found, ["x..x..x.", "x..x.x..", "x.xx.xx.", "xx.x.xx."]);
+/// ```
+pub fn euclidean_neighbors(pattern: &[bool]) -> Vec<EuclideanFit> {
+    let Some(steps) = u8::try_from(pattern.len()).ok().filter(|&s| s > 0) else {
+        return Vec::new();
+    };
+    let onsets = pattern.iter().filter(|&&p| p).count() as u8;
+    let mut seen: Vec<Vec<bool>> = Vec::new();
+    let mut neighbors = Vec::new();
+    for pulses in onsets.saturating_sub(1)..=onsets.saturating_add(1).min(steps) {
+        for rotation in 0..steps {
+            let candidate = euclidean(steps, pulses, rotation);
+            let distance = candidate
+                .iter()
+                .zip(pattern)
+                .filter(|(a, b)| a != b)
+                .count();
+            let allowed = if pulses == onsets { 2 } else { 1 };
+            if distance == 0 || distance > allowed || seen.contains(&candidate) {
+                continue;
+            }
+            seen.push(candidate);
+            neighbors.push(EuclideanFit {
+                steps,
+                pulses,
+                rotation,
+                distance,
+            });
+        }
+    }
+    neighbors.sort_by_key(|fit| (fit.distance, fit.pulses, fit.rotation));
+    neighbors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_patterns_fit_themselves() {
+        for (steps, pulses, rotation) in [(8, 3, 2), (16, 5, 0), (12, 7, 11), (4, 0, 0)] {
+            let pattern = euclidean(steps, pulses, rotation);
+            let fit = fit_euclidean(&pattern).unwrap();
+            assert_eq!(fit.distance, 0);
+            assert_eq!(fit.pattern(), pattern);
+        }
+    }
+
+    #[test]
+    fn neighbors_are_one_edit_away() {
+        let tresillo = euclidean(8, 3, 0);
+        let neighbors = euclidean_neighbors(&tresillo);
+        assert!(!neighbors.is_empty());
+        for fit in &neighbors {
+            let changed = fit
+                .pattern()
+                .iter()
+                .zip(&tresillo)
+                .filter(|(a, b)| a != b)
+                .count();
+            assert_eq!(changed, fit.distance);
+            assert!(fit.distance == 1 || (fit.distance == 2 && fit.pulses == 3));
+        }
+        // Moving the last onset one step earlier gives x..x.x.., another
+        // rotation of the tresillo.
+        let moved = [true, false, false, true, false, true, false, false];
+        assert!(neighbors.iter().any(|fit| fit.pattern() == moved));
+        assert!(euclidean_neighbors(&[]).is_empty());
+        assert_eq!(
+            euclidean_neighbors(&[true]),
+            [EuclideanFit {
+                steps: 1,
+                pulses: 0,
+                rotation: 0,
+                distance: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_unfittable_lengths() {
+        assert_eq!(fit_euclidean(&[]), None);
+        assert_eq!(fit_euclidean(&[false; 256]), None);
+    }
+}
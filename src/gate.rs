@@ -0,0 +1,107 @@
+//! Gate lengths and ties.
+//!
+//! A pattern says when notes start; a gate length says how long each one is
+//! held. Gate lengths are stored one `f32` per step, measured in steps: `0.0`
+//! is a rest, `0.5` holds for half the step and values above `1.0` tie over
+//! the following steps, as bass lines and pads need. A gate that reaches the
+//! next onset plays legato into it.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Gives every pulse of `pattern` the same gate `length`, in steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, gate_lengths};
+///
+/// assert_eq!(gate_lengths(&euclidean(4, 2, 0), 1.5), [1.5, 0.0, 1.5, 0.0]);
+/// ```
+pub fn gate_lengths(pattern: &[bool], length: f32) -> Vec<f32> {
+    pattern
+        .iter()
+        .map(|&pulse| if pulse { length } else { 0.0 })
+        .collect()
+}
+
+/// Holds every pulse of `pattern` until the next one, wrapping round the
+/// cycle, so the notes tie into each other with no gaps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, legato};
+///
+/// // Tresillo: held for 3, 3 and 2 steps.
+/// let gates = legato(&euclidean(8, 3, 0));
+/// assert_eq!(gates, [3.0, 0.0, 0.0, 3.0, 0.0, 0.0, 2.0, 0.0]);
+/// ```
+pub fn legato(pattern: &[bool]) -> Vec<f32> {
+    let onsets: Vec<usize> = (0..pattern.len()).filter(|&i| pattern[i]).collect();
+    let mut gates = vec![0.0; pattern.len()];
+    for (i, &onset) in onsets.iter().enumerate() {
+        let next = onsets.get(i + 1).map_or(onsets[0] + pattern.len(), |&o| o);
+        gates[onset] = (next - onset) as f32;
+    }
+    gates
+}
+
+/// Renders gate lengths as a gate signal of `samples_per_step` samples per
+/// step, `true` while a note is held.
+///
+/// Gates that run past the end of the loop wrap round to its start, so the
+/// buffer plays back seamlessly. A gate that reaches the next note merges
+/// with it into one unbroken high, which is how ties are played on a gate
+/// output; leave a gap (a length below the distance to the next note) to
+/// retrigger. Negative and NaN lengths are rests.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::render_gates;
+///
+/// let buffer = render_gates(&[1.5, 0.0, 0.5, 0.0], 2);
+/// assert_eq!(buffer, [true, true, true, false, true, false, false, false]);
+/// ```
+pub fn render_gates(gates: &[f32], samples_per_step: usize) -> Vec<bool> {
+    let total = gates.len() * samples_per_step;
+    let mut buffer = vec![false; total];
+    if total == 0 {
+        return buffer;
+    }
+    for (step, &length) in gates.iter().enumerate() {
+        if length.is_nan() || length <= 0.0 {
+            continue;
+        }
+        // Rounded to the nearest sample, at least one and at most the loop.
+        let held = ((length * samples_per_step as f32 + 0.5) as usize).clamp(1, total);
+        let start = step * samples_per_step;
+        for sample in start..start + held {
+            buffer[sample % total] = true;
+        }
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn ties_wrap_and_merge() {
+        let gates = legato(&euclidean(8, 3, 2));
+        assert_eq!(gates.iter().sum::<f32>(), 8.0);
+        assert_eq!(render_gates(&gates, 3), vec![true; 24]);
+
+        // A tie from the last step round into the first.
+        let wrapped = render_gates(&[0.0, 0.0, 0.0, 2.0], 1);
+        assert_eq!(wrapped, [true, false, false, true]);
+        assert_eq!(
+            render_gates(&[f32::NAN, -1.0, 0.01], 4),
+            [
+                false, false, false, false, false, false, false, false, true, false, false, false
+            ]
+        );
+        assert!(legato(&[false; 3]).iter().all(|&g| g == 0.0));
+        assert!(render_gates(&[1.0], 0).is_empty());
+    }
+}
@@ -0,0 +1,1126 @@
+//! Generative variation of patterns.
+//!
+//! Tools for evolving a base pattern over time. Everything that involves
+//! randomness takes an explicit seed so a performance can be replayed exactly.
+
+use crate::editor::{EditEvent, PatternEditor};
+use crate::error::{Error, Result, check_params, unwrap_or_panic};
+use crate::pattern::Pattern;
+use crate::rng::Rng;
+use crate::role::TrackRole;
+use crate::{euclidean, try_euclidean};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Produces a deterministic per-cycle variation of a base pattern.
+///
+/// Each onset is dropped with probability `drop_prob` and each rest becomes an
+/// onset with probability `add_prob`. The result depends only on `seed` and
+/// `cycle_index`, so cycle 7 always sounds the same on every replay.
+///
+/// # Arguments
+/// * `pattern` - The base pattern
+/// * `drop_prob` - Probability (0.0-1.0) of removing each onset
+/// * `add_prob` - Probability (0.0-1.0) of adding an onset on each rest
+/// * `seed` - Seed shared by all cycles of a performance
+/// * `cycle_index` - Which cycle (bar) to generate
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, vary};
+///
+/// let base = euclidean(16, 5, 0);
+/// let a = vary(&base, 0.2, 0.1, 1234, 7);
+/// let b = vary(&base, 0.2, 0.1, 1234, 7);
+/// assert_eq!(a, b);
+/// assert_eq!(vary(&base, 0.0, 0.0, 1234, 7), base);
+/// ```
+pub fn vary(
+    pattern: &[bool],
+    drop_prob: f32,
+    add_prob: f32,
+    seed: u64,
+    cycle_index: u64,
+) -> Vec<bool> {
+    let mut rng = Rng::for_cycle(seed, cycle_index);
+    pattern
+        .iter()
+        .map(|&pulse| {
+            // Exactly one draw per step keeps each step's fate independent of
+            // the probabilities chosen for the others.
+            if pulse {
+                !rng.chance(drop_prob)
+            } else {
+                rng.chance(add_prob)
+            }
+        })
+        .collect()
+}
+
+/// Builds a per-step probability field from a second Euclidean layer: steps
+/// on the onsets of E(`prob_pulses`, `steps`) get `high_p`, all others
+/// `low_p`. Both are clamped to 0.0-1.0.
+///
+/// Laid over another track (see `SequencerTrack::set_probability_mask`),
+/// the field makes some of its pulses near-certain and others occasional,
+/// so the groove keeps its shape while varying from bar to bar.
+///
+/// # Panics
+/// Panics if `steps == 0` or `prob_pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::probability_mask;
+///
+/// assert_eq!(probability_mask(8, 3, 0.9, 0.25), [0.9, 0.25, 0.25, 0.9, 0.25, 0.25, 0.9, 0.25]);
+/// ```
+pub fn probability_mask(steps: u8, prob_pulses: u8, high_p: f32, low_p: f32) -> Vec<f32> {
+    unwrap_or_panic(try_probability_mask(steps, prob_pulses, high_p, low_p))
+}
+
+/// Like [`probability_mask`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Fails if `steps == 0` or `prob_pulses > steps`.
+pub fn try_probability_mask(
+    steps: u8,
+    prob_pulses: u8,
+    high_p: f32,
+    low_p: f32,
+) -> Result<Vec<f32>> {
+    let clamp = |p: f32| if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+    let (high_p, low_p) = (clamp(high_p), clamp(low_p));
+    Ok(try_euclidean(steps, prob_pulses, 0)?
+        .into_iter()
+        .map(|onset| if onset { high_p } else { low_p })
+        .collect())
+}
+
+/// How [`ParamWalker`] moves the pulse count from one bar to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    /// With probability `step_prob` the pulse count moves one step up or down
+    /// (equally likely), clamped to the walker's bounds.
+    RandomWalk {
+        /// Probability (0.0-1.0) of moving at each bar.
+        step_prob: f32,
+    },
+    /// Markov chain over pulse counts. Row `i` holds the relative weights of
+    /// moving from `min_pulses + i` pulses to `min_pulses + j` pulses.
+    /// Missing or all-zero rows keep the current pulse count.
+    Matrix(Vec<Vec<f32>>),
+}
+
+/// Evolves Euclidean parameters bar-to-bar and emits one pattern per bar.
+///
+/// The walker starts at the given parameters; each call to [`next`] returns
+/// the pattern for the current bar and then moves `pulses` according to the
+/// configured [`Transition`] and `rotation` by ±1 with the configured drift
+/// probability. The sequence is fully determined by the seed.
+///
+/// [`next`]: Iterator::next
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{ParamWalker, Transition};
+///
+/// let walker = ParamWalker::new(16, 5, 0, 42)
+///     .with_pulse_bounds(3, 8)
+///     .with_transition(Transition::RandomWalk { step_prob: 0.5 })
+///     .with_rotation_drift(0.25);
+///
+/// let bars: Vec<Vec<bool>> = walker.take(8).collect();
+/// assert_eq!(bars.len(), 8);
+/// for bar in &bars {
+///     let pulses = bar.iter().filter(|&&p| p).count();
+///     assert!((3..=8).contains(&pulses));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParamWalker {
+    steps: u8,
+    pulses: u8,
+    rotation: u8,
+    min_pulses: u8,
+    max_pulses: u8,
+    transition: Transition,
+    rotation_drift: f32,
+    rng: Rng,
+}
+
+impl ParamWalker {
+    /// Creates a walker starting at E(`pulses`, `steps`) rotated by `rotation`.
+    ///
+    /// Defaults to a random walk with `step_prob` 0.5 over `0..=steps` and no
+    /// rotation drift.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `pulses > steps`.
+    pub fn new(steps: u8, pulses: u8, rotation: u8, seed: u64) -> Self {
+        unwrap_or_panic(Self::try_new(steps, pulses, rotation, seed))
+    }
+
+    /// Like [`new`](Self::new), but reports invalid parameters as an error.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `pulses > steps`.
+    pub fn try_new(steps: u8, pulses: u8, rotation: u8, seed: u64) -> Result<Self> {
+        check_params(usize::from(steps), usize::from(pulses))?;
+        Ok(Self {
+            steps,
+            pulses,
+            rotation: rotation % steps,
+            min_pulses: 0,
+            max_pulses: steps,
+            transition: Transition::RandomWalk { step_prob: 0.5 },
+            rotation_drift: 0.0,
+            rng: Rng::new(seed),
+        })
+    }
+
+    /// Restricts the pulse count to `min..=max` (clamped to `0..=steps`).
+    /// The current pulse count is pulled inside the new bounds.
+    #[must_use]
+    pub fn with_pulse_bounds(mut self, min: u8, max: u8) -> Self {
+        self.max_pulses = max.min(self.steps);
+        self.min_pulses = min.min(self.max_pulses);
+        self.pulses = self.pulses.clamp(self.min_pulses, self.max_pulses);
+        self
+    }
+
+    /// Sets how the pulse count evolves.
+    #[must_use]
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// Sets the probability (0.0-1.0) that rotation moves by ±1 at each bar.
+    #[must_use]
+    pub fn with_rotation_drift(mut self, probability: f32) -> Self {
+        self.rotation_drift = probability;
+        self
+    }
+
+    /// Returns the current `(pulses, rotation)`.
+    pub fn params(&self) -> (u8, u8) {
+        (self.pulses, self.rotation)
+    }
+
+    fn advance(&mut self) {
+        self.pulses = match &self.transition {
+            Transition::RandomWalk { step_prob } => {
+                if self.rng.chance(*step_prob) {
+                    if self.rng.next_u64() & 1 == 0 {
+                        self.pulses.saturating_sub(1).max(self.min_pulses)
+                    } else {
+                        self.pulses.saturating_add(1).min(self.max_pulses)
+                    }
+                } else {
+                    self.pulses
+                }
+            }
+            Transition::Matrix(matrix) => {
+                let row = matrix
+                    .get(usize::from(self.pulses - self.min_pulses))
+                    .map(Vec::as_slice)
+                    .unwrap_or(&[]);
+                let span = usize::from(self.max_pulses - self.min_pulses) + 1;
+                let weights = &row[..row.len().min(span)];
+                let total: f64 = weights.iter().map(|&w| f64::from(w.max(0.0))).sum();
+                if total > 0.0 {
+                    let mut target = self.rng.next_f64() * total;
+                    let mut next = weights.len() - 1;
+                    for (j, &w) in weights.iter().enumerate() {
+                        target -= f64::from(w.max(0.0));
+                        if target < 0.0 {
+                            next = j;
+                            break;
+                        }
+                    }
+                    self.min_pulses + next as u8
+                } else {
+                    self.pulses
+                }
+            }
+        };
+
+        if self.rng.chance(self.rotation_drift) {
+            self.rotation = if self.rng.next_u64() & 1 == 0 {
+                (self.rotation + self.steps - 1) % self.steps
+            } else {
+                (self.rotation + 1) % self.steps
+            };
+        }
+    }
+}
+
+impl Iterator for ParamWalker {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Vec<bool>> {
+        let pattern = euclidean(self.steps, self.pulses, self.rotation);
+        self.advance();
+        Some(pattern)
+    }
+}
+
+/// How [`CrossfadePlayer`] picks between its two patterns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CrossfadeMode {
+    /// B's steps are spread evenly, like the pulses of a Euclidean rhythm:
+    /// at a mix of 0.25 every fourth step comes from B.
+    #[default]
+    Even,
+    /// Each step comes from B with probability `mix`. The draw depends only
+    /// on the seed and the step, so a performance can be replayed exactly.
+    Random {
+        /// Seed for the step choices.
+        seed: u64,
+    },
+}
+
+/// Blends two Euclidean grooves step by step, DJ style.
+///
+/// Each step is taken from pattern A or pattern B, with the share of B set
+/// by the [`mix`](Self::set_mix) control: 0.0 plays A only, 1.0 plays B
+/// only. The patterns may have different lengths; each keeps looping at its
+/// own length. The player is an endless iterator of steps, and the mix can
+/// be moved while it plays.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::CrossfadePlayer;
+///
+/// // Four on the floor, faded over to E(7,16).
+/// let mut player = CrossfadePlayer::new((16, 4, 0), (16, 7, 0));
+/// let a: Vec<bool> = player.by_ref().take(16).collect();
+/// player.set_mix(1.0);
+/// let b: Vec<bool> = player.by_ref().take(16).collect();
+/// assert_eq!(a.iter().filter(|&&p| p).count(), 4);
+/// assert_eq!(b.iter().filter(|&&p| p).count(), 7);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossfadePlayer {
+    a: Vec<bool>,
+    b: Vec<bool>,
+    mix: f32,
+    mode: CrossfadeMode,
+    position: u64,
+}
+
+impl CrossfadePlayer {
+    /// Creates a player fading between two Euclidean rhythms, each given as
+    /// `(steps, pulses, rotation)`. Starts on A (mix 0.0) in
+    /// [`CrossfadeMode::Even`].
+    ///
+    /// # Panics
+    /// Panics if either step count is zero or has more pulses than steps.
+    pub fn new(a: (u8, u8, u8), b: (u8, u8, u8)) -> Self {
+        unwrap_or_panic(Self::try_new(a, b))
+    }
+
+    /// Like [`new`](Self::new), but reports invalid parameters as an error.
+    ///
+    /// # Errors
+    /// Fails if either step count is zero or has more pulses than steps.
+    pub fn try_new(a: (u8, u8, u8), b: (u8, u8, u8)) -> Result<Self> {
+        Ok(Self {
+            a: try_euclidean(a.0, a.1, a.2)?,
+            b: try_euclidean(b.0, b.1, b.2)?,
+            mix: 0.0,
+            mode: CrossfadeMode::Even,
+            position: 0,
+        })
+    }
+
+    /// Sets how steps are picked, for builder-style construction.
+    #[must_use]
+    pub fn with_mode(mut self, mode: CrossfadeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the share of steps taken from B, clamped to `0.0..=1.0` (NaN
+    /// counts as 0.0). Takes effect from the next step.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = if mix.is_nan() {
+            0.0
+        } else {
+            mix.clamp(0.0, 1.0)
+        };
+    }
+
+    /// The current mix.
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Steps played since creation or the last reset.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Restarts both patterns from their first step.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+
+    /// Whether step `position` is taken from B at the current mix.
+    fn plays_b(&self, position: u64) -> bool {
+        match self.mode {
+            CrossfadeMode::Even => {
+                let mix = f64::from(self.mix);
+                ((position + 1) as f64 * mix).floor() > (position as f64 * mix).floor()
+            }
+            CrossfadeMode::Random { seed } => Rng::for_cycle(seed, position).chance(self.mix),
+        }
+    }
+}
+
+impl Iterator for CrossfadePlayer {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        let source = if self.plays_b(self.position) {
+            &self.b
+        } else {
+            &self.a
+        };
+        let step = source[(self.position % source.len() as u64) as usize];
+        self.position += 1;
+        Some(step)
+    }
+}
+
+/// Regenerates a Euclidean pattern each cycle from an external density signal.
+///
+/// `density_signal` is called with the cycle index and should return a
+/// density in `0.0..=1.0` (values outside are clamped); the pulse count for
+/// that cycle is `round(density * steps)`. Use it to drive the "density knob"
+/// from an LFO, an envelope, or noise.
+///
+/// Returns an endless iterator of one pattern per cycle.
+///
+/// # Panics
+/// Panics if `steps == 0`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::modulated_density;
+///
+/// // Triangle LFO over 4 cycles
+/// let lfo = |cycle: u64| [0.25, 0.5, 0.75, 0.5][(cycle % 4) as usize];
+/// let bars: Vec<Vec<bool>> = modulated_density(16, lfo, 0).take(4).collect();
+/// let pulses: Vec<usize> = bars
+///     .iter()
+///     .map(|bar| bar.iter().filter(|&&p| p).count())
+///     .collect();
+/// assert_eq!(pulses, vec![4, 8, 12, 8]);
+/// ```
+pub fn modulated_density<F>(
+    steps: u8,
+    density_signal: F,
+    rotation: u8,
+) -> impl Iterator<Item = Vec<bool>>
+where
+    F: Fn(u64) -> f32,
+{
+    if steps == 0 {
+        panic!("{}", Error::ZeroSteps);
+    }
+    (0u64..).map(move |cycle| {
+        let density = density_signal(cycle);
+        let density = if density.is_nan() {
+            0.0
+        } else {
+            density.clamp(0.0, 1.0)
+        };
+        let pulses = (density * f32::from(steps)).round() as u8;
+        euclidean(steps, pulses, rotation)
+    })
+}
+
+/// Builds a multi-bar build-up (or breakdown) from E(`from_pulses`, `steps`)
+/// to E(`to_pulses`, `steps`), one pattern per bar.
+///
+/// The pulse count moves in rounded, evenly spaced increments, so the first
+/// bar has exactly `from_pulses` and the last exactly `to_pulses`. A single
+/// bar holds `from_pulses`; zero bars yield nothing.
+///
+/// # Panics
+/// Panics if `steps == 0` or either pulse count exceeds `steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::density_ramp;
+///
+/// let fill = density_ramp(16, 4, 10, 4);
+/// let pulses: Vec<usize> = fill.iter().map(|bar| bar.iter().filter(|&&p| p).count()).collect();
+/// assert_eq!(pulses, vec![4, 6, 8, 10]);
+/// ```
+pub fn density_ramp(steps: u8, from_pulses: u8, to_pulses: u8, bars: usize) -> Vec<Vec<bool>> {
+    density_ramp_rotated(steps, (from_pulses, 0), (to_pulses, 0), bars)
+}
+
+/// Like [`density_ramp`], but also moves the rotation from `from.1` to
+/// `to.1` in the same even increments. Endpoints are `(pulses, rotation)`.
+///
+/// # Panics
+/// Panics if `steps == 0` or either pulse count exceeds `steps`.
+pub fn density_ramp_rotated(
+    steps: u8,
+    from: (u8, u8),
+    to: (u8, u8),
+    bars: usize,
+) -> Vec<Vec<bool>> {
+    unwrap_or_panic(check_params(usize::from(steps), usize::from(from.0)));
+    unwrap_or_panic(check_params(usize::from(steps), usize::from(to.0)));
+    let lerp = |a: u8, b: u8, bar: usize| {
+        if bars < 2 {
+            return a;
+        }
+        let t = bar as f64 / (bars - 1) as f64;
+        (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8
+    };
+    (0..bars)
+        .map(|bar| euclidean(steps, lerp(from.0, to.0, bar), lerp(from.1, to.1, bar)))
+        .collect()
+}
+
+/// How [`phrase`] varies the bars of a phrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VariationRule {
+    /// Each bar is rotated this many steps further than the one before.
+    RotateEachBar(u8),
+    /// Every bar repeats the base pattern except the last, which doubles the
+    /// pulse count (capped at `steps`) as a fill.
+    FillLastBar,
+    /// Bars alternate between these two pulse counts, starting with the
+    /// first; the phrase's own pulse count is not used.
+    AlternateDensity(u8, u8),
+}
+
+/// Builds a phrase of `bars` bars from E(`pulses`, `steps`), varying each bar
+/// according to `rule`. Returns one pattern per bar.
+///
+/// # Panics
+/// Panics if `steps == 0` or a pulse count exceeds `steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{VariationRule, pattern_to_string, phrase};
+///
+/// let bars = phrase(8, 3, 4, VariationRule::FillLastBar);
+/// let bars: Vec<String> = bars.iter().map(|bar| pattern_to_string(bar, 'x', '.')).collect();
+/// assert_eq!(bars, ["x..x..x.", "x..x..x.", "x..x..x.", "x.xxx.xx"]);
+/// ```
+pub fn phrase(steps: u8, pulses: u8, bars: usize, rule: VariationRule) -> Vec<Vec<bool>> {
+    unwrap_or_panic(check_params(usize::from(steps), usize::from(pulses)));
+    (0..bars)
+        .map(|bar| match rule {
+            VariationRule::RotateEachBar(n) => {
+                let rotation = bar as u64 * u64::from(n) % u64::from(steps);
+                euclidean(steps, pulses, rotation as u8)
+            }
+            VariationRule::FillLastBar if bar + 1 == bars => {
+                euclidean(steps, pulses.saturating_mul(2).min(steps), 0)
+            }
+            VariationRule::FillLastBar => euclidean(steps, pulses, 0),
+            VariationRule::AlternateDensity(a, b) => {
+                euclidean(steps, if bar % 2 == 0 { a } else { b }, 0)
+            }
+        })
+        .collect()
+}
+
+/// A requirement on the steps of a pattern, used by [`generate_constrained`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// The step at this index must be a pulse.
+    OnsetAt(usize),
+    /// The step at this index must be a rest.
+    RestAt(usize),
+    /// No two pulses may be neighbours, including across the loop point.
+    NoAdjacentOnsets,
+}
+
+/// Finds every pattern with `pulses` onsets in `steps` that satisfies all
+/// `constraints`, ordered by closeness to the Euclidean rhythm.
+///
+/// Closeness is the smallest Hamming distance to any rotation of E(`pulses`,
+/// `steps`), so rotations of the Euclidean pattern itself come first whenever
+/// they qualify. Ties are broken by distance to the unrotated pattern, then by
+/// earlier onsets first.
+///
+/// The search is exhaustive with pruning, which is instant for the usual 8-16
+/// step grids but grows combinatorially; keep `steps` modest (≲ 24) when the
+/// constraints leave many steps free.
+///
+/// # Panics
+/// Panics if `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Constraint, generate_constrained, pattern_to_string};
+///
+/// // Kick on 1, nothing on the snare's step 4, never two hits in a row.
+/// let found = generate_constrained(
+///     8,
+///     3,
+///     &[Constraint::OnsetAt(0), Constraint::NoAdjacentOnsets, Constraint::RestAt(4)],
+/// );
+/// assert_eq!(pattern_to_string(&found[0], 'x', '.'), "x..x..x.");
+/// ```
+pub fn generate_constrained(steps: u8, pulses: u8, constraints: &[Constraint]) -> Vec<Vec<bool>> {
+    let reference = euclidean(steps, pulses, 0);
+    let steps = usize::from(steps);
+
+    let mut required = vec![None; steps];
+    let mut no_adjacent = false;
+    for constraint in constraints {
+        let (index, value) = match *constraint {
+            Constraint::OnsetAt(i) => (i, true),
+            Constraint::RestAt(i) => (i, false),
+            Constraint::NoAdjacentOnsets => {
+                no_adjacent = true;
+                continue;
+            }
+        };
+        match required.get(index) {
+            Some(Some(existing)) if *existing != value => return Vec::new(),
+            Some(_) => required[index] = Some(value),
+            None => return Vec::new(),
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut current = Vec::with_capacity(steps);
+    search_constrained(
+        &mut current,
+        steps,
+        usize::from(pulses),
+        &required,
+        no_adjacent,
+        &mut found,
+    );
+
+    let mut scored: Vec<((usize, usize), Vec<bool>)> = found
+        .into_iter()
+        .map(|p| {
+            let direct = p.iter().zip(&reference).filter(|(a, b)| a != b).count();
+            ((distance_to_rotations(&p, &reference), direct), p)
+        })
+        .collect();
+    scored.sort_by_key(|(score, _)| *score);
+    scored.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Depth-first enumeration of all patterns honouring the constraints.
+fn search_constrained(
+    current: &mut Vec<bool>,
+    steps: usize,
+    pulses_left: usize,
+    required: &[Option<bool>],
+    no_adjacent: bool,
+    found: &mut Vec<Vec<bool>>,
+) {
+    let index = current.len();
+    if index == steps {
+        let wraps = no_adjacent && steps > 1 && current[0] && current[steps - 1];
+        if pulses_left == 0 && !wraps {
+            found.push(current.clone());
+        }
+        return;
+    }
+    if pulses_left > steps - index {
+        return;
+    }
+
+    for value in [true, false] {
+        if required[index].is_some_and(|r| r != value) {
+            continue;
+        }
+        if value && (pulses_left == 0 || (no_adjacent && index > 0 && current[index - 1])) {
+            continue;
+        }
+        current.push(value);
+        search_constrained(
+            current,
+            steps,
+            pulses_left - usize::from(value),
+            required,
+            no_adjacent,
+            found,
+        );
+        current.pop();
+    }
+}
+
+/// Smallest Hamming distance between `pattern` and any rotation of `reference`.
+fn distance_to_rotations(pattern: &[bool], reference: &[bool]) -> usize {
+    let len = reference.len();
+    (0..len)
+        .map(|r| {
+            (0..len)
+                .filter(|&i| pattern[i] != reference[(i + r) % len])
+                .count()
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// Searches for a pattern that maximizes `objective`, starting from the
+/// Euclidean rhythm E(`pulses`, `steps`).
+///
+/// Runs simulated annealing for `budget` objective evaluations. Each move
+/// shifts one onset to a random rest, so the pulse count never changes. Worse
+/// candidates are occasionally accepted early on to escape local optima; the
+/// temperature adapts to the size of score changes seen so far, so objectives
+/// on any scale work. To approach a target value rather than a maximum, score
+/// with `-(metric - target).abs()`.
+///
+/// Returns the best pattern found and its score. The search is deterministic
+/// for a given `seed`.
+///
+/// # Panics
+/// Panics if `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Pattern, search};
+///
+/// // Prefer onsets on odd steps (off-beats).
+/// let offbeat = |p: &Pattern| p.iter().skip(1).step_by(2).filter(|&&s| s).count() as f64;
+/// let (best, score) = search(8, 3, offbeat, 500, 1);
+/// assert_eq!(best.pulse_count(), 3);
+/// assert_eq!(score, 3.0);
+/// ```
+pub fn search<F>(steps: u8, pulses: u8, objective: F, budget: usize, seed: u64) -> (Pattern, f64)
+where
+    F: Fn(&Pattern) -> f64,
+{
+    let mut current = Pattern::from(euclidean(steps, pulses, 0));
+    let mut current_score = objective(&current);
+    let mut best = (current.clone(), current_score);
+    if pulses == 0 || pulses == steps {
+        return best;
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut mean_delta = 0.0;
+    let mut deltas_seen = 0u32;
+
+    for iteration in 0..budget {
+        let mut candidate = current.clone().into_vec();
+        let onsets: Vec<usize> = (0..candidate.len()).filter(|&i| candidate[i]).collect();
+        let rests: Vec<usize> = (0..candidate.len()).filter(|&i| !candidate[i]).collect();
+        candidate[onsets[rng.below(onsets.len())]] = false;
+        candidate[rests[rng.below(rests.len())]] = true;
+        let candidate = Pattern::from(candidate);
+        let score = objective(&candidate);
+
+        let delta = score - current_score;
+        if delta != 0.0 && delta.is_finite() {
+            deltas_seen += 1;
+            mean_delta += (delta.abs() - mean_delta) / f64::from(deltas_seen);
+        }
+        let temperature = mean_delta * (1.0 - iteration as f64 / budget as f64);
+        let accept =
+            delta >= 0.0 || (temperature > 0.0 && rng.next_f64() < (delta / temperature).exp());
+
+        if accept {
+            current = candidate;
+            current_score = score;
+            if current_score > best.1 {
+                best = (current.clone(), current_score);
+            }
+        }
+    }
+    best
+}
+
+/// Rolls new Euclidean parameters for the tracks of a kit, skipping locked
+/// tracks: the dice button of a groovebox.
+///
+/// The kit is a slice of [`PatternEditor`]s, one per track, and every
+/// change is made through [`PatternEditor::set_params`], so each track can
+/// undo a roll like any other edit. A track keeps its step count; the pulse
+/// count (from 1 to the step count, or within the [`TrackRole::density`]
+/// of a track given a role) and the rotation are rolled, unless turned off
+/// with [`with_density`](Self::with_density) or
+/// [`with_rotation`](Self::with_rotation). Rolls follow from the seed, so
+/// a session can be replayed.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{KitRandomizer, PatternEditor};
+///
+/// let mut kit = vec![PatternEditor::euclidean(16, 4, 0), PatternEditor::euclidean(16, 11, 2)];
+/// let mut dice = KitRandomizer::new(7).with_rotation(false);
+/// dice.lock(0);
+///
+/// let changed = dice.roll(&mut kit);
+/// assert!(changed.iter().all(|&(track, _)| track == 1));
+/// assert_eq!(kit[0].params(), Some((16, 4, 0)));
+/// assert_eq!(kit[1].params().map(|(steps, _, rotation)| (steps, rotation)), Some((16, 2)));
+///
+/// kit[1].undo();
+/// assert_eq!(kit[1].params(), Some((16, 11, 2)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct KitRandomizer {
+    density: bool,
+    rotation: bool,
+    locked: BTreeSet<usize>,
+    roles: BTreeMap<usize, TrackRole>,
+    rng: Rng,
+}
+
+impl KitRandomizer {
+    /// Creates a randomizer that rolls density and rotation, with no tracks
+    /// locked.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            density: true,
+            rotation: true,
+            locked: BTreeSet::new(),
+            roles: BTreeMap::new(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Sets whether rolls change the pulse count.
+    #[must_use]
+    pub fn with_density(mut self, density: bool) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Sets whether rolls change the rotation.
+    #[must_use]
+    pub fn with_rotation(mut self, rotation: bool) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Protects track `track` from rolls.
+    pub fn lock(&mut self, track: usize) {
+        self.locked.insert(track);
+    }
+
+    /// Lets rolls change track `track` again.
+    pub fn unlock(&mut self, track: usize) {
+        self.locked.remove(&track);
+    }
+
+    /// Returns `true` if track `track` is locked.
+    pub fn is_locked(&self, track: usize) -> bool {
+        self.locked.contains(&track)
+    }
+
+    /// Keeps the rolled pulse count of track `track` within the density of
+    /// `role` (capped at the track's step count).
+    pub fn set_role(&mut self, track: usize, role: TrackRole) {
+        self.roles.insert(track, role);
+    }
+
+    /// Gives track `track` a role, for builder-style construction.
+    #[must_use]
+    pub fn with_role(mut self, track: usize, role: TrackRole) -> Self {
+        self.set_role(track, role);
+        self
+    }
+
+    /// Rolls every unlocked track of `kit` and returns the edit made to each,
+    /// with its index.
+    ///
+    /// A track whose pattern was edited step by step is read as its step
+    /// and pulse counts with rotation 0. Tracks longer than 255 steps are
+    /// skipped, as are tracks whose pattern would not change, so hand edits
+    /// survive and no empty edits enter the history. With both parameters
+    /// turned off, nothing is rolled at all.
+    pub fn roll(&mut self, kit: &mut [PatternEditor]) -> Vec<(usize, EditEvent)> {
+        let mut events = Vec::new();
+        if !self.density && !self.rotation {
+            return events;
+        }
+        for (index, editor) in kit.iter_mut().enumerate() {
+            if self.locked.contains(&index) {
+                continue;
+            }
+            let current = editor.params().or_else(|| {
+                let steps = u8::try_from(editor.pattern().len()).ok()?;
+                let pulses = editor.pattern().pulse_count() as u8;
+                Some((steps, pulses, 0))
+            });
+            let Some((steps, pulses, rotation)) = current.filter(|&(steps, ..)| steps > 0) else {
+                continue;
+            };
+            let pulses = if self.density {
+                let range = self
+                    .roles
+                    .get(&index)
+                    .map_or(1..=steps, |role| role.density());
+                let (low, high) = (*range.start().min(&steps), *range.end().min(&steps));
+                low + self.rng.below(usize::from(high - low) + 1) as u8
+            } else {
+                pulses
+            };
+            let rotation = if self.rotation {
+                self.rng.below(usize::from(steps)) as u8
+            } else {
+                rotation
+            };
+            if euclidean(steps, pulses, rotation) != editor.pattern().as_slice() {
+                // Parameters come from a valid pattern, so this cannot fail.
+                events.push((index, editor.set_params(steps, pulses, rotation)));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vary_is_deterministic_per_cycle() {
+        let base = euclidean(16, 7, 0);
+        for cycle in 0..8 {
+            assert_eq!(
+                vary(&base, 0.3, 0.2, 99, cycle),
+                vary(&base, 0.3, 0.2, 99, cycle)
+            );
+        }
+        let cycles: Vec<_> = (0..8).map(|c| vary(&base, 0.3, 0.2, 99, c)).collect();
+        assert!(cycles.iter().any(|c| *c != cycles[0]));
+    }
+
+    #[test]
+    fn vary_extreme_probabilities() {
+        let base = euclidean(8, 3, 0);
+        assert_eq!(vary(&base, 0.0, 0.0, 1, 0), base);
+        assert_eq!(vary(&base, 1.0, 0.0, 1, 0), vec![false; 8]);
+        assert_eq!(vary(&base, 0.0, 1.0, 1, 0), vec![true; 8]);
+        let inverted: Vec<bool> = base.iter().map(|b| !b).collect();
+        assert_eq!(vary(&base, 1.0, 1.0, 1, 0), inverted);
+    }
+
+    #[test]
+    fn probability_mask_follows_the_layer() {
+        let mask = probability_mask(16, 4, 1.5, f32::NAN);
+        assert_eq!(mask.iter().filter(|&&p| p == 1.0).count(), 4);
+        assert_eq!(mask[4], 1.0);
+        assert_eq!(mask[1], 0.0);
+        assert!(try_probability_mask(4, 5, 1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn walker_is_reproducible_and_bounded() {
+        let make = || {
+            ParamWalker::new(16, 6, 0, 7)
+                .with_pulse_bounds(4, 9)
+                .with_rotation_drift(0.5)
+        };
+        let a: Vec<_> = make().take(32).collect();
+        let b: Vec<_> = make().take(32).collect();
+        assert_eq!(a, b);
+        assert_eq!(a[0], euclidean(16, 6, 0));
+        for bar in &a {
+            let pulses = bar.iter().filter(|&&p| p).count();
+            assert!((4..=9).contains(&pulses));
+        }
+    }
+
+    #[test]
+    fn walker_follows_transition_matrix() {
+        // Deterministic cycle 2 -> 3 -> 4 -> 2 over pulse counts 2..=4.
+        let matrix = vec![
+            vec![0.0, 1.0, 0.0],
+            vec![0.0, 0.0, 1.0],
+            vec![1.0, 0.0, 0.0],
+        ];
+        let mut walker = ParamWalker::new(8, 2, 0, 1)
+            .with_pulse_bounds(2, 4)
+            .with_transition(Transition::Matrix(matrix));
+        let mut seen = Vec::new();
+        for _ in 0..6 {
+            seen.push(walker.params().0);
+            walker.next();
+        }
+        assert_eq!(seen, vec![2, 3, 4, 2, 3, 4]);
+    }
+
+    #[test]
+    fn modulated_density_tracks_signal() {
+        let bars: Vec<_> = modulated_density(8, |c| c as f32 / 4.0, 0)
+            .take(6)
+            .collect();
+        let pulses: Vec<usize> = bars
+            .iter()
+            .map(|b| b.iter().filter(|&&p| p).count())
+            .collect();
+        // Densities above 1.0 clamp to a full bar.
+        assert_eq!(pulses, vec![0, 2, 4, 6, 8, 8]);
+        assert_eq!(bars[1], euclidean(8, 2, 0));
+    }
+
+    #[test]
+    fn density_ramp_endpoints_and_rotation() {
+        let down = density_ramp(8, 7, 1, 3);
+        assert_eq!(
+            down,
+            vec![euclidean(8, 7, 0), euclidean(8, 4, 0), euclidean(8, 1, 0)]
+        );
+        assert_eq!(density_ramp(8, 3, 5, 1), vec![euclidean(8, 3, 0)]);
+        assert!(density_ramp(8, 3, 5, 0).is_empty());
+
+        let turning = density_ramp_rotated(16, (4, 0), (4, 3), 4);
+        assert_eq!(turning[3], euclidean(16, 4, 3));
+        assert_eq!(turning[1], euclidean(16, 4, 1));
+    }
+
+    #[test]
+    fn constrained_generation_respects_constraints() {
+        let constraints = [
+            Constraint::OnsetAt(0),
+            Constraint::RestAt(4),
+            Constraint::NoAdjacentOnsets,
+        ];
+        let found = generate_constrained(12, 4, &constraints);
+        assert!(!found.is_empty());
+        for p in &found {
+            assert!(p[0] && !p[4]);
+            assert_eq!(p.iter().filter(|&&x| x).count(), 4);
+            for i in 0..12 {
+                assert!(!(p[i] && p[(i + 1) % 12]));
+            }
+        }
+        // E(4,12) itself qualifies, so it must be ranked first.
+        assert_eq!(found[0], euclidean(12, 4, 0));
+    }
+
+    #[test]
+    fn constrained_generation_conflicts_yield_nothing() {
+        assert!(
+            generate_constrained(8, 3, &[Constraint::OnsetAt(2), Constraint::RestAt(2)]).is_empty()
+        );
+        assert!(generate_constrained(8, 3, &[Constraint::OnsetAt(8)]).is_empty());
+        assert!(generate_constrained(8, 5, &[Constraint::NoAdjacentOnsets]).is_empty());
+        assert_eq!(generate_constrained(8, 3, &[]).len(), 56);
+    }
+
+    #[test]
+    fn crossfade_follows_the_mix() {
+        let mut player = CrossfadePlayer::new((8, 8, 0), (4, 0, 0));
+        player.set_mix(0.25);
+        let even: Vec<bool> = player.by_ref().take(8).collect();
+        assert_eq!(even, [true, true, true, false, true, true, true, false]);
+
+        let random =
+            CrossfadePlayer::new((8, 8, 0), (4, 0, 0)).with_mode(CrossfadeMode::Random { seed: 9 });
+        let mut half = random.clone();
+        half.set_mix(0.5);
+        let from_b = half.by_ref().take(1000).filter(|&p| !p).count();
+        assert!((400..600).contains(&from_b));
+        half.reset();
+        let replay: Vec<bool> = half.take(1000).collect();
+        let mut again = random;
+        again.set_mix(0.5);
+        assert_eq!(again.take(1000).collect::<Vec<_>>(), replay);
+
+        assert!(CrossfadePlayer::try_new((8, 9, 0), (8, 3, 0)).is_err());
+    }
+
+    #[test]
+    fn phrase_applies_variation_rules() {
+        let rotated = phrase(8, 3, 3, VariationRule::RotateEachBar(3));
+        assert_eq!(
+            rotated,
+            [euclidean(8, 3, 0), euclidean(8, 3, 3), euclidean(8, 3, 6)]
+        );
+        let alternating = phrase(16, 4, 4, VariationRule::AlternateDensity(4, 6));
+        let pulses: Vec<usize> = alternating
+            .iter()
+            .map(|bar| bar.iter().filter(|&&p| p).count())
+            .collect();
+        assert_eq!(pulses, [4, 6, 4, 6]);
+        assert_eq!(phrase(8, 5, 2, VariationRule::FillLastBar)[1], [true; 8]);
+        assert!(phrase(8, 3, 0, VariationRule::FillLastBar).is_empty());
+    }
+
+    #[test]
+    fn search_improves_objective_and_keeps_pulses() {
+        // Reward onsets in the first half of the bar.
+        let front_loaded = |p: &Pattern| p.iter().take(8).filter(|&&s| s).count() as f64;
+        let start = front_loaded(&Pattern::from(euclidean(16, 5, 0)));
+        let (best, score) = search(16, 5, front_loaded, 2000, 3);
+
+        assert_eq!(best.pulse_count(), 5);
+        assert!(score > start);
+        assert_eq!(score, 5.0);
+        assert_eq!(search(16, 5, front_loaded, 2000, 3), (best, score));
+    }
+
+    #[test]
+    fn kit_randomizer_respects_locks_and_history() {
+        let kit = || {
+            let mut edited = PatternEditor::euclidean(8, 3, 0);
+            edited.toggle_step(1);
+            vec![
+                PatternEditor::euclidean(16, 4, 0),
+                edited,
+                PatternEditor::euclidean(12, 5, 0),
+            ]
+        };
+        let mut a = kit();
+        let mut dice = KitRandomizer::new(11);
+        dice.lock(2);
+        assert!(dice.is_locked(2));
+        for _ in 0..8 {
+            dice.roll(&mut a);
+        }
+        assert_eq!(a[2].params(), Some((12, 5, 0)));
+        assert_eq!(a[1].params().map(|p| p.0), Some(8));
+        assert!((1..=8).contains(&a[1].params().unwrap().1));
+        while a[1].undo().is_some() {}
+        assert_eq!(a[1].pattern().to_string_with('x', '.'), "x..x..x.");
+
+        // Same seed, same rolls; with both parameters off nothing changes.
+        let mut b = kit();
+        let mut same = KitRandomizer::new(11);
+        same.lock(2);
+        let mut c = kit();
+        assert_eq!(same.roll(&mut b), KitRandomizer::new(11).roll(&mut c)[..2]);
+        dice.unlock(2);
+        assert!(!dice.is_locked(2));
+        let mut frozen = KitRandomizer::new(1)
+            .with_density(false)
+            .with_rotation(false);
+        assert!(frozen.roll(&mut kit()[..1]).is_empty());
+        let mut edited = kit();
+        assert!(frozen.roll(&mut edited).is_empty());
+        assert_eq!(edited[1].pattern().to_string_with('x', '.'), "xx.x..x.");
+        assert!(edited[1].undo().is_some() && !edited[1].can_undo());
+
+        // Roles keep rolls within their density, capped at the step count.
+        let mut roles = KitRandomizer::new(5)
+            .with_role(0, TrackRole::Snare)
+            .with_role(1, TrackRole::ClosedHat);
+        let mut d = kit();
+        for _ in 0..16 {
+            roles.roll(&mut d);
+            assert!((1..=3).contains(&d[0].params().unwrap().1));
+            assert!((6..=8).contains(&d[1].params().unwrap().1));
+        }
+    }
+
+    #[test]
+    fn search_trivial_patterns() {
+        let (best, _) = search(8, 8, |_| 0.0, 100, 0);
+        assert_eq!(best.into_vec(), vec![true; 8]);
+        let (best, _) = search(8, 3, |_| 0.0, 0, 0);
+        assert_eq!(best.into_vec(), euclidean(8, 3, 0));
+    }
+}
@@ -0,0 +1,346 @@
+//! Interchangeable rhythm generation algorithms.
+//!
+//! [`RhythmGenerator`] is the common interface: given steps, pulses and a
+//! rotation, produce a [`Pattern`]. The crate's own [`Bjorklund`] algorithm
+//! is one implementation among several, and anything taking a
+//! `&dyn RhythmGenerator` (such as `SequencerTrack::regenerate`) accepts
+//! user-defined ones too.
+//!
+//! ```
+//! use euclidean_rhythm::RhythmGenerator;
+//! use euclidean_rhythm::generator::{BalancedPolygon, Bjorklund, Bresenham, Clustered};
+//!
+//! let generators: [&dyn RhythmGenerator; 4] = [&Bjorklund, &Bresenham, &Clustered, &BalancedPolygon];
+//! let drawn: Vec<String> = generators
+//!     .iter()
+//!     .map(|g| g.generate(12, 5, 0).to_string_with('x', '.'))
+//!     .collect();
+//! assert_eq!(drawn, ["x..x.x..x.x.", "x..x.x..x.x.", "xxxxx.......", "xx..x..xx..."]);
+//! ```
+
+use crate::error::{Error, Result, check_params, unwrap_or_panic};
+use crate::pattern::Pattern;
+#[cfg(any(feature = "analysis", feature = "sequencer"))]
+use crate::rng::Rng;
+use crate::try_euclidean;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+/// Most search steps [`BalancedPolygon`] takes before giving up.
+const SEARCH_BUDGET: usize = 10_000;
+
+/// An algorithm placing `pulses` onsets over `steps` steps.
+pub trait RhythmGenerator {
+    /// Generates the rhythm, rotated left by `rotation` steps (wrapping, as
+    /// in [`euclidean`](crate::euclidean)).
+    ///
+    /// # Panics
+    /// Implementations panic if `steps == 0` or `pulses > steps`.
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern;
+
+    /// Like [`generate`](Self::generate), but reports invalid parameters as
+    /// an error.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `pulses > steps`.
+    fn try_generate(&self, steps: u8, pulses: u8, rotation: u8) -> Result<Pattern> {
+        check_params(usize::from(steps), usize::from(pulses))?;
+        Ok(self.generate(steps, pulses, rotation))
+    }
+}
+
+/// Bjorklund's algorithm, as used by [`euclidean`](crate::euclidean).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Bjorklund;
+
+impl RhythmGenerator for Bjorklund {
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern {
+        Pattern::new(unwrap_or_panic(try_euclidean(steps, pulses, rotation)))
+    }
+}
+
+/// Bresenham's line algorithm: step `i` is a pulse when `i * pulses`
+/// wraps past a multiple of `steps`. Always a rotation of the Euclidean
+/// rhythm, starting on a pulse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Bresenham;
+
+impl RhythmGenerator for Bresenham {
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern {
+        unwrap_or_panic(check_params(usize::from(steps), usize::from(pulses)));
+        let (steps, pulses) = (usize::from(steps), usize::from(pulses));
+        let pattern = (0..steps).map(|i| i * pulses % steps < pulses).collect();
+        rotated(pattern, rotation)
+    }
+}
+
+/// All pulses together at the start of the bar: the least even rhythm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Clustered;
+
+impl RhythmGenerator for Clustered {
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern {
+        unwrap_or_panic(check_params(usize::from(steps), usize::from(pulses)));
+        let pattern = (0..steps).map(|i| i < pulses).collect();
+        rotated(pattern, rotation)
+    }
+}
+
+/// A union of non-overlapping regular polygons on the circle of steps, so
+/// the onsets are perfectly balanced (their centre of mass is the centre of
+/// the circle) even when they are not evenly spaced.
+///
+/// Larger polygons are preferred, and the first is anchored on step 0.
+/// Some counts cannot be balanced, such as a single pulse, or fewer pulses
+/// than steps when the step count is prime; those fall back to
+/// [`Bjorklund`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BalancedPolygon;
+
+impl RhythmGenerator for BalancedPolygon {
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern {
+        unwrap_or_panic(check_params(usize::from(steps), usize::from(pulses)));
+        let (n, k) = (usize::from(steps), usize::from(pulses));
+        let sides: Vec<usize> = (2..=n).rev().filter(|d| n.is_multiple_of(*d)).collect();
+        let mut pattern = vec![false; n];
+        let mut budget = SEARCH_BUDGET;
+        if place_polygons(&mut pattern, k, &sides, &mut budget) {
+            rotated(pattern, rotation)
+        } else {
+            Bjorklund.generate(steps, pulses, rotation)
+        }
+    }
+}
+
+/// Adds regular polygons with one of `sides` vertices to `pattern` until
+/// `remaining` more onsets are placed, backtracking on overlaps.
+fn place_polygons(
+    pattern: &mut [bool],
+    remaining: usize,
+    sides: &[usize],
+    budget: &mut usize,
+) -> bool {
+    if remaining == 0 {
+        return true;
+    }
+    let n = pattern.len();
+    for (i, &side) in sides.iter().enumerate() {
+        if side > remaining {
+            continue;
+        }
+        let spacing = n / side;
+        for offset in 0..spacing {
+            if *budget == 0 {
+                return false;
+            }
+            *budget -= 1;
+            let vertices = (0..side).map(|v| offset + v * spacing);
+            if vertices.clone().any(|step| pattern[step]) {
+                continue;
+            }
+            vertices.clone().for_each(|step| pattern[step] = true);
+            // Only same-size or smaller polygons follow, so each set of
+            // polygons is tried once.
+            if place_polygons(pattern, remaining - side, &sides[i..], budget) {
+                return true;
+            }
+            vertices.for_each(|step| pattern[step] = false);
+        }
+    }
+    false
+}
+
+/// Pulses on randomly chosen steps. The same seed and arguments always give
+/// the same pattern.
+#[cfg(any(feature = "analysis", feature = "sequencer"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Random {
+    /// Seed for the step choice.
+    pub seed: u64,
+}
+
+#[cfg(any(feature = "analysis", feature = "sequencer"))]
+impl RhythmGenerator for Random {
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern {
+        unwrap_or_panic(check_params(usize::from(steps), usize::from(pulses)));
+        // Depends on the arguments, so each size gets its own draw.
+        let mut rng = Rng::for_cycle(self.seed, u64::from(steps) << 8 | u64::from(pulses));
+        let mut order: Vec<usize> = (0..usize::from(steps)).collect();
+        let mut pattern = vec![false; order.len()];
+        for i in 0..usize::from(pulses) {
+            let pick = i + rng.below(order.len() - i);
+            order.swap(i, pick);
+            pattern[order[i]] = true;
+        }
+        rotated(pattern, rotation)
+    }
+}
+
+/// One of the crate's own generators, as a value that can be stored,
+/// compared and saved, e.g. as a sequencer track's algorithm.
+///
+/// Parses from and displays as `bjorklund`, `bresenham`, `clustered`,
+/// `balanced-polygon` and `random:<seed>` (`random` alone for seed 0).
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::RhythmGenerator;
+/// use euclidean_rhythm::generator::{Algorithm, Clustered};
+///
+/// let algorithm: Algorithm = "clustered".parse()?;
+/// assert_eq!(algorithm.generate(8, 3, 0), Clustered.generate(8, 3, 0));
+/// assert_eq!(algorithm.to_string(), "clustered");
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// [`Bjorklund`].
+    #[default]
+    Bjorklund,
+    /// [`Bresenham`].
+    Bresenham,
+    /// [`Clustered`].
+    Clustered,
+    /// [`BalancedPolygon`].
+    BalancedPolygon,
+    /// [`Random`] with the given seed.
+    #[cfg(any(feature = "analysis", feature = "sequencer"))]
+    Random(u64),
+}
+
+impl RhythmGenerator for Algorithm {
+    fn generate(&self, steps: u8, pulses: u8, rotation: u8) -> Pattern {
+        match *self {
+            Algorithm::Bjorklund => Bjorklund.generate(steps, pulses, rotation),
+            Algorithm::Bresenham => Bresenham.generate(steps, pulses, rotation),
+            Algorithm::Clustered => Clustered.generate(steps, pulses, rotation),
+            Algorithm::BalancedPolygon => BalancedPolygon.generate(steps, pulses, rotation),
+            #[cfg(any(feature = "analysis", feature = "sequencer"))]
+            Algorithm::Random(seed) => Random { seed }.generate(steps, pulses, rotation),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bjorklund" => Ok(Algorithm::Bjorklund),
+            "bresenham" => Ok(Algorithm::Bresenham),
+            "clustered" => Ok(Algorithm::Clustered),
+            "balanced-polygon" => Ok(Algorithm::BalancedPolygon),
+            #[cfg(any(feature = "analysis", feature = "sequencer"))]
+            "random" => Ok(Algorithm::Random(0)),
+            #[cfg(any(feature = "analysis", feature = "sequencer"))]
+            name if name.starts_with("random:") => name["random:".len()..]
+                .parse()
+                .map(Algorithm::Random)
+                .map_err(|_| invalid("malformed random seed")),
+            _ => Err(invalid("unknown generator")),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Bjorklund => f.write_str("bjorklund"),
+            Algorithm::Bresenham => f.write_str("bresenham"),
+            Algorithm::Clustered => f.write_str("clustered"),
+            Algorithm::BalancedPolygon => f.write_str("balanced-polygon"),
+            #[cfg(any(feature = "analysis", feature = "sequencer"))]
+            Algorithm::Random(seed) => write!(f, "random:{seed}"),
+        }
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "generator",
+        reason,
+    }
+}
+
+/// Rotates a freshly generated pattern left, as [`euclidean`](crate::euclidean) does.
+fn rotated(mut pattern: Vec<bool>, rotation: u8) -> Pattern {
+    let len = pattern.len();
+    pattern.rotate_left(usize::from(rotation) % len);
+    Pattern::new(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn every_generator_places_the_pulses() {
+        #[allow(unused_mut)]
+        let mut generators: Vec<&dyn RhythmGenerator> =
+            vec![&Bjorklund, &Bresenham, &Clustered, &BalancedPolygon];
+        #[cfg(any(feature = "analysis", feature = "sequencer"))]
+        generators.push(&Random { seed: 7 });
+        for generator in generators {
+            for steps in 1..=24 {
+                for pulses in 0..=steps {
+                    let pattern = generator.generate(steps, pulses, 3);
+                    assert_eq!(pattern.len(), usize::from(steps));
+                    assert_eq!(pattern.pulse_count(), usize::from(pulses));
+                }
+            }
+            assert!(generator.try_generate(4, 5, 0).is_err());
+        }
+        assert_eq!(*Bjorklund.generate(16, 5, 2), euclidean(16, 5, 2));
+        #[cfg(any(feature = "analysis", feature = "sequencer"))]
+        assert_eq!(
+            Random { seed: 7 }.generate(16, 5, 0),
+            Random { seed: 7 }.generate(16, 5, 0)
+        );
+    }
+
+    #[test]
+    fn algorithms_parse_and_display() {
+        #[allow(unused_mut)]
+        let mut algorithms = vec![
+            Algorithm::Bjorklund,
+            Algorithm::Bresenham,
+            Algorithm::Clustered,
+            Algorithm::BalancedPolygon,
+        ];
+        #[cfg(any(feature = "analysis", feature = "sequencer"))]
+        {
+            algorithms.push(Algorithm::Random(42));
+            assert_eq!("Random".parse(), Ok(Algorithm::Random(0)));
+            assert!("random:x".parse::<Algorithm>().is_err());
+        }
+        for algorithm in algorithms {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+        assert!("euclid".parse::<Algorithm>().is_err());
+        assert_eq!(
+            Algorithm::default().generate(12, 5, 1),
+            Bjorklund.generate(12, 5, 1)
+        );
+    }
+
+    #[test]
+    fn balanced_polygons_cancel_out() {
+        // 5 of 12 is a triangle plus a pair.
+        let pattern = BalancedPolygon.generate(12, 5, 0);
+        let (x, y) = pattern
+            .iter()
+            .enumerate()
+            .filter(|(_, pulse)| **pulse)
+            .map(|(i, _)| core::f64::consts::TAU * i as f64 / 12.0)
+            .fold((0.0, 0.0), |(x, y), angle| {
+                (x + angle.cos(), y + angle.sin())
+            });
+        assert!(x.abs() < 1e-9 && y.abs() < 1e-9);
+        // Prime step counts cannot be balanced.
+        assert_eq!(*BalancedPolygon.generate(7, 3, 0), euclidean(7, 3, 0));
+    }
+}
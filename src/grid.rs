@@ -0,0 +1,109 @@
+//! Euclidean distributions in two dimensions.
+//!
+//! [`euclidean_2d`] spreads points over a grid that wraps around in both
+//! directions, such as an LED matrix driven as a torus: each row receives a
+//! Euclidean share of the points, each row is itself a Euclidean rhythm, and
+//! rows are staggered so neighbouring rows interleave.
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use crate::try_euclidean;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Distributes `points` active cells as evenly as possible over a `rows` by
+/// `cols` toroidal grid, returned as one `Vec<bool>` per row.
+///
+/// Row counts differ by at most one, with the fuller rows spread by
+/// E(`points % rows`, `rows`). Row `r` holds E(k, `cols`) shifted right by
+/// `r * cols / (2 * k)` steps, half its onset spacing per row, which packs
+/// the points in a hexagonal lattice when the counts allow.
+///
+/// # Panics
+/// Panics if `rows` or `cols` is zero or `points > rows * cols`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean_2d, pattern_to_string};
+///
+/// let grid = euclidean_2d(4, 8, 8);
+/// let rows: Vec<String> = grid.iter().map(|row| pattern_to_string(row, 'x', '.')).collect();
+/// assert_eq!(rows, ["x...x...", "..x...x.", "x...x...", "..x...x."]);
+/// ```
+pub fn euclidean_2d(rows: u8, cols: u8, points: u16) -> Vec<Vec<bool>> {
+    unwrap_or_panic(try_euclidean_2d(rows, cols, points))
+}
+
+/// Like [`euclidean_2d`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `rows` or `cols` is zero and
+/// [`Error::PulsesExceedSteps`] if `points > rows * cols`.
+pub fn try_euclidean_2d(rows: u8, cols: u8, points: u16) -> Result<Vec<Vec<bool>>> {
+    let cells = usize::from(rows) * usize::from(cols);
+    if cells == 0 {
+        return Err(Error::ZeroSteps);
+    }
+    if usize::from(points) > cells {
+        return Err(Error::PulsesExceedSteps {
+            pulses: usize::from(points),
+            steps: cells,
+        });
+    }
+
+    let (base, extra) = (points / u16::from(rows), points % u16::from(rows));
+    // Both fit in a u8: base < cols here unless every cell is full.
+    let fuller_rows = try_euclidean(rows, extra as u8, 0)?;
+    let mut grid = Vec::with_capacity(usize::from(rows));
+    for (row, fuller) in fuller_rows.into_iter().enumerate() {
+        let pulses = (base + u16::from(fuller)) as u8;
+        if pulses == 0 {
+            grid.push(vec![false; usize::from(cols)]);
+            continue;
+        }
+        let shift = row * usize::from(cols) / (2 * usize::from(pulses)) % usize::from(cols);
+        let rotation = (usize::from(cols) - shift) % usize::from(cols);
+        grid.push(try_euclidean(cols, pulses, rotation as u8)?);
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column_counts(grid: &[Vec<bool>]) -> Vec<usize> {
+        (0..grid[0].len())
+            .map(|c| grid.iter().filter(|row| row[c]).count())
+            .collect()
+    }
+
+    #[test]
+    fn spreads_points_over_rows_and_columns() {
+        for points in 0..=64 {
+            let grid = euclidean_2d(8, 8, points);
+            let row_counts: Vec<usize> = grid
+                .iter()
+                .map(|r| r.iter().filter(|&&p| p).count())
+                .collect();
+            assert_eq!(row_counts.iter().sum::<usize>(), usize::from(points));
+            assert!(row_counts.iter().max().unwrap() - row_counts.iter().min().unwrap() <= 1);
+        }
+        assert_eq!(
+            column_counts(&euclidean_2d(8, 8, 16)),
+            [4, 0, 4, 0, 4, 0, 4, 0]
+        );
+        assert_eq!(euclidean_2d(3, 255, 765).concat(), vec![true; 765]);
+    }
+
+    #[test]
+    fn rejects_empty_and_overfull_grids() {
+        assert_eq!(try_euclidean_2d(0, 4, 0), Err(Error::ZeroSteps));
+        assert_eq!(
+            try_euclidean_2d(2, 2, 5),
+            Err(Error::PulsesExceedSteps {
+                pulses: 5,
+                steps: 4
+            })
+        );
+    }
+}
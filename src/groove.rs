@@ -0,0 +1,164 @@
+//! Groove templates taken from human performances.
+//!
+//! [`extract_groove`] averages how early or late, and how loud or soft, a
+//! performance plays each step of its grid. The resulting [`GrooveTemplate`]
+//! can then be laid over any pattern: [`GrooveTemplate::apply`] gives the
+//! same `(step, offset)` pairs as the crate's other timing functions (such as
+//! `onsets_to_pattern_offsets`), so a generated Euclidean rhythm picks up the
+//! feel of a recorded drummer.
+
+/// The average per-step feel of a performance, from [`extract_groove`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrooveTemplate {
+    /// Mean timing offset of each step, in steps (negative is early); 0 for
+    /// steps no onset landed on.
+    pub offsets: Vec<f64>,
+    /// Mean velocity of each step minus the mean velocity of the whole
+    /// performance; 0 for steps no onset landed on.
+    pub velocities: Vec<f64>,
+    /// Number of onsets averaged into each step.
+    pub counts: Vec<usize>,
+}
+
+impl GrooveTemplate {
+    /// Number of steps in the template.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns `true` for a template of no steps.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Times the onsets of `pattern` with the template: one `(step, offset)`
+    /// pair per onset, in step order. The template repeats over longer
+    /// patterns; an empty template leaves every offset at 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::{euclidean, extract_groove};
+    ///
+    /// // A shuffle: every second step plays a third of a step late.
+    /// let groove = extract_groove(&[(0.0, 100), (1.33, 80), (2.0, 100), (3.33, 80)], 2);
+    /// let timed = groove.apply(&euclidean(4, 4, 0));
+    /// assert_eq!(timed[0], (0, 0.0));
+    /// assert!((timed[1].1 - 0.33).abs() < 1e-9);
+    /// ```
+    pub fn apply(&self, pattern: &[bool]) -> Vec<(usize, f64)> {
+        (0..pattern.len())
+            .filter(|&step| pattern[step])
+            .map(|step| match self.len() {
+                0 => (step, 0.0),
+                len => (step, self.offsets[step % len]),
+            })
+            .collect()
+    }
+
+    /// The velocity of `step` for a pattern played at `base` velocity, with
+    /// the template's deviation added (repeating over longer patterns) and
+    /// clamped to 1-127.
+    pub fn velocity(&self, step: usize, base: u8) -> u8 {
+        let deviation = match self.len() {
+            0 => 0.0,
+            len => self.velocities[step % len],
+        };
+        (f64::from(base) + deviation).round().clamp(1.0, 127.0) as u8
+    }
+}
+
+/// Averages the timing and velocity of a performance over a loop of `grid`
+/// steps.
+///
+/// Each onset is a `(position, velocity)` pair, with the position in steps
+/// from the start of the performance: a MIDI tick divided by the ticks per
+/// step, an onset time divided by the step duration, or `step + offset` from
+/// a `(step, offset)` pair. Every onset counts towards its nearest step,
+/// folded onto the loop, so several recorded bars average into one.
+/// Negative and non-finite positions are ignored, and a `grid` of 0 gives
+/// an empty template.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::extract_groove;
+///
+/// // Two bars of a 4-step loop: step 1 is pushed, then dragged, and hit harder.
+/// let take = [(0.0, 90), (0.9, 110), (4.02, 90), (5.1, 120)];
+/// let groove = extract_groove(&take, 4);
+/// assert_eq!(groove.counts, [2, 2, 0, 0]);
+/// assert!((groove.offsets[0] - 0.01).abs() < 1e-9);
+/// assert!(groove.offsets[1].abs() < 1e-9); // -0.1 and +0.1 cancel out
+/// assert_eq!(groove.velocities[..2], [-12.5, 12.5]);
+/// ```
+pub fn extract_groove(onsets: &[(f64, u8)], grid: usize) -> GrooveTemplate {
+    let mut offsets = vec![0.0; grid];
+    let mut velocities = vec![0.0; grid];
+    let mut counts = vec![0; grid];
+    if grid == 0 {
+        return GrooveTemplate {
+            offsets,
+            velocities,
+            counts,
+        };
+    }
+    let mut total_velocity = 0.0;
+    let mut total = 0usize;
+    for &(position, velocity) in onsets {
+        if !position.is_finite() || position < 0.0 {
+            continue;
+        }
+        let nearest = position.round();
+        let step = nearest as usize % grid;
+        offsets[step] += position - nearest;
+        velocities[step] += f64::from(velocity);
+        counts[step] += 1;
+        total_velocity += f64::from(velocity);
+        total += 1;
+    }
+    let mean_velocity = if total == 0 {
+        0.0
+    } else {
+        total_velocity / total as f64
+    };
+    for step in 0..grid {
+        if counts[step] > 0 {
+            let count = counts[step] as f64;
+            offsets[step] /= count;
+            velocities[step] = velocities[step] / count - mean_velocity;
+        }
+    }
+    GrooveTemplate {
+        offsets,
+        velocities,
+        counts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn groove_round_trips_onto_a_new_pattern() {
+        let take: Vec<(f64, u8)> = (0..32)
+            .map(|i| {
+                let late = if i % 2 == 1 { 0.25 } else { 0.0 };
+                (f64::from(i) + late, if i % 4 == 0 { 120 } else { 80 })
+            })
+            .chain([(-1.0, 127), (f64::NAN, 127)])
+            .collect();
+        let groove = extract_groove(&take, 8);
+        assert_eq!(groove.counts, [4; 8]);
+        assert_eq!(groove.offsets, [0.0, 0.25, 0.0, 0.25, 0.0, 0.25, 0.0, 0.25]);
+        // Downbeats are 30 over the mean of 90, the rest 10 under.
+        assert_eq!(groove.velocity(0, 90), 120);
+        assert_eq!(groove.velocity(17, 100), 90);
+        assert_eq!(groove.velocity(0, 120), 127);
+
+        let timed = groove.apply(&euclidean(16, 5, 0));
+        let late: Vec<usize> = timed.iter().filter(|t| t.1 > 0.0).map(|t| t.0).collect();
+        assert_eq!(late, [3, 9]);
+        assert_eq!(extract_groove(&take, 0).apply(&[true]), [(0, 0.0)]);
+    }
+}
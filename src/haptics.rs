@@ -0,0 +1,107 @@
+//! Vibration timings for haptic playback.
+//!
+//! Phone vibration APIs take a list of waits and buzzes in milliseconds
+//! (Android's `VibrationEffect.createWaveform`, or Core Haptics events on
+//! iOS). [`to_haptics`] does the timing math for a pattern so a silent
+//! metronome or rhythm trainer can feel the groove.
+
+use alloc::vec::Vec;
+
+/// The longest buzz, in milliseconds; short taps read as clearer pulses.
+const MAX_DURATION_MS: u32 = 40;
+
+/// Intensity of onsets on the beat and off it.
+const ON_BEAT: f32 = 1.0;
+const OFF_BEAT: f32 = 0.5;
+
+/// One vibration, as produced by [`to_haptics`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HapticPulse {
+    /// Silence before the pulse, since the end of the previous one (or the
+    /// start of the loop for the first).
+    pub delay_ms: u32,
+    /// How long to vibrate.
+    pub duration_ms: u32,
+    /// Strength from 0.0 to 1.0.
+    pub intensity: f32,
+}
+
+/// Converts one loop of `pattern` into vibrations at `bpm`, with
+/// sixteenth-note steps.
+///
+/// Each onset buzzes for 40 ms, or half a step if that is shorter. Onsets
+/// on the beat (every fourth step) play at full intensity and the others at
+/// half, so the pulse stays clear. Times are rounded from the exact onset
+/// positions rather than accumulated, so rounding never drifts; to loop,
+/// wait out the rest of the loop (`pattern.len()` steps) after the last
+/// pulse. Returns nothing for a tempo that is not positive.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, to_haptics};
+///
+/// // Tresillo at 120 BPM: steps of 125 ms, onsets at 0, 375 and 750 ms.
+/// let pulses = to_haptics(&euclidean(8, 3, 0), 120.0);
+/// let delays: Vec<u32> = pulses.iter().map(|p| p.delay_ms).collect();
+/// assert_eq!(delays, [0, 335, 335]);
+/// assert_eq!(pulses[0].duration_ms, 40);
+/// assert_eq!(pulses[1].intensity, 0.5);
+/// ```
+pub fn to_haptics(pattern: &[bool], bpm: f64) -> Vec<HapticPulse> {
+    if bpm.is_nan() || bpm <= 0.0 {
+        return Vec::new();
+    }
+    let step_ms = 60_000.0 / bpm / 4.0;
+    // Rounded to the nearest millisecond (the values are never negative).
+    let at = |step: usize| (step as f64 * step_ms + 0.5) as u32;
+    let duration_ms = ((step_ms / 2.0 + 0.5) as u32).clamp(1, MAX_DURATION_MS);
+
+    let mut previous_end = 0;
+    (0..pattern.len())
+        .filter(|&step| pattern[step])
+        .map(|step| {
+            let start = at(step);
+            let pulse = HapticPulse {
+                delay_ms: start.saturating_sub(previous_end),
+                duration_ms,
+                intensity: if step.is_multiple_of(4) {
+                    ON_BEAT
+                } else {
+                    OFF_BEAT
+                },
+            };
+            previous_end = start.max(previous_end) + duration_ms;
+            pulse
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn timings_follow_the_tempo() {
+        // 300 BPM: 50 ms steps, so pulses shrink to half a step.
+        let pulses = to_haptics(&[true, true, false, false, true], 300.0);
+        let timings: Vec<(u32, u32)> = pulses.iter().map(|p| (p.delay_ms, p.duration_ms)).collect();
+        assert_eq!(timings, [(0, 25), (25, 25), (125, 25)]);
+        assert_eq!(pulses[2].intensity, 1.0);
+
+        // Absolute onset times never drift: 16 steps at 97 BPM.
+        let pulses = to_haptics(&euclidean(16, 16, 0), 97.0);
+        let last_start: u32 = pulses
+            .iter()
+            .map(|p| p.delay_ms + p.duration_ms)
+            .sum::<u32>()
+            - pulses[15].duration_ms;
+        assert_eq!(
+            last_start,
+            (15.0 * 60_000.0 / 97.0 / 4.0_f64).round() as u32
+        );
+
+        assert!(to_haptics(&[true], 0.0).is_empty());
+        assert!(to_haptics(&[false; 4], 120.0).is_empty());
+    }
+}
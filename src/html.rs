@@ -0,0 +1,208 @@
+//! HTML step-grid export.
+//!
+//! Renders a [`PatternBank`] as a self-contained HTML snippet (inline `<style>`
+//! and, optionally, a small `<script>` playhead) that can be pasted into web
+//! documentation or an email body.
+
+use crate::bank::PatternBank;
+use std::fmt::Write;
+
+/// Options controlling [`to_html`] output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlOptions {
+    /// Element id of the wrapping `<div>`; all CSS rules are scoped to it so
+    /// several grids can live on one page.
+    pub id: String,
+    /// Width and height of a step cell in pixels.
+    pub cell_size: u32,
+    /// CSS color of pulse cells.
+    pub pulse_color: String,
+    /// CSS color of rest cells.
+    pub rest_color: String,
+    /// Adds a header row with 1-based step numbers.
+    pub step_numbers: bool,
+    /// Number of steps per beat; every beat boundary gets a thicker border.
+    /// A bank's own resolution takes precedence.
+    pub steps_per_beat: usize,
+    /// When set, emits an inline script that animates a playhead at this tempo.
+    /// Leave as `None` for email, where scripts are stripped anyway.
+    pub playhead_bpm: Option<f64>,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            id: "euclid-grid".to_string(),
+            cell_size: 16,
+            pulse_color: "#222".to_string(),
+            rest_color: "#eee".to_string(),
+            step_numbers: false,
+            steps_per_beat: 4,
+            playhead_bpm: None,
+        }
+    }
+}
+
+/// Renders a bank as an HTML table with one row per track.
+///
+/// Tracks shorter than the longest one are padded with empty cells. Track
+/// names are HTML-escaped.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, to_html, HtmlOptions, PatternBank};
+///
+/// let bank = PatternBank::new().with_track("kick", euclidean(8, 3, 0));
+/// let html = to_html(&bank, &HtmlOptions::default());
+/// assert!(html.contains("<table"));
+/// assert_eq!(html.matches("class=\"on\"").count(), 3);
+/// ```
+pub fn to_html(bank: &PatternBank, options: &HtmlOptions) -> String {
+    let id = escape(&options.id);
+    let steps = bank.steps();
+    let beat = bank
+        .resolution()
+        .map_or(options.steps_per_beat, |r| usize::from(r.steps_per_beat()))
+        .max(1);
+    let mut out = String::new();
+
+    let _ = write!(
+        out,
+        "<div id=\"{id}\">\n<style>\n\
+         #{id} table {{ border-collapse: collapse; font-family: monospace; font-size: 12px; }}\n\
+         #{id} td, #{id} th {{ width: {size}px; height: {size}px; padding: 0; border: 1px solid #ccc; text-align: center; }}\n\
+         #{id} th.name {{ width: auto; padding: 0 6px; text-align: right; font-weight: normal; }}\n\
+         #{id} td.on {{ background: {pulse}; }}\n\
+         #{id} td.off {{ background: {rest}; }}\n\
+         #{id} .beat {{ border-left: 2px solid #888; }}\n\
+         #{id} .play {{ outline: 2px solid #e33; outline-offset: -2px; }}\n\
+         </style>\n<table>\n",
+        size = options.cell_size,
+        pulse = escape(&options.pulse_color),
+        rest = escape(&options.rest_color),
+    );
+
+    if options.step_numbers {
+        out.push_str("<tr><th></th>");
+        for step in 0..steps {
+            let _ = write!(out, "<th{}>{}</th>", beat_class(step, beat, ""), step + 1);
+        }
+        out.push_str("</tr>\n");
+    }
+
+    for track in bank.tracks() {
+        let _ = write!(out, "<tr><th class=\"name\">{}</th>", escape(&track.name));
+        for step in 0..steps {
+            let class = match track.pattern.get(step) {
+                Some(true) => "on",
+                Some(false) => "off",
+                None => "",
+            };
+            let _ = write!(
+                out,
+                "<td{} data-step=\"{step}\"></td>",
+                beat_class(step, beat, class)
+            );
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+
+    if let Some(bpm) = options.playhead_bpm.filter(|b| *b > 0.0) {
+        let step_ms = 60_000.0 / bpm / beat as f64;
+        let _ = write!(
+            out,
+            "<script>\n(function() {{\n\
+             var root = document.getElementById(\"{id}\"), step = 0, steps = {steps};\n\
+             if (!root || !steps) return;\n\
+             setInterval(function() {{\n\
+             root.querySelectorAll(\".play\").forEach(function(c) {{ c.classList.remove(\"play\"); }});\n\
+             root.querySelectorAll(\"td[data-step='\" + step + \"']\").forEach(function(c) {{ c.classList.add(\"play\"); }});\n\
+             step = (step + 1) % steps;\n\
+             }}, {step_ms:.3});\n\
+             }})();\n</script>\n"
+        );
+    }
+
+    out.push_str("</div>\n");
+    out
+}
+
+/// Builds the `class` attribute for a cell, marking beat boundaries.
+fn beat_class(step: usize, steps_per_beat: usize, class: &str) -> String {
+    let beat = step > 0 && step.is_multiple_of(steps_per_beat);
+    match (class.is_empty(), beat) {
+        (true, false) => String::new(),
+        (true, true) => " class=\"beat\"".to_string(),
+        (false, false) => format!(" class=\"{class}\""),
+        (false, true) => format!(" class=\"{class} beat\""),
+    }
+}
+
+/// Escapes the characters that are significant in HTML text and attributes.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn renders_one_row_per_track() {
+        let bank = PatternBank::new()
+            .with_track("kick", euclidean(8, 3, 0))
+            .with_track("snare", euclidean(8, 2, 2));
+        let html = to_html(&bank, &HtmlOptions::default());
+
+        assert_eq!(html.matches("<tr>").count(), 2);
+        assert_eq!(html.matches("class=\"on").count(), 5);
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn pads_short_tracks_and_escapes_names() {
+        let bank = PatternBank::new()
+            .with_track("<b>&", vec![true, false])
+            .with_track("long", vec![false; 4]);
+        let html = to_html(&bank, &HtmlOptions::default());
+
+        assert!(html.contains("&lt;b&gt;&amp;"));
+        assert_eq!(html.matches("data-step").count(), 8);
+    }
+
+    #[test]
+    fn optional_step_numbers_and_playhead() {
+        let bank = PatternBank::new().with_track("kick", euclidean(4, 1, 0));
+        let options = HtmlOptions {
+            step_numbers: true,
+            playhead_bpm: Some(120.0),
+            ..HtmlOptions::default()
+        };
+        let html = to_html(&bank, &options);
+
+        assert!(html.contains("<th>4</th>"));
+        assert!(html.contains("<script>"));
+        assert!(html.contains("125.000"));
+
+        // A triplet kit moves the beat lines and slows the playhead.
+        let triplets = bank
+            .with_track("hat", euclidean(6, 6, 0))
+            .with_resolution(crate::Resolution::EighthTriplets);
+        let html = to_html(&triplets, &options);
+        assert!(html.contains("<th class=\"beat\">4</th>"));
+        assert!(html.contains("166.667"));
+    }
+}
@@ -31,11 +31,215 @@
 //! - **E(5,12)**: Persian rhythm - `[x . . x . x . . x . x .]`
 //! - **E(7,16)**: Brazilian bossa nova - `[x . . x . x . x . . x . x . x .]`
 //!
+//! ## Cargo Features
+//!
+//! The generators (and the pluggable [`generator`] algorithms), [`Pattern`],
+//! [`PatternBank`], [`TrackRole`], [`Meter`], the [`clock`] and [`gate`] utilities and
+//! [`Error`] are always available and need only
+//! `alloc`. Everything else is opt-in:
+//!
+//! - `std` (default): links the standard library; disable it for `no_std`.
+//! - `analysis` (default): generative tools, L-systems, scales, melody and
+//!   velocity mapping, meter inference, necklaces, Lyndon factorization and
+//!   the continued-fraction structure of Euclidean rhythms, shadow and
+//!   contour rhythms, fitting Euclidean parameters to observed or tapped
+//!   patterns, and groove templates extracted from performances.
+//! - `sequencer`: the real-time step sequencer, lookahead scheduling,
+//!   run recording and replay, session save/load and live-coding commands.
+//! - `midi`: Standard MIDI File export and import, and delta-timed MIDI
+//!   messages.
+//! - `audio`: audio- and control-rate rendering, such as onset-synced LFOs.
+//! - `audio-analysis`: onset detection in recordings (enables `analysis`).
+//! - `viz`: polar layout, playhead and intensity helpers, and HTML grids.
+//! - `embedded`: timer-driven trigger scheduling for firmware.
+//! - `osc`: OSC trigger output for the sequencer.
+//! - `websocket`: JSON step and transport events for WebSocket clients.
+//! - `serde`: `Serialize` and `Deserialize` for [`Pattern`], [`PatternBank`],
+//!   [`Track`] and [`Resolution`], for saving presets as JSON. A pattern is
+//!   stored as `{"steps":8,"pulses":3,"rotation":0,"pattern":[...]}` and can
+//!   be loaded from the parameters alone. With `sequencer`, also for
+//!   `Sequencer` sessions, holding the settings of the text session
+//!   format.
+//!
 //! ## References
 //!
 //! - Toussaint, G. (2005). "The Euclidean Algorithm Generates Traditional Musical Rhythms"
 //! - Bjorklund, E. (2003). "The Theory of Rep-Rate Pattern Generation in the SNS Timing System"
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod bank;
+pub mod clock;
+pub mod codegen;
+#[cfg(feature = "analysis")]
+pub mod derived;
+pub mod diff;
+#[cfg(feature = "analysis")]
+pub mod dynamics;
+pub mod editor;
+#[cfg(feature = "embedded")]
+pub mod embedded;
+pub mod error;
+#[cfg(feature = "analysis")]
+pub mod fit;
+pub mod gate;
+#[cfg(feature = "analysis")]
+pub mod generative;
+pub mod generator;
+pub mod grid;
+#[cfg(feature = "analysis")]
+pub mod groove;
+pub mod haptics;
+#[cfg(feature = "viz")]
+pub mod html;
+#[cfg(feature = "sequencer")]
+pub mod live;
+#[cfg(feature = "analysis")]
+pub mod lsystem;
+#[doc(hidden)]
+pub mod macros;
+#[cfg(feature = "analysis")]
+pub mod melody;
+pub mod meter;
+#[cfg(feature = "analysis")]
+pub mod metrics;
+#[cfg(feature = "midi")]
+pub mod midi;
+#[cfg(feature = "analysis")]
+pub mod necklace;
+#[cfg(feature = "audio-analysis")]
+pub mod onsets;
+#[cfg(feature = "osc")]
+pub mod osc;
+pub mod pattern;
+pub mod prelude;
+#[cfg(feature = "sequencer")]
+pub mod recorder;
+pub mod regrid;
+#[cfg(any(feature = "analysis", feature = "sequencer"))]
+#[cfg_attr(not(feature = "analysis"), allow(dead_code))]
+mod rng;
+pub mod role;
+pub mod runs;
+#[cfg(feature = "analysis")]
+pub mod scales;
+#[cfg(feature = "sequencer")]
+pub mod scheduler;
+#[cfg(feature = "sequencer")]
+pub mod sequencer;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "sequencer")]
+pub mod session;
+pub mod sparse;
+pub mod stream;
+#[cfg(feature = "analysis")]
+pub mod structure;
+pub mod strudel;
+#[cfg(feature = "analysis")]
+pub mod tap;
+#[cfg(feature = "sequencer")]
+pub mod trig;
+#[cfg(feature = "viz")]
+pub mod viz;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "audio")]
+pub use audio::{LfoShape, render_lfo};
+pub use bank::{PatternBank, Resolution, Track};
+pub use clock::{ClockDivider, Ppqn};
+pub use codegen::{TableFormat, pattern_table, try_pattern_table};
+#[cfg(feature = "analysis")]
+pub use derived::{contour, shadow};
+pub use diff::{MovedOnset, PatternDiff, diff, morph, morph_path, try_morph, try_morph_path};
+#[cfg(feature = "analysis")]
+pub use dynamics::{VelocityContour, velocity_contour};
+pub use editor::{EditEvent, PatternEditor};
+pub use error::{Error, MAX_INPUT_STEPS, Result};
+#[cfg(feature = "analysis")]
+pub use fit::{EuclideanFit, euclidean_neighbors, fit_euclidean};
+pub use gate::{gate_lengths, legato, render_gates};
+#[cfg(feature = "analysis")]
+pub use generative::{
+    Constraint, CrossfadeMode, CrossfadePlayer, KitRandomizer, ParamWalker, Transition,
+    VariationRule, density_ramp, density_ramp_rotated, generate_constrained, modulated_density,
+    phrase, probability_mask, search, try_probability_mask, vary,
+};
+pub use generator::RhythmGenerator;
+pub use grid::{euclidean_2d, try_euclidean_2d};
+#[cfg(feature = "analysis")]
+pub use groove::{GrooveTemplate, extract_groove};
+pub use haptics::{HapticPulse, to_haptics};
+#[cfg(feature = "viz")]
+pub use html::{HtmlOptions, to_html};
+#[cfg(feature = "sequencer")]
+pub use live::{LiveCommand, LiveQueue};
+#[cfg(feature = "analysis")]
+pub use lsystem::LSystem;
+#[cfg(feature = "analysis")]
+pub use melody::{
+    ArpMode, ChordChange, Isorhythm, MapPolicy, StrumDirection, StrumNote, arpeggiate,
+    harmonic_rhythm, map_onsets, melody, strum,
+};
+pub use meter::Meter;
+#[cfg(feature = "analysis")]
+pub use meter::infer_meter;
+#[cfg(feature = "analysis")]
+pub use metrics::{
+    GrooveMetric, metrical_fit, off_beatness, rank_rotations, syncopation, try_rank_rotations,
+};
+#[cfg(all(feature = "midi", feature = "sequencer"))]
+pub use midi::events_to_midi;
+#[cfg(feature = "midi")]
+pub use midi::{
+    ImportedGrid, MidiOptions, TimedMessage, gates_to_midi, grid_from_smf, grid_from_smf_bytes,
+    melody_to_midi, messages_to_pattern, pattern_to_messages, pattern_to_midi, try_gates_to_midi,
+    try_pattern_to_messages, try_pattern_to_midi,
+};
+#[cfg(feature = "analysis")]
+pub use necklace::{
+    LyndonFactorization, Necklace, canonical_rotation, count_necklaces, iter_necklaces,
+    lyndon_factorization,
+};
+#[cfg(feature = "audio-analysis")]
+pub use onsets::{detect_onsets, onsets_to_pattern, onsets_to_pattern_offsets};
+pub use pattern::{Pattern, StepInfo};
+#[cfg(feature = "sequencer")]
+pub use recorder::{EventLog, LogEntry, LoggedEvent, Recorder, SequencerChange};
+pub use regrid::{CompressStrategy, RegridPolicy, compress_pattern, regrid};
+pub use role::TrackRole;
+pub use runs::{from_runs, parse_runs, runs_to_string, to_runs};
+#[cfg(feature = "analysis")]
+pub use scales::Scale;
+#[cfg(feature = "sequencer")]
+pub use scheduler::{ClockSource, EventScheduler, ScheduledEvent, SystemClock};
+#[cfg(feature = "sequencer")]
+pub use sequencer::{
+    ClockSnapshot, DriftEvent, MAX_BPM, RotationDrift, Sequencer, SequencerTrack, StepEvent,
+    StepListener,
+};
+#[cfg(feature = "sequencer")]
+pub use session::SESSION_VERSION;
+pub use sparse::SparsePattern;
+pub use stream::{Bar, CycleStep, StepStream};
+#[cfg(feature = "analysis")]
+pub use structure::{Group, Structure, structure, try_structure};
+pub use strudel::{from_strudel, to_strudel, try_to_strudel};
+#[cfg(feature = "analysis")]
+pub use tap::{CapturedRhythm, TapCapture};
+#[cfg(feature = "sequencer")]
+pub use trig::{ParamLock, TrigCondition};
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
 /// Generates a Euclidean rhythm pattern using Bjorklund's algorithm.
 ///
 /// Distributes `pulses` as evenly as possible across `steps`, optionally
@@ -50,7 +254,8 @@
 /// A vector of booleans where `true` represents a pulse and `false` represents a rest.
 ///
 /// # Panics
-/// Panics if `pulses > steps` or if `steps == 0`.
+/// Panics if `pulses > steps` or if `steps == 0`. Use [`try_euclidean`] for
+/// parameters that come from user input.
 ///
 /// # Examples
 /// ```
@@ -60,27 +265,253 @@
 /// ```
 #[must_use = "euclidean rhythm pattern should be used"]
 pub fn euclidean(steps: u8, pulses: u8, rotation: u8) -> Vec<bool> {
-    if steps == 0 {
-        panic!("steps == 0");
-    }
-    if pulses > steps {
-        panic!("pulses > steps");
-    }
+    error::unwrap_or_panic(try_euclidean(steps, pulses, rotation))
+}
+
+/// Generates a Euclidean rhythm pattern, reporting invalid parameters as an
+/// error instead of panicking.
+///
+/// Takes the same arguments as [`euclidean`].
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `steps == 0` and
+/// [`Error::PulsesExceedSteps`] if `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Error, try_euclidean};
+///
+/// assert_eq!(try_euclidean(8, 3, 0)?.len(), 8);
+/// assert_eq!(
+///     try_euclidean(8, 9, 0),
+///     Err(Error::PulsesExceedSteps { pulses: 9, steps: 8 })
+/// );
+/// # Ok::<(), Error>(())
+/// ```
+pub fn try_euclidean(steps: u8, pulses: u8, rotation: u8) -> Result<Vec<bool>> {
+    try_euclidean_large(steps.into(), pulses.into(), rotation.into())
+}
+
+/// Like [`euclidean`], but for step counts beyond 255, such as one bar of a
+/// 960 PPQ grid.
+///
+/// # Panics
+/// Panics if `pulses > steps` or if `steps == 0`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, euclidean_large};
+///
+/// let fine = euclidean_large(3840, 5, 0);
+/// assert_eq!(fine.iter().filter(|&&p| p).count(), 5);
+/// assert_eq!(euclidean_large(16, 5, 2), euclidean(16, 5, 2));
+/// ```
+pub fn euclidean_large(steps: usize, pulses: usize, rotation: usize) -> Vec<bool> {
+    error::unwrap_or_panic(try_euclidean_large(steps, pulses, rotation))
+}
+
+/// Like [`euclidean_large`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `steps == 0` and
+/// [`Error::PulsesExceedSteps`] if `pulses > steps`.
+pub fn try_euclidean_large(steps: usize, pulses: usize, rotation: usize) -> Result<Vec<bool>> {
+    error::check_params(steps, pulses)?;
     if pulses == 0 {
-        return vec![false; steps as usize];
+        return Ok(vec![false; steps]);
     }
     if pulses == steps {
-        return vec![true; steps as usize];
+        return Ok(vec![true; steps]);
     }
     let mut pattern = bjorklund(steps, pulses);
 
     // Apply rotation
-    let rot = (rotation % steps) as usize;
+    let rot = rotation % steps;
     if rot > 0 {
         pattern.rotate_left(rot);
     }
 
-    pattern
+    Ok(pattern)
+}
+
+/// Generates a Euclidean rhythm with `on` at every pulse and `off` at every
+/// rest, for callers who want velocities, characters or samples rather than
+/// booleans.
+///
+/// Takes the same arguments as [`euclidean`].
+///
+/// # Panics
+/// Panics if `pulses > steps` or if `steps == 0`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::euclidean_map;
+///
+/// let velocities: Vec<u8> = euclidean_map(8, 3, 0, 100, 0);
+/// assert_eq!(velocities, vec![100, 0, 0, 100, 0, 0, 100, 0]);
+///
+/// let glyphs: String = euclidean_map(8, 3, 0, 'x', '.').into_iter().collect();
+/// assert_eq!(glyphs, "x..x..x.");
+/// ```
+pub fn euclidean_map<T: Clone>(steps: u8, pulses: u8, rotation: u8, on: T, off: T) -> Vec<T> {
+    euclidean(steps, pulses, rotation)
+        .into_iter()
+        .map(|pulse| if pulse { on.clone() } else { off.clone() })
+        .collect()
+}
+
+/// Appends a Euclidean rhythm to any collection implementing [`Extend`],
+/// such as a `VecDeque`, a bit vector or a fixed-capacity vector.
+///
+/// The pattern is generated in a stack buffer, so nothing is allocated on the
+/// heap before the steps are handed to `target`.
+///
+/// Takes the same arguments as [`euclidean`].
+///
+/// # Panics
+/// Panics if `pulses > steps` or if `steps == 0`.
+///
+/// # Examples
+/// ```
+/// use std::collections::VecDeque;
+/// use euclidean_rhythm::{euclidean, euclidean_extend};
+///
+/// let mut queue = VecDeque::new();
+/// euclidean_extend(8, 3, 0, &mut queue);
+/// euclidean_extend(8, 5, 0, &mut queue);
+/// assert_eq!(queue.len(), 16);
+/// assert!(queue.iter().take(8).eq(euclidean(8, 3, 0).iter()));
+/// ```
+pub fn euclidean_extend(steps: u8, pulses: u8, rotation: u8, target: &mut impl Extend<bool>) {
+    error::unwrap_or_panic(error::check_params(usize::from(steps), usize::from(pulses)));
+    let steps = usize::from(steps);
+    let flat = macros::bjorklund_fixed::<{ u8::MAX as usize }>(steps, usize::from(pulses));
+    let rotation = usize::from(rotation) % steps;
+    target.extend((0..steps).map(|i| flat[(i + rotation) % steps]));
+}
+
+/// Writes a Euclidean rhythm into a caller-provided buffer of exactly
+/// `steps` elements, without allocating; safe to call from a real-time audio
+/// callback.
+///
+/// Takes the same arguments as [`euclidean`].
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `steps == 0`,
+/// [`Error::PulsesExceedSteps`] if `pulses > steps` and
+/// [`Error::LengthMismatch`] if `out.len() != steps`. `out` is unchanged on
+/// error.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, euclidean_into};
+///
+/// let mut steps = [false; 8];
+/// euclidean_into(8, 5, 2, &mut steps)?;
+/// assert_eq!(steps.to_vec(), euclidean(8, 5, 2));
+/// assert!(euclidean_into(16, 5, 0, &mut steps).is_err());
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+pub fn euclidean_into(steps: u8, pulses: u8, rotation: u8, out: &mut [bool]) -> Result<()> {
+    error::check_params(usize::from(steps), usize::from(pulses))?;
+    let steps = usize::from(steps);
+    if out.len() != steps {
+        return Err(Error::LengthMismatch {
+            expected: steps,
+            actual: out.len(),
+        });
+    }
+    let flat = macros::bjorklund_fixed::<{ u8::MAX as usize }>(steps, usize::from(pulses));
+    let rotation = usize::from(rotation) % steps;
+    for (i, step) in out.iter_mut().enumerate() {
+        *step = flat[(i + rotation) % steps];
+    }
+    Ok(())
+}
+
+/// Generates a Euclidean rhythm at compile time, as an array of `STEPS`
+/// steps, so patterns can live in `static` tables with no runtime cost. The
+/// [`euclid!`] macro wraps it in E(k,n) notation.
+///
+/// Produces exactly the same patterns as [`euclidean`], and works for any
+/// step count.
+///
+/// # Panics
+/// Panics if `STEPS == 0` or `pulses > STEPS`; in a `const` or `static`
+/// that is a compile error.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, euclidean_const};
+///
+/// static KIT: [[bool; 16]; 2] = [euclidean_const(4, 0), euclidean_const(11, 2)];
+/// assert_eq!(KIT[1].to_vec(), euclidean(16, 11, 2));
+/// ```
+pub const fn euclidean_const<const STEPS: usize>(pulses: usize, rotation: usize) -> [bool; STEPS] {
+    macros::euclidean_array::<STEPS>(pulses, rotation)
+}
+
+/// Generates a Euclidean rhythm of up to 64 steps as a bitmask: bit `i` is
+/// step `i`. Nothing is allocated, and a step is tested with a shift (see
+/// [`mask_pulse`]) instead of an index into a vector.
+///
+/// Takes the same arguments as [`euclidean`].
+///
+/// # Panics
+/// Panics if `steps == 0`, `steps > 64` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean_u64, mask_pulse};
+///
+/// let tresillo = euclidean_u64(8, 3, 0);
+/// assert_eq!(tresillo, 0b0100_1001); // x..x..x.
+/// assert!(mask_pulse(tresillo, 3));
+/// assert!(!mask_pulse(tresillo, 4));
+/// ```
+pub fn euclidean_u64(steps: u8, pulses: u8, rotation: u8) -> u64 {
+    error::unwrap_or_panic(try_euclidean_u64(steps, pulses, rotation))
+}
+
+/// Like [`euclidean_u64`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `steps == 0`, [`Error::TooManySteps`] if
+/// `steps > 64` and [`Error::PulsesExceedSteps`] if `pulses > steps`.
+pub fn try_euclidean_u64(steps: u8, pulses: u8, rotation: u8) -> Result<u64> {
+    error::check_params(usize::from(steps), usize::from(pulses))?;
+    if steps > 64 {
+        return Err(Error::TooManySteps {
+            steps: usize::from(steps),
+            max: 64,
+        });
+    }
+    let steps = usize::from(steps);
+    let flat = macros::bjorklund_fixed::<64>(steps, usize::from(pulses));
+    let rotation = usize::from(rotation) % steps;
+    Ok((0..steps).fold(0, |mask, i| {
+        mask | u64::from(flat[(i + rotation) % steps]) << i
+    }))
+}
+
+/// Returns `true` if step `step` of a [`euclidean_u64`] bitmask is a pulse;
+/// steps past 63 are rests.
+pub const fn mask_pulse(mask: u64, step: usize) -> bool {
+    step < 64 && mask >> step & 1 == 1
+}
+
+/// Unpacks the first `steps` steps (at most 64) of a bitmask into a pattern.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, euclidean_u64, mask_to_pattern};
+///
+/// assert_eq!(mask_to_pattern(euclidean_u64(13, 5, 2), 13), euclidean(13, 5, 2));
+/// ```
+pub fn mask_to_pattern(mask: u64, steps: usize) -> Vec<bool> {
+    (0..steps.min(64))
+        .map(|step| mask_pulse(mask, step))
+        .collect()
 }
 
 /// Converts a boolean pattern to a string representation.
@@ -103,6 +534,184 @@ pub fn pattern_to_string(pattern: &[bool], pulse_char: char, rest_char: char) ->
         .collect()
 }
 
+/// Converts a pattern to a string, inserting `separator` between groups of
+/// `group_size` steps.
+///
+/// A `group_size` of 0 disables grouping.
+///
+/// # Arguments
+/// * `pattern` - The pattern to convert
+/// * `pulse_char` - Character to represent pulses (true values)
+/// * `rest_char` - Character to represent rests (false values)
+/// * `group_size` - Number of steps per group
+/// * `separator` - Character placed between groups
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string_grouped};
+/// let pattern = euclidean(16, 5, 0);
+/// assert_eq!(
+///     pattern_to_string_grouped(&pattern, 'x', '.', 4, '|'),
+///     "x..x|..x.|.x..|x..."
+/// );
+/// ```
+pub fn pattern_to_string_grouped(
+    pattern: &[bool],
+    pulse_char: char,
+    rest_char: char,
+    group_size: usize,
+    separator: char,
+) -> String {
+    let mut out = String::with_capacity(pattern.len() * 2);
+    for (i, &b) in pattern.iter().enumerate() {
+        if group_size > 0 && i > 0 && i % group_size == 0 {
+            out.push(separator);
+        }
+        out.push(if b { pulse_char } else { rest_char });
+    }
+    out
+}
+
+/// Builds a line of 1-based step numbers that lines up with the output of
+/// [`pattern_to_string_grouped`] for the same `group_size` and `separator`.
+///
+/// The number of the first step of each group is printed at the start of the
+/// group, padded with spaces.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string_grouped, step_ruler};
+/// let pattern = euclidean(16, 5, 0);
+/// assert_eq!(step_ruler(16, 4, '|'), "1   |5   |9   |13  ");
+/// assert_eq!(pattern_to_string_grouped(&pattern, 'x', '.', 4, '|'), "x..x|..x.|.x..|x...");
+/// ```
+pub fn step_ruler(steps: usize, group_size: usize, separator: char) -> String {
+    let group_size = if group_size == 0 {
+        steps.max(1)
+    } else {
+        group_size
+    };
+    let mut out = String::new();
+    let mut start = 0;
+    while start < steps {
+        if start > 0 {
+            out.push(separator);
+        }
+        let width = group_size.min(steps - start);
+        let label = (start + 1).to_string();
+        let label: String = label.chars().take(width).collect();
+        out.push_str(&format!("{label:<width$}"));
+        start += group_size;
+    }
+    out
+}
+
+/// Returns the indices of the pulses in `pattern`, in step order.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_onsets};
+/// assert_eq!(pattern_to_onsets(&euclidean(8, 3, 0)), [0, 3, 6]);
+/// ```
+pub fn pattern_to_onsets(pattern: &[bool]) -> Vec<usize> {
+    (0..pattern.len()).filter(|&step| pattern[step]).collect()
+}
+
+/// Builds a pattern of `steps` steps with pulses at the indices in
+/// `onsets`, the inverse of [`pattern_to_onsets`]. The onsets may be in any
+/// order and contain duplicates. (Onset *times* from a recording are placed
+/// on a grid by `onsets_to_pattern` in the `audio-analysis` feature.)
+///
+/// # Panics
+/// Panics if an onset is not less than `steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_from_onsets};
+/// assert_eq!(pattern_from_onsets(&[0, 3, 6], 8), euclidean(8, 3, 0));
+/// ```
+pub fn pattern_from_onsets(onsets: &[usize], steps: usize) -> Vec<bool> {
+    error::unwrap_or_panic(try_pattern_from_onsets(onsets, steps))
+}
+
+/// Like [`pattern_from_onsets`], but reports out-of-range onsets as an
+/// error.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] if an onset is not less than `steps`.
+pub fn try_pattern_from_onsets(onsets: &[usize], steps: usize) -> Result<Vec<bool>> {
+    let mut pattern = vec![false; steps];
+    for &onset in onsets {
+        *pattern.get_mut(onset).ok_or(Error::InvalidParameter {
+            name: "onsets",
+            reason: "every onset must be less than steps",
+        })? = true;
+    }
+    Ok(pattern)
+}
+
+/// Returns the inter-onset intervals of `pattern`: the number of steps from
+/// each pulse to the next, wrapping from the last pulse around to the first,
+/// so they add up to the pattern length. A pattern without pulses has none.
+///
+/// The intervals start at the first pulse, so leading rests are not kept;
+/// rotate the output of [`intervals_to_pattern`] right by the first onset to
+/// restore them.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_intervals};
+/// assert_eq!(pattern_to_intervals(&euclidean(8, 3, 0)), [3, 3, 2]);
+/// assert_eq!(pattern_to_intervals(&euclidean(16, 7, 0)), [3, 2, 2, 3, 2, 2, 2]);
+/// ```
+pub fn pattern_to_intervals(pattern: &[bool]) -> Vec<usize> {
+    let onsets = pattern_to_onsets(pattern);
+    let Some(&first) = onsets.first() else {
+        return Vec::new();
+    };
+    onsets
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .chain(onsets.last().map(|&last| pattern.len() - last + first))
+        .collect()
+}
+
+/// Builds a pattern from inter-onset intervals, the inverse of
+/// [`pattern_to_intervals`]: a pulse on the first step, then one after
+/// each interval but the last. The pattern is as long as the intervals add
+/// up to.
+///
+/// # Panics
+/// Panics if an interval is 0.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, intervals_to_pattern};
+/// assert_eq!(intervals_to_pattern(&[3, 3, 2]), euclidean(8, 3, 0));
+/// ```
+pub fn intervals_to_pattern(intervals: &[usize]) -> Vec<bool> {
+    error::unwrap_or_panic(try_intervals_to_pattern(intervals))
+}
+
+/// Like [`intervals_to_pattern`], but reports a zero interval as an error.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] if an interval is 0.
+pub fn try_intervals_to_pattern(intervals: &[usize]) -> Result<Vec<bool>> {
+    let mut pattern = Vec::with_capacity(intervals.iter().sum());
+    for &interval in intervals {
+        if interval == 0 {
+            return Err(Error::InvalidParameter {
+                name: "intervals",
+                reason: "every interval must be at least 1",
+            });
+        }
+        pattern.push(true);
+        pattern.extend(core::iter::repeat_n(false, interval - 1));
+    }
+    Ok(pattern)
+}
+
 /// Rotates a pattern by a given number of steps.
 ///
 /// Positive rotation values rotate left (earlier in time), negative values rotate
@@ -139,6 +748,314 @@ pub fn rotate_pattern(pattern: &[bool], rotation: i32) -> Vec<bool> {
     result
 }
 
+/// Returns the complement of a pattern: every pulse becomes a rest and every
+/// rest a pulse.
+///
+/// The complement of a Euclidean rhythm is Euclidean too, and it fills the
+/// gaps of the original, like a hi-hat playing between kicks.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, invert_pattern, pattern_to_string};
+///
+/// let kick = euclidean(8, 3, 0);
+/// assert_eq!(pattern_to_string(&invert_pattern(&kick), 'x', '.'), ".xx.xx.x");
+/// ```
+pub fn invert_pattern(pattern: &[bool]) -> Vec<bool> {
+    pattern.iter().map(|&step| !step).collect()
+}
+
+/// Returns a pattern played backwards (its retrograde).
+///
+/// A pattern that starts on a pulse usually ends on one once reversed;
+/// rotate the result right by one step to bring that pulse back to the
+/// downbeat.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string, reverse_pattern, rotate_pattern};
+///
+/// let reversed = reverse_pattern(&euclidean(8, 3, 0));
+/// assert_eq!(pattern_to_string(&reversed, 'x', '.'), ".x..x..x");
+/// assert_eq!(pattern_to_string(&rotate_pattern(&reversed, -1), 'x', '.'), "x.x..x..");
+/// ```
+pub fn reverse_pattern(pattern: &[bool]) -> Vec<bool> {
+    pattern.iter().rev().copied().collect()
+}
+
+/// Joins patterns end to end into one phrase.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{concat_patterns, euclidean};
+///
+/// let (tresillo, cinquillo) = (euclidean(8, 3, 0), euclidean(8, 5, 0));
+/// let phrase = concat_patterns(&[&tresillo, &tresillo, &cinquillo]);
+/// assert_eq!(phrase.len(), 24);
+/// assert_eq!(phrase[16..], cinquillo[..]);
+/// ```
+pub fn concat_patterns(patterns: &[&[bool]]) -> Vec<bool> {
+    patterns.concat()
+}
+
+/// Plays a pattern `times` times in a row; 0 times gives an empty pattern.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string, repeat_pattern};
+///
+/// let bars = repeat_pattern(&euclidean(4, 1, 0), 3);
+/// assert_eq!(pattern_to_string(&bars, 'x', '.'), "x...x...x...");
+/// ```
+pub fn repeat_pattern(pattern: &[bool], times: usize) -> Vec<bool> {
+    pattern.repeat(times)
+}
+
+/// Expands every step into `factor` substeps, keeping each pulse on the
+/// first substep, so a pattern moves onto a finer grid without changing its
+/// rhythm. A factor of 0 yields an empty pattern.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string, stretch_pattern};
+///
+/// let sixteenths = stretch_pattern(&euclidean(8, 3, 0), 2);
+/// assert_eq!(pattern_to_string(&sixteenths, 'x', '.'), "x.....x.....x...");
+/// ```
+pub fn stretch_pattern(pattern: &[bool], factor: usize) -> Vec<bool> {
+    let mut stretched = Vec::with_capacity(pattern.len() * factor);
+    for &step in pattern {
+        stretched.extend((0..factor).map(|sub| step && sub == 0));
+    }
+    stretched
+}
+
+/// Returns a pulse wherever both patterns have one.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{and_patterns, euclidean, pattern_to_string};
+///
+/// let both = and_patterns(&euclidean(8, 3, 0), &euclidean(8, 4, 0)).unwrap();
+/// assert_eq!(pattern_to_string(&both, 'x', '.'), "x.....x.");
+/// ```
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn and_patterns(a: &[bool], b: &[bool]) -> Result<Vec<bool>> {
+    zip_patterns(a, b, |a, b| a && b)
+}
+
+/// Returns a pulse wherever either pattern has one.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, or_patterns, pattern_to_string};
+///
+/// let either = or_patterns(&euclidean(8, 3, 0), &euclidean(8, 4, 0)).unwrap();
+/// assert_eq!(pattern_to_string(&either, 'x', '.'), "x.xxx.x.");
+/// ```
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn or_patterns(a: &[bool], b: &[bool]) -> Result<Vec<bool>> {
+    zip_patterns(a, b, |a, b| a || b)
+}
+
+/// Returns a pulse wherever exactly one of the patterns has one. The XOR of
+/// two Euclidean rhythms makes a good ghost-note layer: it keeps the hits
+/// only one of them plays and drops the ones they share.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, pattern_to_string, xor_patterns};
+///
+/// let ghosts = xor_patterns(&euclidean(8, 3, 0), &euclidean(8, 4, 0)).unwrap();
+/// assert_eq!(pattern_to_string(&ghosts, 'x', '.'), "..xxx...");
+/// ```
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn xor_patterns(a: &[bool], b: &[bool]) -> Result<Vec<bool>> {
+    zip_patterns(a, b, |a, b| a != b)
+}
+
+/// Counts the steps where two equally long patterns differ, a similarity
+/// measure for pattern search and for scoring mutations: 0 means identical.
+/// `SparsePattern::distance` is the same measure on onset lists.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, hamming_distance};
+///
+/// // x..x..x. against x.x.x.x.: steps 2, 3 and 4 differ.
+/// assert_eq!(hamming_distance(&euclidean(8, 3, 0), &euclidean(8, 4, 0)), Ok(3));
+/// ```
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn hamming_distance(a: &[bool], b: &[bool]) -> Result<usize> {
+    Ok(xor_patterns(a, b)?
+        .iter()
+        .filter(|&&differs| differs)
+        .count())
+}
+
+/// Alternates the steps of two equally long patterns: `a[0]`, `b[0]`,
+/// `a[1]`, `b[1]` and so on, giving a pattern twice as long. Two voices
+/// interleaved this way make a double-time composite groove.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, interleave_patterns, pattern_to_string};
+///
+/// let groove = interleave_patterns(&euclidean(8, 3, 0), &euclidean(8, 2, 2)).unwrap();
+/// assert_eq!(pattern_to_string(&groove, 'x', '.'), "x....xx.....xx..");
+/// ```
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if the patterns have different lengths.
+pub fn interleave_patterns(a: &[bool], b: &[bool]) -> Result<Vec<bool>> {
+    if a.len() != b.len() {
+        return Err(Error::LengthMismatch {
+            expected: a.len(),
+            actual: b.len(),
+        });
+    }
+    Ok(a.iter().zip(b).flat_map(|(&a, &b)| [a, b]).collect())
+}
+
+/// Combines two equally long patterns step by step.
+fn zip_patterns(a: &[bool], b: &[bool], op: impl Fn(bool, bool) -> bool) -> Result<Vec<bool>> {
+    if a.len() != b.len() {
+        return Err(Error::LengthMismatch {
+            expected: a.len(),
+            actual: b.len(),
+        });
+    }
+    Ok(a.iter().zip(b).map(|(&a, &b)| op(a, b)).collect())
+}
+
+/// Returns `true` if `pattern` is `other` rotated by some number of steps,
+/// that is, the same rhythm started on a different step. Patterns of
+/// different lengths are never rotations of each other.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, is_rotation_of};
+///
+/// // A clave heard from its second hit is still E(3,8).
+/// let heard = [true, false, false, true, false, true, false, false];
+/// assert!(is_rotation_of(&heard, &euclidean(8, 3, 0)));
+/// assert!(!is_rotation_of(&heard, &euclidean(8, 4, 0)));
+/// ```
+pub fn is_rotation_of(pattern: &[bool], other: &[bool]) -> bool {
+    let len = pattern.len();
+    len == other.len()
+        && (len == 0
+            || (0..len)
+                .any(|r| pattern[r..] == other[..len - r] && pattern[..r] == other[len - r..]))
+}
+
+/// Recognizes a Euclidean rhythm: returns `(pulses, steps, rotation)` such
+/// that `euclidean(steps, pulses, rotation)` equals `pattern`, or `None` if
+/// no rotation of E(`pulses`, `steps`) does. The smallest such rotation is
+/// returned, and an empty pattern gives `None`. Patterns of any length are
+/// checked against [`euclidean_large`].
+///
+/// `fit_euclidean`, in the `analysis` feature, finds the closest match for
+/// patterns that are only nearly Euclidean.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{detect_euclidean, euclidean};
+///
+/// assert_eq!(detect_euclidean(&euclidean(16, 7, 3)), Some((7, 16, 3)));
+/// // The clave son is not Euclidean.
+/// let son: Vec<bool> = "x..x..x...x.x...".chars().map(|c| c == 'x').collect();
+/// assert_eq!(detect_euclidean(&son), None);
+/// ```
+pub fn detect_euclidean(pattern: &[bool]) -> Option<(usize, usize, usize)> {
+    let steps = pattern.len();
+    if steps == 0 {
+        return None;
+    }
+    let pulses = pattern.iter().filter(|&&pulse| pulse).count();
+    let base = euclidean_large(steps, pulses, 0);
+    let rotation = (0..steps).find(|&r| base[r..].iter().chain(&base[..r]).eq(pattern))?;
+    Some((pulses, steps, rotation))
+}
+
+/// Rotates a pattern by a fraction of its cycle, snapping to the nearest step.
+///
+/// A `phase` of `0.25` rotates a 16-step pattern left by 4 steps; phases wrap,
+/// so `1.25` and `-0.75` do the same. Halfway phases round up to the next
+/// step, and a non-finite phase leaves the pattern unrotated. See
+/// [`rotate_fractional_offsets`] to keep the fractional part as timing.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, rotate_fractional, rotate_pattern};
+///
+/// let pattern = euclidean(16, 5, 0);
+/// assert_eq!(rotate_fractional(&pattern, 0.25), rotate_pattern(&pattern, 4));
+/// // 0.3 of 16 steps is 4.8 steps, which snaps to 5.
+/// assert_eq!(rotate_fractional(&pattern, 0.3), rotate_pattern(&pattern, 5));
+/// ```
+pub fn rotate_fractional(pattern: &[bool], phase: f32) -> Vec<bool> {
+    let (steps, _) = fractional_shift(pattern.len(), phase);
+    rotate_pattern(pattern, steps as i32)
+}
+
+/// Rotates a pattern by a fraction of its cycle without losing the part of
+/// the rotation that falls between steps.
+///
+/// Returns one `(step, offset)` pair per onset, in step order: `step` is the
+/// onset's step in [`rotate_fractional`]'s result and `offset` is how far
+/// the exact rotated position lies from it, in steps, within `-0.5..=0.5`.
+/// Every onset shares the same offset, so a sequencer can play the snapped
+/// pattern and delay or advance all its notes by `offset` steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, rotate_fractional_offsets};
+///
+/// // 0.3 of 8 steps is 2.4 steps: onsets snap 2 steps earlier and play 0.4
+/// // of a step earlier still.
+/// let timed = rotate_fractional_offsets(&euclidean(8, 3, 0), 0.3);
+/// let steps: Vec<usize> = timed.iter().map(|&(step, _)| step).collect();
+/// assert_eq!(steps, [1, 4, 6]);
+/// assert!(timed.iter().all(|&(_, offset)| (offset + 0.4).abs() < 1e-5));
+/// ```
+pub fn rotate_fractional_offsets(pattern: &[bool], phase: f32) -> Vec<(usize, f32)> {
+    let (steps, offset) = fractional_shift(pattern.len(), phase);
+    let mut onsets: Vec<(usize, f32)> = (0..pattern.len())
+        .filter(|&i| pattern[i])
+        .map(|i| ((i + pattern.len() - steps) % pattern.len(), offset))
+        .collect();
+    onsets.sort_unstable_by_key(|&(step, _)| step);
+    onsets
+}
+
+/// Splits a left rotation by `phase` of a `len`-step cycle into whole steps
+/// in `0..len` and the remaining offset `whole - exact`, avoiding float
+/// functions that need `std`.
+fn fractional_shift(len: usize, phase: f32) -> (usize, f32) {
+    if len == 0 || !phase.is_finite() {
+        return (0, 0.0);
+    }
+    // `%` is exact for any magnitude, unlike casting to an integer, which
+    // saturates.
+    let mut fraction = phase % 1.0;
+    if fraction < 0.0 {
+        fraction += 1.0;
+    }
+    let exact = fraction * len as f32;
+    // Truncation floors a non-negative value, so this rounds half up.
+    let whole = (exact + 0.5) as usize;
+    (whole % len, whole as f32 - exact)
+}
+
 /// Core Bjorklund algorithm implementation.
 ///
 /// Distributes pulses evenly by repeatedly pairing and concatenating groups
@@ -148,10 +1065,7 @@ pub fn rotate_pattern(pattern: &[bool], rotation: i32) -> Vec<bool> {
 ///
 /// Assumes inputs are already validated by the caller.
 #[inline]
-fn bjorklund(steps: u8, pulses: u8) -> Vec<bool> {
-    let steps = steps as usize;
-    let pulses = pulses as usize;
-
+fn bjorklund(steps: usize, pulses: usize) -> Vec<bool> {
     if pulses == 1 {
         let mut pattern = vec![false; steps];
         pattern[0] = true;
@@ -284,6 +1198,19 @@ mod tests {
         let _ = euclidean(0, 0, 0);
     }
 
+    #[test]
+    fn try_euclidean_reports_errors() {
+        assert_eq!(try_euclidean(8, 3, 0), Ok(euclidean(8, 3, 0)));
+        assert_eq!(try_euclidean(0, 0, 0), Err(Error::ZeroSteps));
+        assert_eq!(
+            try_euclidean(8, 9, 0),
+            Err(Error::PulsesExceedSteps {
+                pulses: 9,
+                steps: 8
+            })
+        );
+    }
+
     #[test]
     fn rotation_wraps() {
         let pattern = euclidean(8, 3, 10); // 10 % 8 == 2
@@ -342,6 +1269,245 @@ mod tests {
         assert_eq!(rotated[7], original[0]);
     }
 
+    #[test]
+    fn map_matches_bool_pattern() {
+        let mapped = euclidean_map(13, 5, 3, 1u8, 0u8);
+        let expected: Vec<u8> = euclidean(13, 5, 3).into_iter().map(u8::from).collect();
+        assert_eq!(mapped, expected);
+    }
+
+    #[test]
+    fn extend_matches_euclidean() {
+        for steps in [1u8, 7, 16, 255] {
+            for pulses in [0, 1, steps / 3, steps] {
+                let mut out = vec![true];
+                euclidean_extend(steps, pulses, 5, &mut out);
+                assert_eq!(out[1..], euclidean(steps, pulses, 5)[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn large_patterns_match_euclidean() {
+        for steps in [1u8, 12, 255] {
+            let pulses = steps / 2;
+            assert_eq!(
+                euclidean_large(steps.into(), pulses.into(), 300),
+                euclidean(steps, pulses, (300 % usize::from(steps)) as u8)
+            );
+        }
+        let bar = euclidean_large(3840, 960, 0);
+        assert!(
+            bar.chunks(4)
+                .all(|beat| beat == [true, false, false, false])
+        );
+        assert_eq!(try_euclidean_large(0, 0, 0), Err(Error::ZeroSteps));
+    }
+
+    #[test]
+    fn const_patterns_match_euclidean() {
+        const LONG: [bool; 300] = euclidean_const(101, 0);
+        assert_eq!(LONG.iter().filter(|&&p| p).count(), 101);
+        assert_eq!(euclidean_const::<13>(5, 20).to_vec(), euclidean(13, 5, 20));
+    }
+
+    #[test]
+    fn masks_match_euclidean() {
+        for steps in [1u8, 8, 13, 63, 64] {
+            for pulses in 0..=steps {
+                let mask = euclidean_u64(steps, pulses, 3);
+                assert_eq!(
+                    mask_to_pattern(mask, steps.into()),
+                    euclidean(steps, pulses, 3)
+                );
+                assert_eq!(mask.count_ones(), u32::from(pulses));
+            }
+        }
+        assert_eq!(euclidean_u64(64, 64, 0), u64::MAX);
+        assert!(!mask_pulse(u64::MAX, 64));
+        assert_eq!(
+            try_euclidean_u64(65, 1, 0),
+            Err(Error::TooManySteps { steps: 65, max: 64 })
+        );
+        assert_eq!(try_euclidean_u64(0, 0, 0), Err(Error::ZeroSteps));
+    }
+
+    #[test]
+    fn onset_indices_round_trip() {
+        for pulses in 0..=12 {
+            let pattern = euclidean(12, pulses, 5);
+            assert_eq!(
+                pattern_from_onsets(&pattern_to_onsets(&pattern), 12),
+                pattern
+            );
+        }
+        assert_eq!(pattern_from_onsets(&[2, 0, 2], 3), [true, false, true]);
+        assert!(try_pattern_from_onsets(&[3], 3).is_err());
+        assert!(pattern_to_onsets(&[]).is_empty());
+    }
+
+    #[test]
+    fn intervals_round_trip() {
+        for pulses in 1..=16 {
+            let pattern = euclidean(16, pulses, 0);
+            let intervals = pattern_to_intervals(&pattern);
+            assert_eq!(intervals.iter().sum::<usize>(), 16);
+            assert_eq!(intervals_to_pattern(&intervals), pattern);
+        }
+        assert_eq!(pattern_to_intervals(&[false, true, false]), [3]);
+        assert!(pattern_to_intervals(&[false; 4]).is_empty());
+        assert!(try_intervals_to_pattern(&[2, 0]).is_err());
+    }
+
+    #[test]
+    fn inverts_into_the_complement() {
+        let kick = euclidean(16, 5, 0);
+        let hats = invert_pattern(&kick);
+        assert!(kick.iter().zip(&hats).all(|(k, h)| k != h));
+        assert_eq!(invert_pattern(&hats), kick);
+        assert_eq!(Pattern::from(kick).invert().as_slice(), hats);
+    }
+
+    #[test]
+    fn reverses_into_the_retrograde() {
+        let pattern = euclidean(12, 5, 1);
+        let reversed = reverse_pattern(&pattern);
+        assert_eq!(reversed.first(), pattern.last());
+        assert_eq!(reverse_pattern(&reversed), pattern);
+        assert_eq!(Pattern::from(pattern).reverse().as_slice(), reversed);
+        assert!(reverse_pattern(&[]).is_empty());
+    }
+
+    #[test]
+    fn combines_equal_lengths_only() {
+        let (a, b) = (euclidean(16, 5, 0), euclidean(16, 7, 3));
+        let and = and_patterns(&a, &b).unwrap();
+        let or = or_patterns(&a, &b).unwrap();
+        let xor = xor_patterns(&a, &b).unwrap();
+        for step in 0..16 {
+            assert_eq!(xor[step], or[step] && !and[step]);
+        }
+        assert_eq!(xor_patterns(&a, &a), Ok(vec![false; 16]));
+        let distance = hamming_distance(&a, &b).unwrap();
+        assert_eq!(distance, xor.iter().filter(|&&x| x).count());
+        assert_eq!(
+            distance,
+            SparsePattern::from(a.as_slice()).distance(&SparsePattern::from(b.as_slice()))
+        );
+        assert_eq!(hamming_distance(&a, &a), Ok(0));
+        assert!(hamming_distance(&a, &b[1..]).is_err());
+        assert_eq!(
+            or_patterns(&a, &b[..8]),
+            Err(Error::LengthMismatch {
+                expected: 16,
+                actual: 8
+            })
+        );
+    }
+
+    #[test]
+    fn interleaves_alternate_steps() {
+        let (a, b) = (euclidean(8, 3, 0), euclidean(8, 5, 1));
+        let groove = interleave_patterns(&a, &b).unwrap();
+        assert_eq!(groove.len(), 16);
+        assert!(groove.iter().step_by(2).eq(&a));
+        assert!(groove.iter().skip(1).step_by(2).eq(&b));
+        assert!(interleave_patterns(&a, &[]).is_err());
+    }
+
+    #[test]
+    fn stretches_onto_finer_grids() {
+        let pattern = euclidean(8, 5, 1);
+        let stretched = stretch_pattern(&pattern, 4);
+        assert_eq!(stretched.len(), 32);
+        assert_eq!(
+            pattern_to_onsets(&stretched),
+            pattern_to_onsets(&pattern)
+                .iter()
+                .map(|&i| i * 4)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(stretch_pattern(&pattern, 1), pattern);
+        assert!(stretch_pattern(&pattern, 0).is_empty());
+    }
+
+    #[test]
+    fn recognizes_rotations() {
+        let pattern = euclidean(12, 5, 0);
+        for rotation in -12..=12 {
+            assert!(is_rotation_of(
+                &rotate_pattern(&pattern, rotation),
+                &pattern
+            ));
+        }
+        assert!(!is_rotation_of(
+            &reverse_pattern(&euclidean(12, 5, 1)),
+            &[true; 12]
+        ));
+        assert!(!is_rotation_of(&pattern, &pattern[1..]));
+        assert!(is_rotation_of(&[], &[]));
+        assert!(
+            Pattern::euclidean(8, 3)
+                .rotate(3)
+                .is_rotation_of(&euclidean(8, 3, 0))
+        );
+    }
+
+    #[test]
+    fn detects_euclidean_rhythms() {
+        for pulses in 0..=12 {
+            for rotation in 0..12 {
+                let (p, s, r) = detect_euclidean(&euclidean(12, pulses, rotation)).unwrap();
+                assert_eq!((p, s), (usize::from(pulses), 12));
+                assert_eq!(euclidean_large(s, p, r), euclidean(12, pulses, rotation));
+                assert!(r <= usize::from(rotation));
+            }
+        }
+        assert_eq!(detect_euclidean(&[true, true, false, false]), None);
+        assert_eq!(detect_euclidean(&[]), None);
+        assert_eq!(detect_euclidean(&[true; 256]), Some((256, 256, 0)));
+        assert_eq!(
+            detect_euclidean(&euclidean_large(1000, 7, 400)),
+            Some((7, 1000, 400))
+        );
+    }
+
+    #[test]
+    fn concatenates_and_repeats() {
+        let cell = euclidean(8, 3, 0);
+        assert_eq!(repeat_pattern(&cell, 2), concat_patterns(&[&cell, &cell]));
+        assert!(repeat_pattern(&cell, 0).is_empty());
+        assert!(concat_patterns(&[]).is_empty());
+        assert_eq!(
+            concat_patterns(&[&[true], &[], &[false, true]]),
+            [true, false, true]
+        );
+    }
+
+    #[test]
+    fn into_fills_matching_buffers_only() {
+        let mut out = [true; 255];
+        euclidean_into(255, 100, 7, &mut out).unwrap();
+        assert_eq!(out[..], euclidean(255, 100, 7)[..]);
+
+        let mut short = [true; 4];
+        assert_eq!(
+            euclidean_into(8, 3, 0, &mut short),
+            Err(Error::LengthMismatch {
+                expected: 8,
+                actual: 4
+            })
+        );
+        assert_eq!(
+            euclidean_into(4, 5, 0, &mut short),
+            Err(Error::PulsesExceedSteps {
+                pulses: 5,
+                steps: 4
+            })
+        );
+        assert_eq!(short, [true; 4]);
+    }
+
     #[test]
     fn pattern_to_string_works() {
         let pattern = euclidean(8, 3, 0);
@@ -349,6 +1515,23 @@ mod tests {
         assert_eq!(pattern_to_string(&pattern, '1', '0'), "10010010");
     }
 
+    #[test]
+    fn grouped_string_and_ruler_align() {
+        let pattern = euclidean(12, 5, 0);
+        let grouped = pattern_to_string_grouped(&pattern, 'x', '.', 3, ' ');
+        assert_eq!(grouped, "x.. x.x ..x .x.");
+        let ruler = step_ruler(12, 3, ' ');
+        assert_eq!(ruler, "1   4   7   10 ");
+        assert_eq!(ruler.len(), grouped.len());
+
+        // Group size 0 means no grouping.
+        assert_eq!(
+            pattern_to_string_grouped(&pattern, 'x', '.', 0, '|'),
+            pattern_to_string(&pattern, 'x', '.')
+        );
+        assert_eq!(step_ruler(5, 4, '|'), "1   |5");
+    }
+
     #[test]
     fn rotate_pattern_works() {
         let pattern = vec![true, false, false, true];
@@ -373,4 +1556,37 @@ mod tests {
         let empty: Vec<bool> = vec![];
         assert_eq!(rotate_pattern(&empty, 1), empty);
     }
+
+    #[test]
+    fn fractional_rotation_wraps_and_keeps_offsets() {
+        let pattern = euclidean(16, 5, 0);
+        for phase in [-0.75, 0.25, 1.25, 4096.25, -4095.75] {
+            assert_eq!(
+                rotate_fractional(&pattern, phase),
+                rotate_pattern(&pattern, 4)
+            );
+        }
+        assert_eq!(rotate_fractional(&pattern, 0.99), pattern);
+        assert_eq!(rotate_fractional(&pattern, f32::NAN), pattern);
+        // Floats this large are whole numbers of cycles.
+        for phase in [1e20, -1e20, f32::MAX] {
+            assert_eq!(rotate_fractional(&pattern, phase), pattern);
+            assert!(
+                rotate_fractional_offsets(&pattern, phase)
+                    .iter()
+                    .all(|&(_, offset)| offset == 0.0)
+            );
+        }
+        assert!(rotate_fractional(&[], 0.5).is_empty());
+
+        // 0.97 of 16 steps is 15.52 steps: snaps to 16 (no rotation), 0.48 late.
+        let timed = rotate_fractional_offsets(&pattern, 0.97);
+        let onsets: Vec<usize> = (0..16).filter(|&i| pattern[i]).collect();
+        assert_eq!(timed.iter().map(|&(s, _)| s).collect::<Vec<_>>(), onsets);
+        assert!(
+            timed
+                .iter()
+                .all(|&(_, offset)| (offset - 0.48).abs() < 1e-4)
+        );
+    }
 }
@@ -0,0 +1,295 @@
+//! Live-coding commands for a running [`Sequencer`].
+//!
+//! A [`LiveQueue`] takes one-line commands as they are typed and applies them
+//! to the sequencer on the next bar line, so edits land in time with the
+//! music:
+//!
+//! ```text
+//! kick = e(4,16)
+//! hat = e(11,16,2)
+//! swing 55
+//! bpm 124
+//! ```
+//!
+//! `e(pulses,steps)` and `e(pulses,steps,rotation)` follow the usual E(k,n)
+//! notation, optionally followed by the [`Algorithm`] to generate it with,
+//! as in `hat = e(5,12) balanced-polygon`. Assigning to a new name adds a
+//! track; assigning to an existing one changes its pattern and keeps its
+//! note, velocity, conditions and locks, and its algorithm unless one is
+//! given.
+//!
+//! The `euclid repl` binary, built with this feature, runs a sequencer in
+//! real time and feeds it the commands typed at the terminal.
+
+use crate::error::{Error, Result};
+use crate::generator::Algorithm;
+use crate::sequencer::{Sequencer, SequencerTrack};
+use crate::try_euclidean;
+
+/// One parsed live-coding command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiveCommand {
+    /// Sets (or adds) the named track to E(`pulses`, `steps`) rotated by
+    /// `rotation`.
+    Track {
+        /// Track name.
+        name: String,
+        /// Step count.
+        steps: u8,
+        /// Pulse count.
+        pulses: u8,
+        /// Rotation, as passed to [`euclidean`](crate::euclidean).
+        rotation: u8,
+        /// The algorithm to switch to, if given.
+        generator: Option<Algorithm>,
+    },
+    /// Changes the tempo.
+    Bpm(f64),
+    /// Changes the swing, in percent (50-75); see
+    /// [`Sequencer::set_swing`].
+    Swing(u8),
+}
+
+impl core::str::FromStr for LiveCommand {
+    type Err = Error;
+
+    /// Parses `name = e(pulses,steps[,rotation]) [algorithm]`,
+    /// `bpm <tempo>` or `swing <percent>`.
+    fn from_str(line: &str) -> Result<Self> {
+        let line = line.trim();
+        if let Some((name, call)) = line.split_once('=') {
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(invalid("track names are single words"));
+            }
+            let (args, generator) = call
+                .trim()
+                .strip_prefix("e(")
+                .and_then(|rest| rest.split_once(')'))
+                .ok_or(invalid(
+                    "expected e(pulses,steps) or e(pulses,steps,rotation)",
+                ))?;
+            let generator = match generator.trim() {
+                "" => None,
+                name => Some(name.parse::<Algorithm>()?),
+            };
+            let args: Vec<u8> = args
+                .split(',')
+                .map(|arg| {
+                    arg.trim()
+                        .parse()
+                        .map_err(|_| invalid("arguments are 0-255"))
+                })
+                .collect::<Result<_>>()?;
+            let (pulses, steps, rotation) = match args[..] {
+                [pulses, steps] => (pulses, steps, 0),
+                [pulses, steps, rotation] => (pulses, steps, rotation),
+                _ => return Err(invalid("e() takes two or three arguments")),
+            };
+            try_euclidean(steps, pulses, rotation)?;
+            return Ok(LiveCommand::Track {
+                name: name.into(),
+                steps,
+                pulses,
+                rotation,
+                generator,
+            });
+        }
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["bpm", tempo] => match tempo.parse::<f64>() {
+                Ok(bpm) if bpm.is_finite() && bpm > 0.0 => Ok(LiveCommand::Bpm(bpm)),
+                _ => Err(invalid("bpm must be a positive number")),
+            },
+            ["swing", percent] => match percent.parse::<u8>() {
+                Ok(percent @ 50..=75) => Ok(LiveCommand::Swing(percent)),
+                _ => Err(invalid("swing must be 50-75 percent")),
+            },
+            _ => Err(invalid("unknown command")),
+        }
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "command",
+        reason,
+    }
+}
+
+/// Commands waiting for the next bar line.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{LiveQueue, Sequencer};
+///
+/// let mut seq = Sequencer::new(120.0);
+/// let mut queue = LiveQueue::new(16);
+/// queue.push_line("kick = e(4,16)")?;
+/// queue.push_line("bpm 124")?;
+///
+/// queue.apply(&mut seq); // position 0 is a bar line
+/// assert_eq!(seq.tracks()[0].params(), (16, 4, 0));
+/// assert_eq!(seq.bpm(), 124.0);
+///
+/// seq.tick();
+/// queue.push_line("kick = e(5,16)")?;
+/// queue.apply(&mut seq); // mid-bar: waits
+/// assert_eq!(seq.tracks()[0].params(), (16, 4, 0));
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveQueue {
+    steps_per_bar: u64,
+    pending: Vec<LiveCommand>,
+}
+
+impl LiveQueue {
+    /// Creates an empty queue for bars of `steps_per_bar` sequencer steps
+    /// (at least 1).
+    pub fn new(steps_per_bar: u64) -> Self {
+        Self {
+            steps_per_bar: steps_per_bar.max(1),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Parses a command and queues it for the next bar line. Blank lines
+    /// are ignored.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidParameter`] named `"command"` for a line that
+    /// is not a command, and the usual parameter errors for invalid
+    /// Euclidean parameters. Nothing is queued on error.
+    pub fn push_line(&mut self, line: &str) -> Result<()> {
+        if !line.trim().is_empty() {
+            self.pending.push(line.parse()?);
+        }
+        Ok(())
+    }
+
+    /// Queues an already parsed command.
+    pub fn push(&mut self, command: LiveCommand) {
+        self.pending.push(command);
+    }
+
+    /// The commands waiting for the next bar line, oldest first.
+    pub fn pending(&self) -> &[LiveCommand] {
+        &self.pending
+    }
+
+    /// Applies the queued commands in order if the sequencer is on a bar
+    /// line, and returns whether it did. Call before each
+    /// [`tick`](Sequencer::tick) so changes take effect on the downbeat.
+    pub fn apply(&mut self, seq: &mut Sequencer) -> bool {
+        if !seq.position().is_multiple_of(self.steps_per_bar) {
+            return false;
+        }
+        for command in self.pending.drain(..) {
+            match command {
+                LiveCommand::Bpm(bpm) => seq.set_bpm(bpm),
+                LiveCommand::Swing(percent) => seq.set_swing(percent),
+                LiveCommand::Track {
+                    name,
+                    steps,
+                    pulses,
+                    rotation,
+                    generator,
+                } => {
+                    // Parameters were validated when parsed.
+                    let existing = seq.tracks().iter().position(|track| track.name == name);
+                    let track = match existing.and_then(|index| seq.track_mut(index)) {
+                        Some(track) => {
+                            track.set_params(steps, pulses, rotation);
+                            track
+                        }
+                        None => {
+                            let track = SequencerTrack::new(name, steps, pulses, rotation);
+                            let index = seq.add_track(track);
+                            seq.track_mut(index).expect("track was just added")
+                        }
+                    };
+                    if let Some(generator) = generator {
+                        track.set_generator(generator);
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            "hat = e(11, 16, 2)".parse(),
+            Ok(LiveCommand::Track {
+                name: "hat".into(),
+                steps: 16,
+                pulses: 11,
+                rotation: 2,
+                generator: None
+            })
+        );
+        assert_eq!(
+            "roll = e(3,8) clustered".parse(),
+            Ok(LiveCommand::Track {
+                name: "roll".into(),
+                steps: 8,
+                pulses: 3,
+                rotation: 0,
+                generator: Some(Algorithm::Clustered)
+            })
+        );
+        assert_eq!("  bpm 98.5 ".parse(), Ok(LiveCommand::Bpm(98.5)));
+        assert_eq!("swing 55".parse(), Ok(LiveCommand::Swing(55)));
+        for bad in [
+            "kick = e(4)",
+            "kick = x(4,16)",
+            "big kick = e(4,16)",
+            "kick = e(17,16)",
+            "kick = e(4,16) euclid",
+            "bpm -3",
+            "swing 45",
+            "swing 80",
+        ] {
+            assert!(bad.parse::<LiveCommand>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn applies_on_bar_lines_and_keeps_track_settings() {
+        let mut seq =
+            Sequencer::new(120.0).with_track(SequencerTrack::new("kick", 16, 4, 0).with_note(35));
+        let mut queue = LiveQueue::new(4);
+        for _ in 0..2 {
+            seq.tick();
+        }
+        queue.push_line("kick = e(3,8)").unwrap();
+        queue.push_line("").unwrap();
+        assert!(queue.push_line("kick e(3,8)").is_err());
+        assert_eq!(queue.pending().len(), 1);
+
+        assert!(!queue.apply(&mut seq));
+        for _ in 0..2 {
+            seq.tick();
+        }
+        assert!(queue.apply(&mut seq));
+        assert!(queue.pending().is_empty());
+        assert_eq!(seq.tracks()[0].params(), (8, 3, 0));
+        assert_eq!(seq.tracks()[0].note, 35);
+
+        for line in ["kick = e(3,8) clustered", "kick = e(2,8)"] {
+            queue.push_line(line).unwrap();
+        }
+        for _ in 0..4 {
+            seq.tick();
+        }
+        assert!(queue.apply(&mut seq));
+        assert_eq!(seq.tracks()[0].generator(), Algorithm::Clustered);
+        assert_eq!(seq.tracks()[0].pattern()[..3], [true, true, false]);
+    }
+}
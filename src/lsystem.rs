@@ -0,0 +1,176 @@
+//! L-system rewriting of rhythm strings.
+//!
+//! An [`LSystem`] replaces every pulse and every rest of a pattern with a
+//! user-defined sequence of steps, in parallel, once per generation. Seeding it
+//! with a Euclidean rhythm gives self-similar patterns that grow and mutate
+//! while keeping the character of the seed.
+
+use crate::error::{Error, Result};
+
+/// A pair of rewrite rules, one for pulses and one for rests.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{LSystem, euclidean, pattern_to_string};
+///
+/// // x -> x.   and   . -> x
+/// let system = LSystem::from_rules("x.", "x")?;
+/// let generations: Vec<Vec<bool>> = system.generations(&euclidean(3, 1, 0), 3).collect();
+///
+/// let text: Vec<String> = generations
+///     .iter()
+///     .map(|g| pattern_to_string(g, 'x', '.'))
+///     .collect();
+/// assert_eq!(text, ["x..", "x.xx", "x.xx.x.", "x.xx.x.xx.x"]);
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LSystem {
+    pulse_rule: Vec<bool>,
+    rest_rule: Vec<bool>,
+}
+
+impl LSystem {
+    /// Creates a system from the replacement for a pulse and for a rest.
+    pub fn new(pulse_rule: Vec<bool>, rest_rule: Vec<bool>) -> Self {
+        Self {
+            pulse_rule,
+            rest_rule,
+        }
+    }
+
+    /// Creates a system from rules written as rhythm strings, where `x`, `X`
+    /// or `1` is a pulse and `.`, `-` or `0` is a rest.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidSymbol`] if a rule contains any other
+    /// character.
+    pub fn from_rules(pulse_rule: &str, rest_rule: &str) -> Result<Self> {
+        Ok(Self::new(parse_rule(pulse_rule)?, parse_rule(rest_rule)?))
+    }
+
+    /// Applies the rules once to every step of `pattern`.
+    pub fn rewrite(&self, pattern: &[bool]) -> Vec<bool> {
+        let mut out = Vec::with_capacity(self.len_after(pattern));
+        for &step in pattern {
+            out.extend_from_slice(if step {
+                &self.pulse_rule
+            } else {
+                &self.rest_rule
+            });
+        }
+        out
+    }
+
+    /// Returns an iterator over the seed followed by `count` rewritten
+    /// generations.
+    pub fn generations<'a>(
+        &'a self,
+        seed: &[bool],
+        count: usize,
+    ) -> impl Iterator<Item = Vec<bool>> + 'a {
+        let mut current = Some(seed.to_vec());
+        (0..=count).map_while(move |_| {
+            let pattern = current.take()?;
+            current = Some(self.rewrite(&pattern));
+            Some(pattern)
+        })
+    }
+
+    /// Length of each of the first `count` generations after the seed, without
+    /// building the patterns.
+    ///
+    /// Lengths grow geometrically, so this is the cheap way to find how many
+    /// generations fit a step budget. Saturates at `usize::MAX`.
+    pub fn lengths(&self, seed: &[bool], count: usize) -> Vec<usize> {
+        let mut pulses = seed.iter().filter(|&&s| s).count();
+        let mut rests = seed.len() - pulses;
+        let (pp, pr) = counts(&self.pulse_rule);
+        let (rp, rr) = counts(&self.rest_rule);
+
+        let mut lengths = Vec::with_capacity(count);
+        for _ in 0..count {
+            let next_pulses = pulses
+                .saturating_mul(pp)
+                .saturating_add(rests.saturating_mul(rp));
+            let next_rests = pulses
+                .saturating_mul(pr)
+                .saturating_add(rests.saturating_mul(rr));
+            pulses = next_pulses;
+            rests = next_rests;
+            lengths.push(pulses.saturating_add(rests));
+        }
+        lengths
+    }
+
+    fn len_after(&self, pattern: &[bool]) -> usize {
+        pattern
+            .iter()
+            .map(|&s| {
+                if s {
+                    self.pulse_rule.len()
+                } else {
+                    self.rest_rule.len()
+                }
+            })
+            .sum()
+    }
+}
+
+/// Counts `(pulses, rests)` in a rule.
+fn counts(rule: &[bool]) -> (usize, usize) {
+    let pulses = rule.iter().filter(|&&s| s).count();
+    (pulses, rule.len() - pulses)
+}
+
+fn parse_rule(rule: &str) -> Result<Vec<bool>> {
+    rule.chars()
+        .enumerate()
+        .map(|(position, symbol)| match symbol {
+            'x' | 'X' | '1' => Ok(true),
+            '.' | '-' | '0' => Ok(false),
+            _ => Err(Error::InvalidSymbol { symbol, position }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{euclidean, pattern_to_string};
+
+    #[test]
+    fn rewrites_in_parallel() {
+        let system = LSystem::from_rules("x.", "x").unwrap();
+        let next = system.rewrite(&euclidean(8, 3, 0));
+        assert_eq!(pattern_to_string(&next, 'x', '.'), "x.xxx.xxx.x");
+    }
+
+    #[test]
+    fn lengths_match_generated_patterns() {
+        let system = LSystem::from_rules("x..", "x.").unwrap();
+        let seed = euclidean(8, 5, 0);
+        let lengths = system.lengths(&seed, 4);
+        let generated: Vec<usize> = system
+            .generations(&seed, 4)
+            .skip(1)
+            .map(|g| g.len())
+            .collect();
+        assert_eq!(lengths, generated);
+    }
+
+    #[test]
+    fn rejects_unknown_symbols() {
+        assert_eq!(
+            LSystem::from_rules("x?", "."),
+            Err(Error::InvalidSymbol {
+                symbol: '?',
+                position: 1
+            })
+        );
+        assert_eq!(
+            LSystem::from_rules("X1", "-0"),
+            Ok(LSystem::new(vec![true, true], vec![false, false]))
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! The [`euclid!`](crate::euclid) macro and its `const` generator.
+
+/// Expands to a compile-time constant `[bool; steps]` Euclidean pattern.
+///
+/// Written `euclid!(pulses, steps)` or `euclid!(pulses, steps, rot rotation)`,
+/// mirroring the E(k,n) notation. All arguments must be constant expressions;
+/// invalid parameters are a compile error.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclid, euclidean};
+///
+/// const TRESILLO: [bool; 8] = euclid!(3, 8);
+/// const BELL: [bool; 8] = euclid!(5, 8, rot 2);
+///
+/// assert_eq!(TRESILLO.to_vec(), euclidean(8, 3, 0));
+/// assert_eq!(BELL.to_vec(), euclidean(8, 5, 2));
+/// ```
+#[macro_export]
+macro_rules! euclid {
+    ($pulses:expr, $steps:expr) => {
+        $crate::euclid!($pulses, $steps, rot 0)
+    };
+    ($pulses:expr, $steps:expr, rot $rotation:expr) => {{
+        const PATTERN: [bool; $steps] = $crate::euclidean_const::<{ $steps }>($pulses, $rotation);
+        PATTERN
+    }};
+}
+
+/// `const` Bjorklund implementation backing
+/// [`euclidean_const`](crate::euclidean_const).
+///
+/// # Panics
+/// Panics (at compile time in const contexts) if `N == 0` or `pulses > N`.
+pub(crate) const fn euclidean_array<const N: usize>(pulses: usize, rotation: usize) -> [bool; N] {
+    if N == 0 {
+        panic!("steps == 0");
+    }
+    if pulses > N {
+        panic!("pulses > steps");
+    }
+    let flat = bjorklund_fixed::<N>(N, pulses);
+
+    let mut out = [false; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = flat[(i + rotation % N) % N];
+        i += 1;
+    }
+    out
+}
+
+/// Unrotated Bjorklund pattern of `steps` steps in the first `steps` slots of
+/// a fixed-size buffer, so that no heap allocation is needed.
+///
+/// Rather than a list of groups it tracks the two distinct group shapes, `a`
+/// and `b`, and how many of each there are, which fits in arrays of `CAP`.
+/// Callers must ensure `0 < steps <= CAP` and `pulses <= steps`.
+pub(crate) const fn bjorklund_fixed<const CAP: usize>(steps: usize, pulses: usize) -> [bool; CAP] {
+    let mut flat = [false; CAP];
+    if pulses == 0 || pulses == steps {
+        let mut i = 0;
+        while i < steps {
+            flat[i] = pulses == steps;
+            i += 1;
+        }
+        return flat;
+    }
+
+    let mut a = [false; CAP];
+    let mut b = [false; CAP];
+    a[0] = true;
+    let (mut a_len, mut b_len) = (1, 1);
+    let (mut a_count, mut b_count) = (pulses, steps - pulses);
+
+    while b_count > 1 {
+        let pairs = if a_count < b_count { a_count } else { b_count };
+        // Leftover a-groups become the new b-groups; keep a copy before `a` grows.
+        let old_a = a;
+        let old_a_len = a_len;
+
+        let mut i = 0;
+        while i < b_len {
+            a[a_len + i] = b[i];
+            i += 1;
+        }
+        a_len += b_len;
+
+        if a_count > b_count {
+            b = old_a;
+            b_len = old_a_len;
+            b_count = a_count - pairs;
+        } else {
+            b_count -= pairs;
+        }
+        a_count = pairs;
+    }
+
+    let mut pos = 0;
+    let mut group = 0;
+    while group < a_count {
+        let mut i = 0;
+        while i < a_len {
+            flat[pos] = a[i];
+            pos += 1;
+            i += 1;
+        }
+        group += 1;
+    }
+    group = 0;
+    while group < b_count {
+        let mut i = 0;
+        while i < b_len {
+            flat[pos] = b[i];
+            pos += 1;
+            i += 1;
+        }
+        group += 1;
+    }
+    flat
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{euclidean, euclidean_const};
+
+    fn matches_runtime<const N: usize>() {
+        for pulses in 0..=N {
+            for rotation in [0, 1, N / 2, N + 1] {
+                assert_eq!(
+                    euclidean_const::<N>(pulses, rotation).to_vec(),
+                    euclidean(N as u8, pulses as u8, rotation as u8),
+                    "E({pulses},{N}) rot {rotation}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn const_generator_matches_bjorklund() {
+        matches_runtime::<1>();
+        matches_runtime::<5>();
+        matches_runtime::<8>();
+        matches_runtime::<12>();
+        matches_runtime::<13>();
+        matches_runtime::<16>();
+        matches_runtime::<24>();
+        matches_runtime::<31>();
+        matches_runtime::<64>();
+    }
+
+    #[test]
+    fn macro_in_static_table() {
+        static KIT: [[bool; 16]; 3] = [euclid!(4, 16), euclid!(3, 16, rot 4), euclid!(11, 16)];
+        assert_eq!(KIT[1].to_vec(), euclidean(16, 3, 4));
+        assert_eq!(KIT[2].iter().filter(|&&p| p).count(), 11);
+    }
+}
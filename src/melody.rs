@@ -0,0 +1,500 @@
+//! Pitch layers locked to rhythm onsets.
+//!
+//! Functions here attach notes to the pulses of a pattern, turning a rhythm
+//! into an arpeggio or a melody line.
+
+use crate::euclidean;
+use crate::rng::Rng;
+
+/// Order in which [`arpeggiate`] cycles through chord tones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpMode {
+    /// Lowest to highest, then wrap.
+    Up,
+    /// Highest to lowest, then wrap.
+    Down,
+    /// Up then down without repeating the top and bottom notes.
+    UpDown,
+    /// A seeded random chord tone on every onset.
+    Random {
+        /// Seed for the note choices.
+        seed: u64,
+    },
+}
+
+/// Assigns chord tones to the onsets of a pattern.
+///
+/// Chord tones are sorted before use, so `chord` may be given in any order.
+/// Returns `(step, note)` pairs for every pulse of `pattern`, or nothing if
+/// the chord is empty.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{ArpMode, arpeggiate, euclidean};
+///
+/// let pattern = euclidean(8, 5, 0); // x.xx.xx.
+/// let notes = arpeggiate(&pattern, &[60, 64, 67], ArpMode::UpDown);
+/// assert_eq!(notes, vec![(0, 60), (2, 64), (3, 67), (5, 64), (6, 60)]);
+/// ```
+pub fn arpeggiate(pattern: &[bool], chord: &[u8], mode: ArpMode) -> Vec<(usize, u8)> {
+    if chord.is_empty() {
+        return Vec::new();
+    }
+    let mut tones = chord.to_vec();
+    tones.sort_unstable();
+    let len = tones.len();
+
+    let mut rng = match mode {
+        ArpMode::Random { seed } => Some(Rng::new(seed)),
+        _ => None,
+    };
+
+    pattern
+        .iter()
+        .enumerate()
+        .filter(|&(_, &pulse)| pulse)
+        .enumerate()
+        .map(|(n, (step, _))| {
+            let index = match mode {
+                ArpMode::Up => n % len,
+                ArpMode::Down => len - 1 - n % len,
+                ArpMode::UpDown => ping_pong(n, len),
+                ArpMode::Random { .. } => rng.as_mut().map_or(0, |r| r.below(len)),
+            };
+            (step, tones[index])
+        })
+        .collect()
+}
+
+/// Stroke direction of [`strum`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrumDirection {
+    /// Every stroke from the lowest note to the highest, like a guitar
+    /// downstroke.
+    Down,
+    /// Every stroke from the highest note to the lowest.
+    Up,
+    /// Down and up strokes in turn, starting down, one per onset.
+    Alternate,
+    /// Down on even steps and up on odd ones, as a strumming hand that keeps
+    /// moving on every step whether or not it hits the strings.
+    Pendulum,
+}
+
+/// One note of a strummed chord, as produced by [`strum`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrumNote {
+    /// Step of the onset the stroke belongs to.
+    pub step: usize,
+    /// Delay after the step, in steps.
+    pub offset: f32,
+    /// The chord tone.
+    pub note: u8,
+}
+
+/// Strums `chord` on every onset of a pattern.
+///
+/// Each stroke spreads the chord tones evenly over `spread_steps` steps
+/// (fractions are fine; 0 plays a block chord), the first tone on the step
+/// itself. Chord tones are sorted first, so `chord` may be given in any
+/// order. Returns the notes of every stroke in the order they sound, or
+/// nothing if the chord is empty. A negative or non-finite spread counts
+/// as 0.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{StrumDirection, euclidean, strum};
+///
+/// // x..x..x. with an E minor triad: down, up, down.
+/// let notes = strum(&euclidean(8, 3, 0), &[64, 55, 59], 0.25, StrumDirection::Alternate);
+/// let stroke: Vec<(usize, u8)> = notes[3..6].iter().map(|n| (n.step, n.note)).collect();
+/// assert_eq!(stroke, [(3, 64), (3, 59), (3, 55)]);
+/// assert_eq!(notes[5].offset, 0.25);
+/// ```
+pub fn strum(
+    pattern: &[bool],
+    chord: &[u8],
+    spread_steps: f32,
+    direction: StrumDirection,
+) -> Vec<StrumNote> {
+    let mut tones = chord.to_vec();
+    tones.sort_unstable();
+    let spread = if spread_steps.is_finite() {
+        spread_steps.max(0.0)
+    } else {
+        0.0
+    };
+    // The gap between successive tones of a stroke.
+    let gap = spread / tones.len().saturating_sub(1).max(1) as f32;
+
+    let mut notes = Vec::with_capacity(tones.len() * pattern.len());
+    let onsets = (0..pattern.len()).filter(|&step| pattern[step]);
+    for (n, step) in onsets.enumerate() {
+        let down = match direction {
+            StrumDirection::Down => true,
+            StrumDirection::Up => false,
+            StrumDirection::Alternate => n.is_multiple_of(2),
+            StrumDirection::Pendulum => step.is_multiple_of(2),
+        };
+        for i in 0..tones.len() {
+            let tone = if down { i } else { tones.len() - 1 - i };
+            notes.push(StrumNote {
+                step,
+                offset: gap * i as f32,
+                note: tones[tone],
+            });
+        }
+    }
+    notes
+}
+
+/// How [`map_onsets`] walks through its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapPolicy {
+    /// In order, wrapping back to the first value.
+    Cycle,
+    /// Forward then backward without repeating the end values.
+    PingPong,
+    /// A seeded random value on every onset.
+    Random {
+        /// Seed for the value choices.
+        seed: u64,
+    },
+}
+
+/// Attaches a payload to every onset of a pattern.
+///
+/// Returns one entry per step: `Some(value)` on pulses and `None` on rests.
+/// An empty `values` slice yields all `None`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MapPolicy, euclidean, map_onsets};
+///
+/// let lane = map_onsets(&euclidean(8, 5, 0), &["kick", "snare", "tom"], MapPolicy::PingPong);
+/// let hits: Vec<&str> = lane.into_iter().flatten().collect();
+/// assert_eq!(hits, vec!["kick", "snare", "tom", "snare", "kick"]);
+/// ```
+pub fn map_onsets<T: Clone>(pattern: &[bool], values: &[T], policy: MapPolicy) -> Vec<Option<T>> {
+    if values.is_empty() {
+        return vec![None; pattern.len()];
+    }
+    let len = values.len();
+    let mut rng = match policy {
+        MapPolicy::Random { seed } => Some(Rng::new(seed)),
+        _ => None,
+    };
+    let mut n = 0;
+
+    pattern
+        .iter()
+        .map(|&pulse| {
+            if !pulse {
+                return None;
+            }
+            let index = match policy {
+                MapPolicy::Cycle => n % len,
+                MapPolicy::PingPong => ping_pong(n, len),
+                MapPolicy::Random { .. } => rng.as_mut().map_or(0, |r| r.below(len)),
+            };
+            n += 1;
+            Some(values[index].clone())
+        })
+        .collect()
+}
+
+/// Index of the `n`th element when bouncing between the ends of `len` items.
+fn ping_pong(n: usize, len: usize) -> usize {
+    if len == 1 {
+        return 0;
+    }
+    let period = 2 * (len - 1);
+    let i = n % period;
+    if i < len { i } else { period - i }
+}
+
+/// Assigns successive pitches to successive onsets, wrapping around `pitches`.
+///
+/// Returns one entry per step: `Some(pitch)` on pulses and `None` on rests.
+/// Pitches are free-form (MIDI notes, semitone offsets, scale degrees); see
+/// `melody_to_midi` (with the `midi` feature) to export the result. An empty
+/// `pitches` slice yields all `None`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, melody};
+///
+/// let line = melody(&euclidean(8, 3, 0), &[0, 7]);
+/// assert_eq!(
+///     line,
+///     vec![Some(0), None, None, Some(7), None, None, Some(0), None]
+/// );
+/// ```
+pub fn melody(pattern: &[bool], pitches: &[i8]) -> Vec<Option<i8>> {
+    map_onsets(pattern, pitches, MapPolicy::Cycle)
+}
+
+/// An isorhythm: a rhythmic pattern (talea) cycled against a sequence of
+/// values (color) of a different length.
+///
+/// Each onset of the talea takes the next color value. Because the two cycle
+/// independently, the combined pattern only repeats after
+/// [`period_steps`](Self::period_steps) steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Isorhythm, euclidean};
+///
+/// // Three onsets against a four-note color: realigns after four taleas.
+/// let iso = Isorhythm::new(euclidean(8, 3, 0), vec!['C', 'E', 'G', 'B']);
+/// assert_eq!(iso.period_taleas(), 4);
+/// assert_eq!(iso.period_steps(), 32);
+///
+/// let first: Vec<_> = iso.events().take(4).collect();
+/// assert_eq!(first, vec![(0, 'C'), (3, 'E'), (6, 'G'), (8, 'B')]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Isorhythm<T> {
+    talea: Vec<bool>,
+    color: Vec<T>,
+}
+
+impl<T: Clone> Isorhythm<T> {
+    /// Combines a talea and a color.
+    pub fn new(talea: Vec<bool>, color: Vec<T>) -> Self {
+        Self { talea, color }
+    }
+
+    /// The rhythmic pattern.
+    pub fn talea(&self) -> &[bool] {
+        &self.talea
+    }
+
+    /// The value sequence.
+    pub fn color(&self) -> &[T] {
+        &self.color
+    }
+
+    /// Number of talea repetitions before talea and color realign, or 0 if
+    /// the talea has no onsets or the color is empty.
+    pub fn period_taleas(&self) -> usize {
+        let onsets = self.talea.iter().filter(|&&p| p).count();
+        if onsets == 0 || self.color.is_empty() {
+            return 0;
+        }
+        lcm(onsets, self.color.len()) / onsets
+    }
+
+    /// Length of the combined period in steps (0 if there are no events).
+    pub fn period_steps(&self) -> usize {
+        self.period_taleas() * self.talea.len()
+    }
+
+    /// Endless stream of `(absolute_step, value)` events.
+    pub fn events(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        let len = self.talea.len();
+        let active = self.period_taleas() > 0;
+        (0..)
+            .take_while(move |_| active)
+            .filter(move |step| self.talea[step % len])
+            .enumerate()
+            .map(move |(n, step)| (step, self.color[n % self.color.len()].clone()))
+    }
+
+    /// All events of one combined period.
+    pub fn cycle(&self) -> Vec<(usize, T)> {
+        let period = self.period_steps();
+        self.events()
+            .take_while(|&(step, _)| step < period)
+            .collect()
+    }
+}
+
+/// A chord change produced by [`harmonic_rhythm`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChordChange<T> {
+    /// Step at which the chord starts.
+    pub step: usize,
+    /// Number of steps the chord is held, up to the next change (wrapping
+    /// into the next cycle for the last change).
+    pub hold: usize,
+    /// The chord itself.
+    pub chord: T,
+}
+
+/// Places chord changes on the onsets of E(`pulses`, `steps`).
+///
+/// Chords are taken in order, wrapping around when there are more onsets than
+/// chords. The chord type is up to the caller: symbols, note lists, or a
+/// voicing struct all work.
+///
+/// # Panics
+/// Panics if `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::harmonic_rhythm;
+///
+/// let changes = harmonic_rhythm(&["Am", "F", "C"], 8, 3, 0);
+/// let summary: Vec<_> = changes.iter().map(|c| (c.step, c.hold, c.chord)).collect();
+/// assert_eq!(summary, vec![(0, 3, "Am"), (3, 3, "F"), (6, 2, "C")]);
+/// ```
+pub fn harmonic_rhythm<T: Clone>(
+    chords: &[T],
+    steps: u8,
+    pulses: u8,
+    rotation: u8,
+) -> Vec<ChordChange<T>> {
+    let pattern = euclidean(steps, pulses, rotation);
+    if chords.is_empty() {
+        return Vec::new();
+    }
+    let onsets: Vec<usize> = (0..pattern.len()).filter(|&i| pattern[i]).collect();
+    onsets
+        .iter()
+        .enumerate()
+        .map(|(n, &step)| {
+            let next = onsets
+                .get(n + 1)
+                .copied()
+                .unwrap_or(onsets[0] + pattern.len());
+            ChordChange {
+                step,
+                hold: next - step,
+                chord: chords[n % chords.len()].clone(),
+            }
+        })
+        .collect()
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    a / gcd(a, b) * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arp_orders() {
+        let pattern = euclidean(8, 5, 0);
+        let chord = [67, 60, 64];
+        let notes = |mode| -> Vec<u8> {
+            arpeggiate(&pattern, &chord, mode)
+                .into_iter()
+                .map(|(_, n)| n)
+                .collect()
+        };
+        assert_eq!(notes(ArpMode::Up), vec![60, 64, 67, 60, 64]);
+        assert_eq!(notes(ArpMode::Down), vec![67, 64, 60, 67, 64]);
+        assert_eq!(notes(ArpMode::UpDown), vec![60, 64, 67, 64, 60]);
+
+        let random = notes(ArpMode::Random { seed: 5 });
+        assert_eq!(random, notes(ArpMode::Random { seed: 5 }));
+        assert!(random.iter().all(|n| chord.contains(n)));
+    }
+
+    #[test]
+    fn arp_edge_cases() {
+        let pattern = euclidean(4, 2, 0);
+        assert!(arpeggiate(&pattern, &[], ArpMode::Up).is_empty());
+        assert_eq!(
+            arpeggiate(&pattern, &[48], ArpMode::UpDown),
+            vec![(0, 48), (2, 48)]
+        );
+    }
+
+    #[test]
+    fn strums_spread_and_alternate() {
+        let pattern = [true, true, false, true];
+        let strokes = |direction| -> Vec<Vec<u8>> {
+            strum(&pattern, &[60, 64, 67], 0.5, direction)
+                .chunks(3)
+                .map(|stroke| stroke.iter().map(|n| n.note).collect())
+                .collect()
+        };
+        let (down, up) = ([60, 64, 67], [67, 64, 60]);
+        assert_eq!(strokes(StrumDirection::Down), [down, down, down]);
+        assert_eq!(strokes(StrumDirection::Up), [up, up, up]);
+        assert_eq!(strokes(StrumDirection::Alternate), [down, up, down]);
+        assert_eq!(strokes(StrumDirection::Pendulum), [down, up, up]);
+
+        let offsets: Vec<f32> = strum(&pattern, &[60, 64, 67], 0.5, StrumDirection::Down)
+            .iter()
+            .take(3)
+            .map(|n| n.offset)
+            .collect();
+        assert_eq!(offsets, [0.0, 0.25, 0.5]);
+        let block = strum(&pattern, &[48], f32::NAN, StrumDirection::Up);
+        assert!(block.iter().all(|n| n.offset == 0.0));
+        assert!(strum(&pattern, &[], 1.0, StrumDirection::Down).is_empty());
+    }
+
+    #[test]
+    fn melody_wraps_pitches() {
+        let line = melody(&euclidean(8, 5, 0), &[1, 2, 3]);
+        let pitches: Vec<i8> = line.iter().flatten().copied().collect();
+        assert_eq!(pitches, vec![1, 2, 3, 1, 2]);
+        assert_eq!(line.len(), 8);
+        assert!(melody(&euclidean(8, 5, 0), &[]).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn isorhythm_period_and_cycle() {
+        let iso = Isorhythm::new(euclidean(16, 5, 0), vec![60, 62, 64, 65, 67, 69, 71]);
+        assert_eq!(iso.period_taleas(), 7);
+        assert_eq!(iso.period_steps(), 112);
+
+        let cycle = iso.cycle();
+        assert_eq!(cycle.len(), 35);
+        // The next period starts like the first one.
+        let next: Vec<_> = iso.events().skip(35).take(3).collect();
+        let shifted: Vec<_> = cycle[..3].iter().map(|&(s, v)| (s + 112, v)).collect();
+        assert_eq!(next, shifted);
+    }
+
+    #[test]
+    fn isorhythm_without_events() {
+        let iso = Isorhythm::new(vec![false; 4], vec![1, 2]);
+        assert_eq!(iso.period_steps(), 0);
+        assert_eq!(iso.events().next(), None);
+        let iso: Isorhythm<u8> = Isorhythm::new(euclidean(4, 1, 0), vec![]);
+        assert!(iso.cycle().is_empty());
+    }
+
+    #[test]
+    fn harmonic_rhythm_holds_wrap() {
+        let changes = harmonic_rhythm(&[1, 4, 5, 1], 16, 3, 1);
+        let total: usize = changes.iter().map(|c| c.hold).sum();
+        assert_eq!(total, 16);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[2].chord, 5);
+        assert!(harmonic_rhythm::<u8>(&[], 8, 3, 0).is_empty());
+    }
+
+    #[test]
+    fn map_onsets_policies() {
+        let pattern = euclidean(8, 5, 1);
+        let values = [10, 20, 30];
+        let hits = |policy| -> Vec<i32> {
+            map_onsets(&pattern, &values, policy)
+                .into_iter()
+                .flatten()
+                .collect()
+        };
+        assert_eq!(hits(MapPolicy::Cycle), vec![10, 20, 30, 10, 20]);
+        assert_eq!(hits(MapPolicy::PingPong), vec![10, 20, 30, 20, 10]);
+        let random = hits(MapPolicy::Random { seed: 9 });
+        assert_eq!(random, hits(MapPolicy::Random { seed: 9 }));
+        assert!(random.iter().all(|v| values.contains(v)));
+
+        let lane = map_onsets(&pattern, &values, MapPolicy::Cycle);
+        for (step, value) in lane.iter().enumerate() {
+            assert_eq!(value.is_some(), pattern[step]);
+        }
+    }
+}
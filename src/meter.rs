@@ -0,0 +1,272 @@
+//! Meters and metrical inference.
+//!
+//! A [`Meter`] describes how the steps of a bar group into beats, either
+//! uniformly (four beats of four sixteenths) or additively (3+3+2).
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A bar layout given as the number of steps in each beat.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::Meter;
+///
+/// let common = Meter::simple(4, 4);
+/// assert_eq!(common.bar_len(), 16);
+///
+/// let tresillo = Meter::additive(&[3, 3, 2]);
+/// assert_eq!(tresillo.beat_starts(), vec![0, 3, 6]);
+/// assert_eq!(tresillo.to_string(), "3+3+2");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Meter {
+    groups: Vec<u8>,
+}
+
+impl Meter {
+    /// A meter of `beats` beats with `steps_per_beat` steps each.
+    ///
+    /// # Panics
+    /// Panics if either argument is zero.
+    pub fn simple(beats: u8, steps_per_beat: u8) -> Self {
+        unwrap_or_panic(Self::try_simple(beats, steps_per_beat))
+    }
+
+    /// Like [`simple`](Self::simple), but reports zero arguments as an error.
+    ///
+    /// # Errors
+    /// Returns [`Error::ZeroSteps`] if either argument is zero.
+    pub fn try_simple(beats: u8, steps_per_beat: u8) -> Result<Self> {
+        Self::try_additive(&vec![steps_per_beat; usize::from(beats)])
+    }
+
+    /// A meter with beats of the given (possibly unequal) lengths.
+    ///
+    /// # Panics
+    /// Panics if `groups` is empty or contains a zero.
+    pub fn additive(groups: &[u8]) -> Self {
+        unwrap_or_panic(Self::try_additive(groups))
+    }
+
+    /// Like [`additive`](Self::additive), but reports an empty or zero-length
+    /// beat as an error.
+    ///
+    /// # Errors
+    /// Returns [`Error::ZeroSteps`] if `groups` is empty or contains a zero.
+    pub fn try_additive(groups: &[u8]) -> Result<Self> {
+        if groups.is_empty() || groups.contains(&0) {
+            return Err(Error::ZeroSteps);
+        }
+        Ok(Self {
+            groups: groups.to_vec(),
+        })
+    }
+
+    /// Steps in each beat.
+    pub fn groups(&self) -> &[u8] {
+        &self.groups
+    }
+
+    /// Number of beats per bar.
+    pub fn beats(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Number of steps per bar.
+    pub fn bar_len(&self) -> usize {
+        self.groups.iter().map(|&g| usize::from(g)).sum()
+    }
+
+    /// Returns `true` if every beat has the same length.
+    pub fn is_uniform(&self) -> bool {
+        self.groups.windows(2).all(|w| w[0] == w[1])
+    }
+
+    /// Step index within the bar at which each beat starts.
+    pub fn beat_starts(&self) -> Vec<usize> {
+        self.groups
+            .iter()
+            .scan(0, |pos, &g| {
+                let start = *pos;
+                *pos += usize::from(g);
+                Some(start)
+            })
+            .collect()
+    }
+
+    /// Metrical weight of a step (taken modulo the bar length): 3 on the
+    /// downbeat, 2 on other beats, 1 on even subdivisions inside beats of four
+    /// or more steps, 0 elsewhere.
+    pub fn weight(&self, step: usize) -> u8 {
+        let mut pos = step % self.bar_len();
+        if pos == 0 {
+            return 3;
+        }
+        for &group in &self.groups {
+            let group = usize::from(group);
+            if pos < group {
+                return match pos {
+                    0 => 2,
+                    p if group >= 4 && p % 2 == 0 => 1,
+                    _ => 0,
+                };
+            }
+            pos -= group;
+        }
+        0
+    }
+}
+
+impl fmt::Display for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, g) in self.groups.iter().enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+            write!(f, "{g}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "analysis")]
+/// Ranks plausible meters for a pattern, best first.
+///
+/// Candidates are bar lengths that divide the pattern length, each grouped
+/// into equal beats or into additive combinations of 2s and 3s. Each is scored
+/// by the correlation between the pattern's onsets and the meter's metrical
+/// weights (see [`Meter::weight`]), from -1.0 to 1.0. Ties keep simpler meters
+/// (one bar per cycle, equal beats) first.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Meter, euclidean, infer_meter};
+///
+/// let ranked = infer_meter(&euclidean(8, 3, 0));
+/// assert_eq!(ranked[0].0, Meter::additive(&[3, 3, 2]));
+/// ```
+pub fn infer_meter(pattern: &[bool]) -> Vec<(Meter, f64)> {
+    let mut scored: Vec<(Meter, f64)> = candidate_meters(pattern.len())
+        .into_iter()
+        .map(|meter| {
+            let score = correlation(pattern, &meter);
+            (meter, score)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored
+}
+
+#[cfg(feature = "analysis")]
+/// Candidate meters for a cycle of `len` steps, in order of simplicity.
+fn candidate_meters(len: usize) -> Vec<Meter> {
+    let bar_lens = (2..=len.min(255)).rev().filter(|l| len.is_multiple_of(*l));
+
+    let mut meters = Vec::new();
+    for bar in bar_lens {
+        for beat in 2..bar {
+            if bar.is_multiple_of(beat) && bar / beat <= 255 {
+                meters.push(Meter::simple((bar / beat) as u8, beat as u8));
+            }
+        }
+        // Additive groupings of 2s and 3s; capped to keep the search small.
+        if bar <= 16 {
+            let mut groups = Vec::new();
+            additive_groupings(bar, &mut groups, &mut meters);
+        }
+    }
+
+    let mut unique = Vec::with_capacity(meters.len());
+    for meter in meters {
+        if !unique.contains(&meter) {
+            unique.push(meter);
+        }
+    }
+    unique
+}
+
+#[cfg(feature = "analysis")]
+fn additive_groupings(remaining: usize, groups: &mut Vec<u8>, out: &mut Vec<Meter>) {
+    if remaining == 0 {
+        if groups.len() > 1 {
+            out.push(Meter::additive(groups));
+        }
+        return;
+    }
+    for size in [3u8, 2] {
+        if usize::from(size) <= remaining {
+            groups.push(size);
+            additive_groupings(remaining - usize::from(size), groups, out);
+            groups.pop();
+        }
+    }
+}
+
+#[cfg(feature = "analysis")]
+/// Pearson correlation between onsets and metrical weights over one cycle.
+pub(crate) fn correlation(pattern: &[bool], meter: &Meter) -> f64 {
+    let n = pattern.len() as f64;
+    if pattern.is_empty() {
+        return 0.0;
+    }
+    let xs: Vec<f64> = pattern.iter().map(|&p| f64::from(u8::from(p))).collect();
+    let ys: Vec<f64> = (0..pattern.len())
+        .map(|i| f64::from(meter.weight(i)))
+        .collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let mut cov = 0.0;
+    let mut var_x = 0.0;
+    let mut var_y = 0.0;
+    for (x, y) in xs.iter().zip(&ys) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        0.0
+    } else {
+        cov / (var_x * var_y).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "analysis")]
+    use crate::euclidean;
+
+    #[test]
+    fn weights() {
+        let meter = Meter::simple(4, 4);
+        let weights: Vec<u8> = (0..8).map(|i| meter.weight(i)).collect();
+        assert_eq!(weights, vec![3, 0, 1, 0, 2, 0, 1, 0]);
+        assert_eq!(meter.weight(16), 3);
+        assert!(meter.is_uniform());
+        assert!(!Meter::additive(&[3, 3, 2]).is_uniform());
+        assert_eq!(Meter::try_simple(0, 4), Err(Error::ZeroSteps));
+        assert_eq!(Meter::try_additive(&[3, 0]), Err(Error::ZeroSteps));
+    }
+
+    #[cfg(feature = "analysis")]
+    #[test]
+    fn four_on_the_floor_is_simple_meter() {
+        let ranked = infer_meter(&euclidean(16, 4, 0));
+        let best = &ranked[0].0;
+        assert!(best.is_uniform());
+        assert_eq!(best.groups()[0], 4);
+        assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[cfg(feature = "analysis")]
+    #[test]
+    fn twelve_eight_bell() {
+        // Beats every three steps in a 12-step cycle.
+        let pattern: Vec<bool> = (0..12).map(|i| i % 3 == 0).collect();
+        let ranked = infer_meter(&pattern);
+        assert_eq!(ranked[0].0.groups()[0], 3);
+    }
+}
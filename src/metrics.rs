@@ -0,0 +1,165 @@
+//! Groove metrics and rotation ranking.
+//!
+//! Every rotation of a Euclidean rhythm has the same onsets but a different
+//! feel: E(3,8) is the tresillo on rotation 0 and an off-beat figure on
+//! rotation 1. The metrics here put numbers on that difference, and
+//! [`rank_rotations`] uses them to shortlist rotations instead of trying
+//! each one by ear.
+
+use crate::error::{Result, check_params, unwrap_or_panic};
+use crate::euclidean;
+use crate::meter::{Meter, correlation};
+
+/// A property of a pattern to rank rotations by; higher scores rank first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GrooveMetric {
+    /// How much the onsets cut across the meter; see [`syncopation`].
+    Syncopation(Meter),
+    /// How many onsets avoid the regular beat grids; see [`off_beatness`].
+    OffBeatness,
+    /// How well the onsets follow the meter's strong beats; see
+    /// [`metrical_fit`].
+    MetricalFit(Meter),
+}
+
+impl GrooveMetric {
+    /// Scores `pattern` with this metric.
+    pub fn score(&self, pattern: &[bool]) -> f64 {
+        match self {
+            GrooveMetric::Syncopation(meter) => f64::from(syncopation(pattern, meter)),
+            GrooveMetric::OffBeatness => off_beatness(pattern) as f64,
+            GrooveMetric::MetricalFit(meter) => metrical_fit(pattern, meter),
+        }
+    }
+}
+
+/// Longuet-Higgins and Lee syncopation: every onset held through a rest on
+/// a stronger metrical position (see [`Meter::weight`]) before the next
+/// onset scores the difference in weight. The pattern loops, so the last
+/// onset is held into the next cycle.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Meter, syncopation};
+///
+/// let meter = Meter::simple(2, 4);
+/// assert_eq!(syncopation(&[true, false, false, false, true, false, false, false], &meter), 0);
+/// // Pushing the second onset early skips the beat at step 4.
+/// assert_eq!(syncopation(&[true, false, false, true, false, false, false, false], &meter), 2);
+/// ```
+pub fn syncopation(pattern: &[bool], meter: &Meter) -> u32 {
+    let onsets: Vec<usize> = (0..pattern.len()).filter(|&i| pattern[i]).collect();
+    let len = pattern.len();
+    onsets
+        .iter()
+        .enumerate()
+        .map(|(n, &onset)| {
+            let next = onsets.get(n + 1).copied().unwrap_or(onsets[0] + len);
+            let held = meter.weight(onset);
+            let strongest = (onset + 1..next)
+                .map(|step| meter.weight(step % len))
+                .max()
+                .unwrap_or(0);
+            u32::from(strongest.saturating_sub(held))
+        })
+        .sum()
+}
+
+/// Toussaint's off-beatness: the number of onsets on steps that no regular
+/// polygon through step 0 reaches, i.e. steps sharing no factor with the
+/// pattern length. These are the steps farthest from any even subdivision
+/// of the cycle, such as the odd steps of a 16-step bar.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, off_beatness};
+///
+/// assert_eq!(off_beatness(&euclidean(8, 3, 0)), 1); // x..x..x.
+/// assert_eq!(off_beatness(&euclidean(8, 3, 1)), 2); // ..x..x.x
+/// ```
+pub fn off_beatness(pattern: &[bool]) -> usize {
+    (1..pattern.len())
+        .filter(|&step| pattern[step] && gcd(step, pattern.len()) == 1)
+        .count()
+}
+
+/// Correlation between the onsets and the meter's metrical weights, from
+/// -1.0 (onsets only on weak steps) to 1.0, as used by
+/// [`infer_meter`](crate::infer_meter).
+pub fn metrical_fit(pattern: &[bool], meter: &Meter) -> f64 {
+    correlation(pattern, meter)
+}
+
+/// Returns the distinct rotations of E(`pulses`, `steps`) with their score
+/// under `metric`, best first.
+///
+/// Rotations that repeat an earlier rotation's pattern (as E(4,16) does
+/// every four steps) are left out. Ties keep the smaller rotation first.
+///
+/// # Panics
+/// Panics if `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{GrooveMetric, Meter, rank_rotations};
+///
+/// // The most syncopated rotations of the tresillo against 4/4 eighths.
+/// let ranked = rank_rotations(8, 3, &GrooveMetric::Syncopation(Meter::simple(4, 2)));
+/// assert_eq!(ranked[0], (1, 5.0)); // ..x..x.x
+/// // The plain tresillo is among the least syncopated.
+/// assert!(ranked.ends_with(&[(0, 2.0), (6, 2.0)]));
+/// ```
+pub fn rank_rotations(steps: u8, pulses: u8, metric: &GrooveMetric) -> Vec<(u8, f64)> {
+    unwrap_or_panic(try_rank_rotations(steps, pulses, metric))
+}
+
+/// Like [`rank_rotations`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Fails if `steps == 0` or `pulses > steps`.
+pub fn try_rank_rotations(steps: u8, pulses: u8, metric: &GrooveMetric) -> Result<Vec<(u8, f64)>> {
+    check_params(usize::from(steps), usize::from(pulses))?;
+    let mut seen: Vec<Vec<bool>> = Vec::new();
+    let mut ranked = Vec::new();
+    for rotation in 0..steps {
+        let pattern = euclidean(steps, pulses, rotation);
+        if seen.contains(&pattern) {
+            continue;
+        }
+        ranked.push((rotation, metric.score(&pattern)));
+        seen.push(pattern);
+    }
+    // A stable sort keeps ties in rotation order.
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(ranked)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_score_rotations() {
+        let meter = Meter::simple(4, 4);
+        let son = euclidean(16, 5, 0);
+        assert_eq!(syncopation(&[false; 16], &meter), 0);
+        assert!(syncopation(&euclidean(16, 5, 3), &meter) > syncopation(&[true; 16], &meter));
+        assert_eq!(off_beatness(&[true; 12]), 4);
+        assert!(metrical_fit(&euclidean(16, 4, 0), &meter) > metrical_fit(&son, &meter));
+    }
+
+    #[test]
+    fn ranking_skips_repeats_and_orders_ties() {
+        let ranked = rank_rotations(16, 4, &GrooveMetric::MetricalFit(Meter::simple(4, 4)));
+        let rotations: Vec<u8> = ranked.iter().map(|&(r, _)| r).collect();
+        assert_eq!(rotations, [0, 2, 1, 3]);
+
+        let all = rank_rotations(8, 8, &GrooveMetric::OffBeatness);
+        assert_eq!(all, [(0, 4.0)]);
+        assert!(try_rank_rotations(0, 0, &GrooveMetric::OffBeatness).is_err());
+    }
+}
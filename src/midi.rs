@@ -0,0 +1,827 @@
+//! Standard MIDI File export and import.
+//!
+//! A minimal, dependency-free SMF writer. Each step of a pattern becomes a
+//! fixed number of ticks; pulses become note-on/note-off pairs. In the other
+//! direction, [`grid_from_smf`] reads a drum part back into a
+//! [`PatternBank`].
+//!
+//! For callers with their own SMF pipeline (e.g. built on `midly`), the same
+//! logic is exposed as delta-timed [`TimedMessage`]s carrying raw channel
+//! message bytes, which map one-to-one onto `midly::TrackEvent`s. The crate
+//! does not depend on `midly` (there is no `midly` feature or `From`
+//! conversion), so the mapping is the caller's:
+//!
+//! ```text
+//! let kind = LiveEvent::parse(&msg.bytes)?.as_track_event(&mut arena);
+//! TrackEvent { delta: msg.delta.into(), kind }
+//! ```
+
+use crate::bank::{PatternBank, Resolution};
+use crate::error::{Error, MAX_INPUT_STEPS, Result, check_input_steps, unwrap_or_panic};
+#[cfg(feature = "sequencer")]
+use crate::sequencer::StepEvent;
+use std::io;
+use std::path::Path;
+
+/// Settings shared by the MIDI exporters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiOptions {
+    /// Ticks per quarter note (the SMF division).
+    pub ppq: u16,
+    /// Pattern steps per quarter note (4 = sixteenth notes).
+    pub steps_per_beat: u16,
+    /// Tempo written into the file.
+    pub bpm: f64,
+    /// MIDI channel, 0-15.
+    pub channel: u8,
+    /// Note-on velocity, 1-127.
+    pub velocity: u8,
+    /// Note length as a fraction of one step (at least one tick is used).
+    pub gate: f32,
+}
+
+impl Default for MidiOptions {
+    fn default() -> Self {
+        Self {
+            ppq: 96,
+            steps_per_beat: 4,
+            bpm: 120.0,
+            channel: 0,
+            velocity: 100,
+            gate: 0.5,
+        }
+    }
+}
+
+impl MidiOptions {
+    /// Number of ticks in one pattern step.
+    pub fn ticks_per_step(&self) -> u32 {
+        (u32::from(self.ppq) / u32::from(self.steps_per_beat.max(1))).max(1)
+    }
+
+    /// Sets `steps_per_beat` from a step resolution, such as a bank's.
+    #[must_use]
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.steps_per_beat = resolution.steps_per_beat();
+        self
+    }
+}
+
+/// A note to be written, positioned on the step grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct StepNote {
+    pub(crate) step: usize,
+    pub(crate) note: u8,
+    pub(crate) velocity: u8,
+    /// Hits within the step; 1 for a single note.
+    pub(crate) ratchet: u8,
+    /// Note length in steps, overriding [`MidiOptions::gate`]; may exceed 1.
+    pub(crate) gate: Option<f32>,
+}
+
+/// A channel message with its delta time in ticks since the previous message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedMessage {
+    /// Ticks since the previous message (or since the start of the track).
+    pub delta: u32,
+    /// Raw MIDI bytes: status, data 1, data 2.
+    pub bytes: [u8; 3],
+}
+
+/// Exports a pattern as a single-track MIDI file triggering `note` on every pulse.
+///
+/// Returns the bytes of a format-0 Standard MIDI File.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, euclidean, pattern_to_midi};
+///
+/// let bytes = pattern_to_midi(&euclidean(8, 3, 0), 36, &MidiOptions::default());
+/// assert_eq!(&bytes[..4], b"MThd");
+/// // std::fs::write("tresillo.mid", bytes)?;
+/// ```
+///
+/// # Panics
+/// Panics if `note` or the channel in `options` is out of the MIDI range.
+pub fn pattern_to_midi(pattern: &[bool], note: u8, options: &MidiOptions) -> Vec<u8> {
+    unwrap_or_panic(try_pattern_to_midi(pattern, note, options))
+}
+
+/// Like [`pattern_to_midi`], but reports an invalid note or channel as an
+/// error.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] if `note > 127` or
+/// `options.channel > 15`.
+pub fn try_pattern_to_midi(pattern: &[bool], note: u8, options: &MidiOptions) -> Result<Vec<u8>> {
+    check_note(note, options)?;
+    Ok(write_smf(
+        &pattern_notes(pattern, note, options),
+        pattern.len(),
+        options,
+    ))
+}
+
+/// Converts a pattern into delta-timed note-on/note-off messages for `note`,
+/// without any file framing or meta events.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, pattern_to_messages};
+///
+/// let messages = pattern_to_messages(&[true, false, true, false], 36, &MidiOptions::default());
+/// // 24 ticks per step, 12-tick gates.
+/// let deltas: Vec<u32> = messages.iter().map(|m| m.delta).collect();
+/// assert_eq!(deltas, vec![0, 12, 36, 12]);
+/// assert_eq!(messages[0].bytes, [0x90, 36, 100]);
+/// ```
+///
+/// # Panics
+/// Panics if `note` or the channel in `options` is out of the MIDI range.
+pub fn pattern_to_messages(pattern: &[bool], note: u8, options: &MidiOptions) -> Vec<TimedMessage> {
+    unwrap_or_panic(try_pattern_to_messages(pattern, note, options))
+}
+
+/// Like [`pattern_to_messages`], but reports an invalid note or channel as an
+/// error.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] if `note > 127` or
+/// `options.channel > 15`.
+pub fn try_pattern_to_messages(
+    pattern: &[bool],
+    note: u8,
+    options: &MidiOptions,
+) -> Result<Vec<TimedMessage>> {
+    check_note(note, options)?;
+    Ok(note_messages(
+        &pattern_notes(pattern, note, options),
+        options,
+    ))
+}
+
+/// Exports per-step gate lengths (see the [`gate`](crate::gate) module) as
+/// a single-track MIDI file, holding `note` for each gate.
+///
+/// Each note-off is placed at its gate length, so gates longer than a step
+/// tie over the following steps. A gate is cut short where the next note
+/// starts, with the note-off written before the next note-on, and at the end
+/// of the pattern, so the file loops cleanly. Steps with a gate of zero (or
+/// below, or NaN) are rests.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, euclidean, gates_to_midi, legato};
+///
+/// // A tied bass line: each note held until the next.
+/// let bytes = gates_to_midi(&legato(&euclidean(8, 3, 0)), 36, &MidiOptions::default());
+/// assert_eq!(&bytes[..4], b"MThd");
+/// ```
+///
+/// # Panics
+/// Panics if `note` or the channel in `options` is out of the MIDI range.
+pub fn gates_to_midi(gates: &[f32], note: u8, options: &MidiOptions) -> Vec<u8> {
+    unwrap_or_panic(try_gates_to_midi(gates, note, options))
+}
+
+/// Like [`gates_to_midi`], but reports an invalid note or channel as an
+/// error.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] if `note > 127` or
+/// `options.channel > 15`.
+pub fn try_gates_to_midi(gates: &[f32], note: u8, options: &MidiOptions) -> Result<Vec<u8>> {
+    check_note(note, options)?;
+    Ok(write_smf(
+        &gate_notes(gates, note, options),
+        gates.len(),
+        options,
+    ))
+}
+
+/// Checks that a note number and the output channel fit MIDI data bytes.
+fn check_note(note: u8, options: &MidiOptions) -> Result<()> {
+    if note > 127 {
+        return Err(Error::InvalidParameter {
+            name: "note",
+            reason: "MIDI notes range from 0 to 127",
+        });
+    }
+    if options.channel > 15 {
+        return Err(Error::InvalidParameter {
+            name: "channel",
+            reason: "MIDI channels range from 0 to 15",
+        });
+    }
+    Ok(())
+}
+
+/// Rebuilds a pattern of `steps` steps from delta-timed messages, marking a
+/// pulse on every step where a note-on for `note` starts.
+///
+/// Note-ons are snapped to the nearest step using the tick resolution from
+/// `options`; other messages only advance time.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, euclidean, messages_to_pattern, pattern_to_messages};
+///
+/// let options = MidiOptions::default();
+/// let pattern = euclidean(16, 5, 0);
+/// let messages = pattern_to_messages(&pattern, 38, &options);
+/// assert_eq!(messages_to_pattern(&messages, 38, 16, &options), pattern);
+/// ```
+pub fn messages_to_pattern(
+    messages: &[TimedMessage],
+    note: u8,
+    steps: usize,
+    options: &MidiOptions,
+) -> Vec<bool> {
+    let ticks_per_step = u64::from(options.ticks_per_step());
+    let mut pattern = vec![false; steps];
+    if steps == 0 {
+        return pattern;
+    }
+    let mut tick = 0u64;
+    for message in messages {
+        tick += u64::from(message.delta);
+        let [status, data1, velocity] = message.bytes;
+        if status & 0xF0 == 0x90 && data1 == note && velocity > 0 {
+            let step = ((tick + ticks_per_step / 2) / ticks_per_step) as usize;
+            pattern[step % steps] = true;
+        }
+    }
+    pattern
+}
+
+/// Exports a melody, one optional pitch per step (as produced by `melody`
+/// with the `analysis` feature), as a MIDI file.
+///
+/// Each pitch is an offset in semitones from `root`; resulting notes are
+/// clamped to the MIDI range 0-127.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, melody_to_midi};
+///
+/// let line = [Some(0), None, Some(3), Some(7), None, Some(10), Some(0), None];
+/// let bytes = melody_to_midi(&line, 60, &MidiOptions::default());
+/// assert_eq!(&bytes[..4], b"MThd");
+/// ```
+pub fn melody_to_midi(melody: &[Option<i8>], root: u8, options: &MidiOptions) -> Vec<u8> {
+    let notes: Vec<StepNote> = melody
+        .iter()
+        .enumerate()
+        .filter_map(|(step, pitch)| {
+            pitch.map(|p| StepNote {
+                step,
+                note: (i16::from(root) + i16::from(p)).clamp(0, 127) as u8,
+                velocity: options.velocity,
+                ratchet: 1,
+                gate: None,
+            })
+        })
+        .collect();
+    write_smf(&notes, melody.len(), options)
+}
+
+/// Exports step events recorded from a [`Sequencer`](crate::Sequencer) as a
+/// MIDI file, with every parameter lock and trig condition already applied.
+///
+/// Each event is placed at its `tick`, with its own note and velocity;
+/// ratcheted steps are split into evenly spaced hits. `steps` sets the length
+/// of the file in sequencer ticks.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, ParamLock, Sequencer, SequencerTrack, events_to_midi};
+///
+/// let snare = SequencerTrack::new("snare", 8, 2, 4).with_lock(4, ParamLock::default().ratchet(2));
+/// let mut seq = Sequencer::new(120.0).with_track(snare);
+/// let events: Vec<_> = (0..16).flat_map(|_| seq.tick()).collect();
+/// let bytes = events_to_midi(&events, 16, &MidiOptions::default());
+/// assert_eq!(&bytes[..4], b"MThd");
+/// ```
+#[cfg(feature = "sequencer")]
+pub fn events_to_midi(events: &[StepEvent], steps: usize, options: &MidiOptions) -> Vec<u8> {
+    let notes: Vec<StepNote> = events
+        .iter()
+        .map(|event| StepNote {
+            step: event.tick as usize,
+            note: event.note.min(127),
+            velocity: event.velocity,
+            ratchet: event.ratchet,
+            gate: None,
+        })
+        .collect();
+    write_smf(&notes, steps, options)
+}
+
+fn pattern_notes(pattern: &[bool], note: u8, options: &MidiOptions) -> Vec<StepNote> {
+    pattern
+        .iter()
+        .enumerate()
+        .filter(|&(_, &pulse)| pulse)
+        .map(|(step, _)| StepNote {
+            step,
+            note: note.min(127),
+            velocity: options.velocity,
+            ratchet: 1,
+            gate: None,
+        })
+        .collect()
+}
+
+/// One note per positive gate length, each cut short at the next note and
+/// at the end of the pattern.
+fn gate_notes(gates: &[f32], note: u8, options: &MidiOptions) -> Vec<StepNote> {
+    let onsets: Vec<usize> = (0..gates.len()).filter(|&i| gates[i] > 0.0).collect();
+    onsets
+        .iter()
+        .enumerate()
+        .map(|(i, &step)| {
+            let next = onsets.get(i + 1).map_or(gates.len(), |&o| o);
+            StepNote {
+                step,
+                note: note.min(127),
+                velocity: options.velocity,
+                ratchet: 1,
+                gate: Some(gates[step].min((next - step) as f32)),
+            }
+        })
+        .collect()
+}
+
+/// Turns step notes into sorted, delta-timed note-on/note-off messages.
+fn note_messages(notes: &[StepNote], options: &MidiOptions) -> Vec<TimedMessage> {
+    let ticks_per_step = options.ticks_per_step();
+    let gate_ticks = ((ticks_per_step as f32 * options.gate).round() as u32).max(1);
+    let channel = options.channel & 0x0F;
+
+    // (tick, is_note_on, note, velocity); note-offs sort before note-ons.
+    let mut events: Vec<(u32, bool, u8, u8)> = Vec::with_capacity(notes.len() * 2);
+    for n in notes {
+        let hits = u32::from(n.ratchet.max(1));
+        let spacing = (ticks_per_step / hits).max(1);
+        let gate = if let Some(steps) = n.gate.filter(|_| hits == 1) {
+            ((ticks_per_step as f32 * steps).round() as u32).max(1)
+        } else if hits == 1 {
+            gate_ticks
+        } else {
+            ((spacing as f32 * options.gate).round() as u32).max(1)
+        };
+        for hit in 0..hits {
+            let start = n.step as u32 * ticks_per_step + hit * spacing;
+            events.push((start, true, n.note, n.velocity.clamp(1, 127)));
+            events.push((start + gate, false, n.note, 0));
+        }
+    }
+    events.sort_by_key(|&(tick, on, note, _)| (tick, on, note));
+
+    let mut last_tick = 0;
+    events
+        .into_iter()
+        .map(|(tick, on, note, velocity)| {
+            let delta = tick - last_tick;
+            last_tick = tick;
+            let status = if on { 0x90 } else { 0x80 };
+            TimedMessage {
+                delta,
+                bytes: [status | channel, note, velocity],
+            }
+        })
+        .collect()
+}
+
+/// Writes a format-0 SMF containing the given notes; `steps` sets the length.
+pub(crate) fn write_smf(notes: &[StepNote], steps: usize, options: &MidiOptions) -> Vec<u8> {
+    let mut track = Vec::new();
+    // Tempo meta event in microseconds per quarter note.
+    let tempo = (60_000_000.0 / options.bpm.max(1.0)).round() as u32;
+    track.extend_from_slice(&[0x00, 0xFF, 0x51, 0x03]);
+    track.extend_from_slice(&tempo.to_be_bytes()[1..]);
+
+    let mut last_tick = 0;
+    for message in note_messages(notes, options) {
+        write_vlq(&mut track, message.delta);
+        last_tick += message.delta;
+        track.extend_from_slice(&message.bytes);
+    }
+
+    // End of track at the end of the last step.
+    let end = (steps as u32 * options.ticks_per_step()).max(last_tick);
+    write_vlq(&mut track, end - last_tick);
+    track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+    let mut out = Vec::with_capacity(22 + track.len());
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&options.ppq.to_be_bytes());
+    out.extend_from_slice(b"MTrk");
+    out.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    out.extend_from_slice(&track);
+    out
+}
+
+/// Largest time-signature denominator exponent accepted: 2^7, a 128th note.
+const MAX_DENOMINATOR_EXPONENT: u8 = 7;
+
+/// Drum patterns read from a MIDI file by [`grid_from_smf`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedGrid {
+    /// One track per note number, named after it (e.g. `"36"`), lowest note
+    /// first. Every track spans the whole file, rounded up to whole bars.
+    pub bank: PatternBank,
+    /// Mean distance of the note-ons from their steps, in steps (0 to 0.5).
+    pub mean_error: f64,
+    /// Largest distance of a note-on from its step, in steps.
+    pub max_error: f64,
+    /// The microtiming of each track, in bank order: one `(step, offset)`
+    /// pair per note-on, in step order, with the exact position minus the
+    /// step in steps (negative is early). Keeps the feel of a live take
+    /// that quantizing removed.
+    pub offsets: Vec<Vec<(usize, f64)>>,
+}
+
+/// Reads the drum patterns of a Standard MIDI File, quantized to
+/// `steps_per_bar` steps per bar.
+///
+/// Parse errors are reported as [`io::ErrorKind::InvalidData`] wrapping the
+/// crate's [`Error`]; see [`grid_from_smf_bytes`].
+///
+/// # Errors
+/// Returns any error from reading the file or from [`grid_from_smf_bytes`].
+pub fn grid_from_smf(path: impl AsRef<Path>, steps_per_bar: usize) -> io::Result<ImportedGrid> {
+    let bytes = std::fs::read(path)?;
+    grid_from_smf_bytes(&bytes, steps_per_bar)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Reads the drum patterns of a Standard MIDI File held in memory, with one
+/// track per note number, quantized to `steps_per_bar` steps per bar.
+///
+/// Note-ons from every track and channel are collected and snapped to the
+/// nearest step; the bar length comes from the first time signature (4/4 if
+/// there is none). [`ImportedGrid`] reports how far the notes had to move,
+/// which tells a tight programmed part from a loose live take. This is the
+/// counterpart of [`pattern_to_midi`], and the result can go straight into
+/// the fitting tools.
+///
+/// # Errors
+/// Returns [`Error::ZeroSteps`] if `steps_per_bar == 0`,
+/// [`Error::TooManySteps`] if the tracks would be longer than
+/// [`MAX_INPUT_STEPS`], and [`Error::InvalidParameter`] named `"smf"` if the
+/// data is not a well-formed SMF with a metrical (ticks per quarter note)
+/// division and a time signature no finer than 128th notes.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{MidiOptions, euclidean, grid_from_smf_bytes, pattern_to_midi};
+///
+/// let bytes = pattern_to_midi(&euclidean(16, 5, 0), 36, &MidiOptions::default());
+/// let grid = grid_from_smf_bytes(&bytes, 16)?;
+/// assert_eq!(grid.bank.get("36").unwrap().pattern, euclidean(16, 5, 0));
+/// assert_eq!(grid.max_error, 0.0);
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+pub fn grid_from_smf_bytes(bytes: &[u8], steps_per_bar: usize) -> Result<ImportedGrid> {
+    if steps_per_bar == 0 {
+        return Err(Error::ZeroSteps);
+    }
+    let smf = parse_smf(bytes)?;
+    let (numerator, denominator) = smf.time_signature.unwrap_or((4, 4));
+    let bar_ticks = f64::from(smf.division) * 4.0 * f64::from(numerator) / f64::from(denominator);
+    let step_ticks = bar_ticks / steps_per_bar as f64;
+    let end = smf.notes.iter().map(|&(tick, _)| tick).max().unwrap_or(0);
+    let bars = ((end as f64 + 1.0) / bar_ticks).ceil().max(1.0);
+    let steps = if bars > MAX_INPUT_STEPS as f64 {
+        usize::MAX
+    } else {
+        (bars as usize).saturating_mul(steps_per_bar)
+    };
+    check_input_steps(steps)?;
+
+    let mut tracks: Vec<(u8, Vec<bool>)> = Vec::new();
+    let mut offsets: Vec<Vec<(usize, f64)>> = Vec::new();
+    let (mut total_error, mut max_error) = (0.0, 0.0f64);
+    for &(tick, note) in &smf.notes {
+        let position = tick as f64 / step_ticks;
+        let step = position.round();
+        let offset = position - step;
+        let error = offset.abs();
+        total_error += error;
+        max_error = max_error.max(error);
+        let index = match tracks.binary_search_by_key(&note, |&(n, _)| n) {
+            Ok(index) => index,
+            Err(index) => {
+                tracks.insert(index, (note, vec![false; steps]));
+                offsets.insert(index, Vec::new());
+                index
+            }
+        };
+        let step = step as usize % steps;
+        tracks[index].1[step] = true;
+        offsets[index].push((step, offset));
+    }
+
+    let mut bank = PatternBank::new();
+    for (note, pattern) in tracks {
+        bank.push(note.to_string(), pattern);
+    }
+    for timing in &mut offsets {
+        timing.sort_by_key(|&(step, _)| step);
+    }
+    Ok(ImportedGrid {
+        bank,
+        mean_error: total_error / smf.notes.len().max(1) as f64,
+        max_error,
+        offsets,
+    })
+}
+
+/// The parts of a MIDI file that [`grid_from_smf_bytes`] needs.
+struct ParsedSmf {
+    /// Ticks per quarter note.
+    division: u16,
+    /// Numerator and denominator of the first time signature.
+    time_signature: Option<(u8, u32)>,
+    /// `(tick, note)` for every note-on with a non-zero velocity.
+    notes: Vec<(u64, u8)>,
+}
+
+fn malformed(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "smf",
+        reason,
+    }
+}
+
+fn parse_smf(bytes: &[u8]) -> Result<ParsedSmf> {
+    let mut reader = SmfReader { bytes, pos: 0 };
+    let (id, header) = reader.chunk()?;
+    if id != *b"MThd" || header.len() < 6 {
+        return Err(malformed("missing MThd header"));
+    }
+    let division = u16::from_be_bytes([header[4], header[5]]);
+    if division == 0 || division & 0x8000 != 0 {
+        return Err(malformed(
+            "only ticks-per-quarter-note divisions are supported",
+        ));
+    }
+
+    let mut smf = ParsedSmf {
+        division,
+        time_signature: None,
+        notes: Vec::new(),
+    };
+    while reader.pos < bytes.len() {
+        let (id, data) = reader.chunk()?;
+        if id == *b"MTrk" {
+            parse_track(data, &mut smf)?;
+        }
+    }
+    smf.notes.sort_unstable();
+    Ok(smf)
+}
+
+fn parse_track(data: &[u8], smf: &mut ParsedSmf) -> Result<()> {
+    let mut reader = SmfReader {
+        bytes: data,
+        pos: 0,
+    };
+    let mut tick = 0u64;
+    let mut running_status = None;
+    while reader.pos < data.len() {
+        tick += u64::from(reader.vlq()?);
+        let mut status = reader.byte()?;
+        match status {
+            0xFF => {
+                let kind = reader.byte()?;
+                let len = reader.vlq()? as usize;
+                let body = reader.take(len)?;
+                if kind == 0x58 && len >= 2 && smf.time_signature.is_none() {
+                    if body[1] > MAX_DENOMINATOR_EXPONENT {
+                        return Err(malformed("time signature denominator out of range"));
+                    }
+                    smf.time_signature = Some((body[0].max(1), 1u32 << body[1]));
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = reader.vlq()? as usize;
+                reader.take(len)?;
+            }
+            _ => {
+                if status < 0x80 {
+                    // Running status: this byte is the first data byte.
+                    status = running_status.ok_or(malformed("data byte without a status"))?;
+                    reader.pos -= 1;
+                }
+                running_status = Some(status);
+                let data_len = if matches!(status & 0xF0, 0xC0 | 0xD0) {
+                    1
+                } else {
+                    2
+                };
+                let body = reader.take(data_len)?;
+                if status & 0xF0 == 0x90 && body[1] > 0 {
+                    smf.notes.push((tick, body[0]));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A cursor over SMF bytes that reports truncation as an error.
+struct SmfReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SmfReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(malformed("truncated data"))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Reads a variable-length quantity of at most four bytes.
+    fn vlq(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | u32::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed("variable-length quantity too long"))
+    }
+
+    /// Reads a chunk's four-byte id and its data.
+    fn chunk(&mut self) -> Result<([u8; 4], &'a [u8])> {
+        let header = self.take(8)?;
+        let id = [header[0], header[1], header[2], header[3]];
+        let len = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        Ok((id, self.take(len as usize)?))
+    }
+}
+
+/// Appends a MIDI variable-length quantity.
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = [0u8; 5];
+    let mut i = bytes.len() - 1;
+    bytes[i] = (value & 0x7F) as u8;
+    value >>= 7;
+    while value > 0 {
+        i -= 1;
+        bytes[i] = (value & 0x7F) as u8 | 0x80;
+        value >>= 7;
+    }
+    out.extend_from_slice(&bytes[i..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn vlq_encoding() {
+        let encode = |v| {
+            let mut out = Vec::new();
+            write_vlq(&mut out, v);
+            out
+        };
+        assert_eq!(encode(0), vec![0x00]);
+        assert_eq!(encode(0x7F), vec![0x7F]);
+        assert_eq!(encode(0x80), vec![0x81, 0x00]);
+        assert_eq!(encode(0x0FFF_FFFF), vec![0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn pattern_file_layout() {
+        let options = MidiOptions::default();
+        let bytes = pattern_to_midi(&euclidean(8, 3, 0), 36, &options);
+
+        assert_eq!(&bytes[..4], b"MThd");
+        assert_eq!(&bytes[12..14], &96u16.to_be_bytes());
+        assert_eq!(&bytes[14..18], b"MTrk");
+        let track_len = u32::from_be_bytes(bytes[18..22].try_into().unwrap()) as usize;
+        assert_eq!(bytes.len(), 22 + track_len);
+        assert_eq!(&bytes[bytes.len() - 3..], &[0xFF, 0x2F, 0x00]);
+
+        let note_ons = bytes
+            .windows(3)
+            .filter(|w| w[0] == 0x90 && w[1] == 36)
+            .count();
+        assert_eq!(note_ons, 3);
+        // 120 BPM = 500000 microseconds per quarter note.
+        assert_eq!(&bytes[26..29], &[0x07, 0xA1, 0x20]);
+    }
+
+    #[test]
+    fn ratchets_split_the_step() {
+        let note = StepNote {
+            step: 1,
+            note: 38,
+            velocity: 90,
+            ratchet: 3,
+            gate: None,
+        };
+        let messages = note_messages(&[note], &MidiOptions::default());
+        // 24 ticks per step: hits at 24, 32 and 40, each gated for 4 ticks.
+        let deltas: Vec<u32> = messages.iter().map(|m| m.delta).collect();
+        assert_eq!(deltas, vec![24, 4, 4, 4, 4, 4]);
+        assert_eq!(messages.iter().filter(|m| m.bytes[0] == 0x90).count(), 3);
+    }
+
+    #[test]
+    fn imports_drum_notes_per_track() {
+        // 96 PPQ, 3/4, running status, a note-on with velocity 0 as a
+        // note-off, and a snare 10 ticks late (a 24-tick step: 5/12 off).
+        #[rustfmt::skip]
+        let track = [
+            0x00, 0xFF, 0x58, 0x04, 0x03, 0x02, 0x18, 0x08,
+            0x00, 0x99, 36, 100,
+            0x30, 36, 0,
+            0x30, 42, 90,
+            0x22, 38, 90,
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let mut bytes = b"MThd\0\0\0\x06\0\0\0\x01\0\x60MTrk".to_vec();
+        bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&track);
+
+        let grid = grid_from_smf_bytes(&bytes, 12).unwrap();
+        let names: Vec<&str> = grid.bank.tracks().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["36", "38", "42"]);
+        assert_eq!(grid.bank.steps(), 12);
+        assert!(grid.bank.get("36").unwrap().pattern[0]);
+        assert!(grid.bank.get("42").unwrap().pattern[4]);
+        assert!(grid.bank.get("38").unwrap().pattern[5]);
+        assert!((grid.max_error - 5.0 / 12.0).abs() < 1e-9);
+        // The snare keeps its lateness as a groove offset.
+        let snare = &grid.offsets[1];
+        assert_eq!(snare.len(), 1);
+        assert_eq!(snare[0].0, 5);
+        assert!((snare[0].1 - 5.0 / 12.0).abs() < 1e-9);
+        assert_eq!(grid.offsets[0], [(0, 0.0)]);
+
+        assert_eq!(grid_from_smf_bytes(&bytes, 0), Err(Error::ZeroSteps));
+        assert!(grid_from_smf_bytes(&bytes[..30], 16).is_err());
+        assert!(grid_from_smf_bytes(b"RIFF", 16).is_err());
+
+        // Absurd denominators and grids are refused instead of allocated.
+        let mut fine = bytes.clone();
+        fine[27] = 31;
+        assert!(matches!(
+            grid_from_smf_bytes(&fine, 16),
+            Err(Error::InvalidParameter { name: "smf", .. })
+        ));
+        for steps_per_bar in [MAX_INPUT_STEPS + 1, usize::MAX] {
+            assert!(matches!(
+                grid_from_smf_bytes(&bytes, steps_per_bar),
+                Err(Error::TooManySteps { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn gates_tie_and_are_cut_at_the_next_note() {
+        let options = MidiOptions::default();
+        // Held 2.5 steps, then 3 steps but cut at the next note, then tied
+        // past the end of the pattern.
+        let gates = [2.5, 0.0, 0.0, 3.0, 0.0, 4.0];
+        let messages = note_messages(&gate_notes(&gates, 40, &options), &options);
+        let timeline: Vec<(u32, u8)> = messages
+            .iter()
+            .scan(0, |tick, m| {
+                *tick += m.delta;
+                Some((*tick, m.bytes[0]))
+            })
+            .collect();
+        assert_eq!(
+            timeline,
+            [
+                (0, 0x90),
+                (60, 0x80),
+                (72, 0x90),
+                (120, 0x80),
+                (120, 0x90),
+                (144, 0x80)
+            ]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Binary necklaces: every rhythm of a given density, up to rotation.
+//!
+//! Two patterns that differ only by rotation are the same necklace, so the
+//! necklaces with `pulses` onsets over `steps` steps are the complete set of
+//! distinct rhythms of that density. Exactly one of them is the Euclidean
+//! rhythm E(`pulses`, `steps`).
+//!
+//! [`canonical_rotation`] finds the necklace of any one pattern, so patterns
+//! can be compared up to rotation. [`lyndon_factorization`] looks inside a
+//! single pattern instead, splitting it into Lyndon words (patterns that sort
+//! strictly before all their own rotations) to expose its repetition
+//! structure.
+
+use crate::try_euclidean;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// One necklace, as yielded by [`iter_necklaces`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Necklace {
+    /// The representative rotation: the one that sorts first when pulses
+    /// come before rests, so it always starts on its longest run of pulses.
+    pub pattern: Vec<bool>,
+    /// Whether this necklace is a rotation of E(`pulses`, `steps`).
+    pub is_euclidean: bool,
+}
+
+/// Counts the binary necklaces with `pulses` onsets over `steps` steps.
+///
+/// Returns `None` if the count does not fit in a `u128`, and `Some(0)` if
+/// `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::count_necklaces;
+///
+/// // 56 ways to place 3 onsets in 8 steps, but only 7 distinct rhythms.
+/// assert_eq!(count_necklaces(8, 3), Some(7));
+/// ```
+pub fn count_necklaces(steps: u8, pulses: u8) -> Option<u128> {
+    if steps == 0 || pulses > steps {
+        return Some(0);
+    }
+    let (n, k) = (u128::from(steps), u128::from(pulses));
+    // Burnside's lemma: (1/n) * sum over d | gcd(n, k) of phi(d) * C(n/d, k/d).
+    let g = gcd(n, k);
+    let mut total: u128 = 0;
+    for d in (1..=g).filter(|&d| g.is_multiple_of(d)) {
+        let term = binomial(n / d, k / d)?.checked_mul(totient(d))?;
+        total = total.checked_add(term)?;
+    }
+    Some(total / n)
+}
+
+/// Iterates over the binary necklaces with `pulses` onsets over `steps`
+/// steps, in lexicographic order of their representatives with pulses
+/// sorting first.
+///
+/// Uses the Fredricksen–Kessler–Maiorana algorithm over all binary
+/// necklaces of length `steps` and keeps those of the requested density,
+/// so the cost grows like `2^steps / steps`; it suits the few dozen steps
+/// found in music. Yields nothing if `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, iter_necklaces, rotate_pattern};
+///
+/// let all: Vec<_> = iter_necklaces(8, 3).collect();
+/// assert_eq!(all.len(), 7);
+///
+/// let tresillo = all.iter().find(|n| n.is_euclidean).unwrap();
+/// assert_eq!(tresillo.pattern, rotate_pattern(&euclidean(8, 3, 0), 6));
+/// ```
+pub fn iter_necklaces(steps: u8, pulses: u8) -> Necklaces {
+    let euclidean = try_euclidean(steps, pulses, 0).unwrap_or_default();
+    Necklaces {
+        // 1-based digits, 0 = pulse and 1 = rest, so pulses sort first.
+        digits: vec![0; usize::from(steps) + 1],
+        pulses: usize::from(pulses),
+        euclidean: representative(&euclidean),
+        started: steps == 0 || pulses > steps,
+    }
+}
+
+/// Iterator returned by [`iter_necklaces`].
+#[derive(Debug, Clone)]
+pub struct Necklaces {
+    digits: Vec<u8>,
+    pulses: usize,
+    euclidean: Vec<bool>,
+    started: bool,
+}
+
+impl Necklaces {
+    /// Advances to the next prenecklace and returns whether it is a
+    /// necklace, or `None` once the enumeration is exhausted.
+    fn advance(&mut self) -> Option<bool> {
+        let n = self.digits.len() - 1;
+        if !self.started {
+            self.started = true;
+            return (n > 0).then_some(true);
+        }
+        let i = (1..=n).rev().find(|&i| self.digits[i] == 0)?;
+        self.digits[i] = 1;
+        for j in i + 1..=n {
+            self.digits[j] = self.digits[j - i];
+        }
+        Some(n.is_multiple_of(i))
+    }
+}
+
+impl Iterator for Necklaces {
+    type Item = Necklace;
+
+    fn next(&mut self) -> Option<Necklace> {
+        loop {
+            if !self.advance()? {
+                continue;
+            }
+            let pattern: Vec<bool> = self.digits[1..].iter().map(|&d| d == 0).collect();
+            if pattern.iter().filter(|&&p| p).count() == self.pulses {
+                let is_euclidean = pattern == self.euclidean;
+                return Some(Necklace {
+                    pattern,
+                    is_euclidean,
+                });
+            }
+        }
+    }
+}
+
+/// The result of [`lyndon_factorization`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LyndonFactorization {
+    /// Lyndon words that concatenate back to the pattern, in non-increasing
+    /// order.
+    pub factors: Vec<Vec<bool>>,
+    /// The shortest pattern that repeats to form the whole pattern.
+    pub primitive_root: Vec<bool>,
+    /// How many times `primitive_root` repeats; 1 for a primitive pattern.
+    pub repetitions: usize,
+}
+
+/// Splits `pattern` into its Lyndon factorization using Duval's algorithm,
+/// with pulses sorting before rests as in [`iter_necklaces`].
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, lyndon_factorization};
+///
+/// // x..x..x. = (x..)(x..)(x.)
+/// let tresillo = lyndon_factorization(&euclidean(8, 3, 0));
+/// assert_eq!(tresillo.factors.len(), 3);
+/// assert_eq!(tresillo.repetitions, 1);
+///
+/// // E(4,12) is the dotted-quarter pulse x.. repeated four times.
+/// let pulse = lyndon_factorization(&euclidean(12, 4, 0));
+/// assert_eq!(pulse.primitive_root, [true, false, false]);
+/// assert_eq!(pulse.repetitions, 4);
+/// ```
+pub fn lyndon_factorization(pattern: &[bool]) -> LyndonFactorization {
+    let n = pattern.len();
+    // Compare as digits with 0 = pulse, so pulses sort first.
+    let digit = |i: usize| u8::from(!pattern[i]);
+    let mut factors = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let (mut j, mut k) = (i + 1, i);
+        while j < n && digit(k) <= digit(j) {
+            k = if digit(k) < digit(j) { i } else { k + 1 };
+            j += 1;
+        }
+        while i <= k {
+            factors.push(pattern[i..i + j - k].to_vec());
+            i += j - k;
+        }
+    }
+    let period = (1..=n)
+        .find(|&p| n.is_multiple_of(p) && pattern.iter().zip(&pattern[p..]).all(|(a, b)| a == b))
+        .unwrap_or(0);
+    LyndonFactorization {
+        factors,
+        primitive_root: pattern[..period].to_vec(),
+        repetitions: n.checked_div(period).unwrap_or(0),
+    }
+}
+
+/// Returns the canonical rotation of `pattern` and the amount it was
+/// rotated by (left, as for [`rotate_pattern`](crate::rotate_pattern)).
+///
+/// The canonical rotation is the lexicographically smallest one, with
+/// pulses sorting before rests as in [`iter_necklaces`], so it starts on the
+/// pattern's longest run of pulses and is the representative of its
+/// necklace. Two patterns are rotations of each other exactly when their
+/// canonical rotations are equal, which makes it a key for comparing and
+/// deduplicating patterns up to rotation. Ties between equal rotations of a
+/// repeating pattern go to the smallest amount.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{canonical_rotation, euclidean, rotate_pattern};
+///
+/// let (canonical, rotation) = canonical_rotation(&euclidean(8, 3, 0));
+/// assert_eq!(canonical, [true, false, true, false, false, true, false, false]);
+/// assert_eq!(rotate_pattern(&euclidean(8, 3, 0), rotation as i32), canonical);
+/// assert_eq!(canonical_rotation(&euclidean(8, 3, 5)).0, canonical);
+/// ```
+pub fn canonical_rotation(pattern: &[bool]) -> (Vec<bool>, usize) {
+    let key = |r: usize| pattern[r..].iter().chain(&pattern[..r]).map(|&p| !p);
+    let best = (0..pattern.len())
+        .min_by(|&a, &b| key(a).cmp(key(b)))
+        .unwrap_or(0);
+    let canonical = pattern[best..]
+        .iter()
+        .chain(&pattern[..best])
+        .copied()
+        .collect();
+    (canonical, best)
+}
+
+/// The rotation of `pattern` that sorts first with pulses before rests.
+fn representative(pattern: &[bool]) -> Vec<bool> {
+    canonical_rotation(pattern).0
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn totient(n: u128) -> u128 {
+    (1..=n).filter(|&i| gcd(i, n) == 1).count() as u128
+}
+
+fn binomial(n: u128, k: u128) -> Option<u128> {
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        // C(n, i + 1) = C(n, i) * (n - i) / (i + 1) is always exact.
+        result = result.checked_mul(n - i)? / (i + 1);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_match_enumeration() {
+        for steps in 1..=12 {
+            for pulses in 0..=steps {
+                let necklaces: Vec<Necklace> = iter_necklaces(steps, pulses).collect();
+                assert_eq!(
+                    Some(necklaces.len() as u128),
+                    count_necklaces(steps, pulses)
+                );
+                assert_eq!(necklaces.iter().filter(|n| n.is_euclidean).count(), 1);
+                assert!(necklaces.windows(2).all(|w| {
+                    w[0].pattern
+                        .iter()
+                        .map(|&p| !p)
+                        .lt(w[1].pattern.iter().map(|&p| !p))
+                }));
+            }
+        }
+        assert_eq!(count_necklaces(16, 4), Some(116));
+    }
+
+    #[test]
+    fn lyndon_factors_rebuild_the_pattern() {
+        for pulses in 0..=16 {
+            let pattern = crate::euclidean(16, pulses, 5);
+            let lyndon = lyndon_factorization(&pattern);
+            assert_eq!(lyndon.factors.concat(), pattern);
+            assert!(lyndon.factors.iter().all(|f| representative(f) == *f));
+            assert!(
+                lyndon
+                    .factors
+                    .windows(2)
+                    .all(|w| { w[0].iter().map(|&p| !p).ge(w[1].iter().map(|&p| !p)) })
+            );
+            assert_eq!(lyndon.primitive_root.repeat(lyndon.repetitions), pattern);
+            let (canonical, rotation) = canonical_rotation(&pattern);
+            assert_eq!(crate::rotate_pattern(&pattern, rotation as i32), canonical);
+            assert_eq!(canonical, representative(&crate::euclidean(16, pulses, 0)));
+        }
+        let empty = lyndon_factorization(&[]);
+        assert_eq!(canonical_rotation(&[]), (Vec::new(), 0));
+        assert!(empty.factors.is_empty() && empty.repetitions == 0);
+    }
+
+    #[test]
+    fn degenerate_and_huge_inputs() {
+        assert_eq!(count_necklaces(0, 0), Some(0));
+        assert_eq!(count_necklaces(4, 5), Some(0));
+        assert_eq!(iter_necklaces(0, 0).next(), None);
+        assert_eq!(iter_necklaces(4, 5).next(), None);
+        assert_eq!(count_necklaces(255, 127), None);
+        assert_eq!(count_necklaces(255, 1), Some(1));
+    }
+}
@@ -0,0 +1,192 @@
+//! Onset detection for recorded rhythms.
+//!
+//! A deliberately simple energy-based detector: it is meant for isolated,
+//! percussive recordings such as a drum loop or hand claps, not for mixed
+//! music. Together with [`onsets_to_pattern`] and
+//! [`fit_euclidean`](crate::fit_euclidean) it turns a recording into
+//! E(k,n,r) parameters:
+//!
+//! ```
+//! use euclidean_rhythm::{detect_onsets, euclidean, fit_euclidean, onsets_to_pattern};
+//!
+//! // Two seconds of tresillo clicks at 8 steps per loop.
+//! let sample_rate = 8000;
+//! let mut samples = vec![0.0f32; 2 * sample_rate as usize];
+//! for (step, &pulse) in euclidean(8, 3, 0).iter().enumerate() {
+//!     if !pulse {
+//!         continue;
+//!     }
+//!     let start = step * samples.len() / 8;
+//!     for i in 0..400 {
+//!         let t = i as f32 / sample_rate as f32;
+//!         samples[start + i] = (t * 2000.0).sin() * (-t * 60.0).exp();
+//!     }
+//! }
+//!
+//! let onsets = detect_onsets(&samples, sample_rate);
+//! let pattern = onsets_to_pattern(&onsets, 2.0, 8);
+//! let fit = fit_euclidean(&pattern).unwrap();
+//! assert_eq!((fit.steps, fit.pulses, fit.rotation), (8, 3, 0));
+//! ```
+
+/// Analysis hop: one energy value per 10 ms.
+const HOP_SECONDS: f64 = 0.01;
+/// Minimum rise in log energy between consecutive frames (about 4 dB).
+const RISE_THRESHOLD: f64 = 1.0;
+/// Frames quieter than this mean-square level (-50 dBFS) never start an onset.
+const ENERGY_FLOOR: f64 = 1e-5;
+/// Onsets closer together than this are merged into the first one.
+const MIN_GAP_SECONDS: f64 = 0.05;
+
+/// Detects note onsets in mono `samples` recorded at `sample_rate` Hz and
+/// returns their times in seconds, in ascending order.
+///
+/// The signal is cut into 10 ms frames; an onset is a frame whose energy
+/// jumps by more than about 4 dB over the previous frame, is a local peak of
+/// that jump, and lies at least 50 ms after the previous onset. Times are
+/// accurate to one frame.
+pub fn detect_onsets(samples: &[f32], sample_rate: u32) -> Vec<f64> {
+    let hop = ((f64::from(sample_rate) * HOP_SECONDS) as usize).max(1);
+    let log_energy: Vec<(f64, f64)> = samples
+        .chunks(hop)
+        .map(|frame| {
+            let energy =
+                frame.iter().map(|&s| f64::from(s).powi(2)).sum::<f64>() / frame.len() as f64;
+            (energy, (energy + ENERGY_FLOOR * 0.1).ln())
+        })
+        .collect();
+    let rise = |i: usize| match i {
+        0 => log_energy[0].1 - (ENERGY_FLOOR * 0.1).ln(),
+        _ => log_energy[i].1 - log_energy[i - 1].1,
+    };
+
+    let mut onsets: Vec<f64> = Vec::new();
+    for i in 0..log_energy.len() {
+        let r = rise(i);
+        let is_peak =
+            (i == 0 || r >= rise(i - 1)) && (i + 1 == log_energy.len() || r > rise(i + 1));
+        if log_energy[i].0 < ENERGY_FLOOR || r < RISE_THRESHOLD || !is_peak {
+            continue;
+        }
+        let time = (i * hop) as f64 / f64::from(sample_rate);
+        if onsets
+            .last()
+            .is_none_or(|&last| time - last >= MIN_GAP_SECONDS)
+        {
+            onsets.push(time);
+        }
+    }
+    onsets
+}
+
+/// Quantizes onset times to a loop of `steps` steps lasting `loop_seconds`.
+///
+/// Each onset lands on the nearest step; times past the end of the loop wrap
+/// around, so several recorded loops fold onto one pattern. Negative times
+/// are ignored. Returns an empty pattern if `steps == 0` or `loop_seconds` is
+/// not positive. See [`onsets_to_pattern_offsets`] to keep the timing.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::onsets_to_pattern;
+///
+/// let pattern = onsets_to_pattern(&[0.01, 0.74, 1.49, 2.0], 2.0, 8);
+/// assert_eq!(pattern, [true, false, false, true, false, false, true, false]);
+/// ```
+pub fn onsets_to_pattern(onsets: &[f64], loop_seconds: f64, steps: usize) -> Vec<bool> {
+    if steps == 0 || loop_seconds.partial_cmp(&0.0) != Some(core::cmp::Ordering::Greater) {
+        return Vec::new();
+    }
+    let mut pattern = vec![false; steps];
+    for (step, _) in onsets_to_pattern_offsets(onsets, loop_seconds, steps) {
+        pattern[step] = true;
+    }
+    pattern
+}
+
+/// Quantizes onset times like [`onsets_to_pattern`], but keeps how far each
+/// onset was from its step.
+///
+/// Returns one `(step, offset)` pair per onset, in step order: `step` is the
+/// step the onset lands on and `offset` is the exact position minus the
+/// step, in steps, within `-0.5..=0.5` (negative is early). Onsets folded
+/// onto the same step keep a pair each. Together with the step duration
+/// the pairs restore the original times, so a performance can be stored as
+/// a pattern plus its groove.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::onsets_to_pattern_offsets;
+///
+/// let timed = onsets_to_pattern_offsets(&[0.01, 0.74, 1.49], 2.0, 8);
+/// let steps: Vec<usize> = timed.iter().map(|&(step, _)| step).collect();
+/// assert_eq!(steps, [0, 3, 6]);
+/// // 0.74 s is step 2.96: four hundredths of a step early.
+/// assert!((timed[1].1 + 0.04).abs() < 1e-9);
+/// ```
+pub fn onsets_to_pattern_offsets(
+    onsets: &[f64],
+    loop_seconds: f64,
+    steps: usize,
+) -> Vec<(usize, f64)> {
+    if steps == 0 || loop_seconds.partial_cmp(&0.0) != Some(core::cmp::Ordering::Greater) {
+        return Vec::new();
+    }
+    let mut timed: Vec<(usize, f64)> = onsets
+        .iter()
+        .filter(|&&t| t >= 0.0)
+        .map(|&time| {
+            let position = time / loop_seconds * steps as f64;
+            let step = position.round();
+            (step as usize % steps, position - step)
+        })
+        .collect();
+    timed.sort_by_key(|&(step, _)| step);
+    timed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn click(samples: &mut [f32], start: usize, amplitude: f32) {
+        for (i, sample) in samples[start..].iter_mut().take(2000).enumerate() {
+            let t = i as f32 / 44_100.0;
+            *sample += amplitude * (t * 3000.0).sin() * (-t * 80.0).exp();
+        }
+    }
+
+    #[test]
+    fn finds_clicks_and_ignores_silence() {
+        let mut samples = vec![0.0f32; 44_100];
+        assert!(detect_onsets(&samples, 44_100).is_empty());
+
+        click(&mut samples, 4410, 0.8);
+        click(&mut samples, 22_050, 0.3);
+        // Too close to the previous click to count separately.
+        click(&mut samples, 22_050 + 441, 0.3);
+        let onsets = detect_onsets(&samples, 44_100);
+        assert_eq!(onsets.len(), 2);
+        assert!((onsets[0] - 0.1).abs() <= 0.01);
+        assert!((onsets[1] - 0.5).abs() <= 0.01);
+    }
+
+    #[test]
+    fn quantizing_wraps_and_rejects_bad_grids() {
+        assert_eq!(
+            onsets_to_pattern(&[0.99, -0.5], 1.0, 4),
+            [true, false, false, false]
+        );
+        assert!(onsets_to_pattern(&[0.5], 0.0, 4).is_empty());
+        assert!(onsets_to_pattern(&[0.5], f64::NAN, 4).is_empty());
+        assert!(onsets_to_pattern(&[0.5], 1.0, 0).is_empty());
+
+        // A late hit near the loop end wraps to step 0 but stays early.
+        let timed = onsets_to_pattern_offsets(&[0.6, 0.95], 1.0, 4);
+        assert_eq!(timed.len(), 2);
+        assert_eq!(timed[0].0, 0);
+        assert!((timed[0].1 + 0.2).abs() < 1e-9);
+        assert_eq!(timed[1].0, 2);
+        assert!((timed[1].1 - 0.4).abs() < 1e-9);
+    }
+}
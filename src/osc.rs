@@ -0,0 +1,185 @@
+//! OSC trigger output (enabled with the `osc` feature).
+//!
+//! [`OscSink`] is a [`StepListener`] that sends one UDP Open Sound Control
+//! message per step event, for SuperCollider, Max, TouchDesigner and friends.
+//! Messages are encoded by hand, so the feature adds no dependencies.
+
+use crate::sequencer::{StepEvent, StepListener};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Sends `/euclid/track/<N>/trigger <velocity> <step>` for every step event.
+///
+/// Both arguments are OSC `int32`s. The `/euclid` prefix is configurable.
+///
+/// # Examples
+/// ```no_run
+/// use euclidean_rhythm::osc::OscSink;
+/// use euclidean_rhythm::{Sequencer, SequencerTrack};
+///
+/// let mut sink = OscSink::new("127.0.0.1:57120")?;
+/// let mut seq = Sequencer::new(120.0).with_track(SequencerTrack::new("kick", 16, 4, 0));
+/// seq.advance_with(1.0, &mut sink);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct OscSink {
+    socket: UdpSocket,
+    prefix: String,
+    errors: u64,
+}
+
+impl OscSink {
+    /// Binds an ephemeral local UDP port and targets `address`.
+    ///
+    /// The port is bound for the IP version of the target, IPv4 or IPv6. If
+    /// `address` resolves to several targets, the first one that can be
+    /// reached is used.
+    pub fn new(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let mut last_error = None;
+        for target in address.to_socket_addrs()? {
+            match connect(target) {
+                Ok(socket) => {
+                    return Ok(Self {
+                        socket,
+                        prefix: "/euclid".to_string(),
+                        errors: 0,
+                    });
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing")
+        }))
+    }
+
+    /// Replaces the `/euclid` address prefix.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sends the message for one event.
+    pub fn send(&self, event: &StepEvent) -> io::Result<()> {
+        let address = format!("{}/track/{}/trigger", self.prefix, event.track);
+        let packet = encode_message(&address, &[i32::from(event.velocity), event.step as i32]);
+        self.socket.send(&packet).map(|_| ())
+    }
+
+    /// Number of sends that failed while used as a [`StepListener`].
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+}
+
+impl StepListener for OscSink {
+    fn on_step(&mut self, event: &StepEvent) {
+        // UDP is fire-and-forget; a dropped trigger must not stop playback.
+        if self.send(event).is_err() {
+            self.errors += 1;
+        }
+    }
+}
+
+/// A UDP socket on an ephemeral port of `target`'s IP version, connected to it.
+fn connect(target: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = match target {
+        SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+        SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?,
+    };
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+/// Encodes an OSC message with `int32` arguments.
+pub(crate) fn encode_message(address: &str, args: &[i32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_padded_str(&mut out, address);
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|_| 'i'))
+        .collect();
+    push_padded_str(&mut out, &tags);
+    for arg in args {
+        out.extend_from_slice(&arg.to_be_bytes());
+    }
+    out
+}
+
+/// Writes a null-terminated string padded to a multiple of four bytes.
+fn push_padded_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    let padding = 4 - s.len() % 4;
+    out.extend(std::iter::repeat_n(0, padding));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_osc_message() {
+        let packet = encode_message("/a", &[1, -1]);
+        assert_eq!(
+            packet,
+            vec![
+                b'/', b'a', 0, 0, b',', b'i', b'i', 0, 0, 0, 0, 1, 0xFF, 0xFF, 0xFF, 0xFF
+            ]
+        );
+        // Exactly four-aligned strings still get a terminating null word.
+        assert_eq!(encode_message("/abc", &[]).len(), 12);
+    }
+
+    #[test]
+    fn sends_trigger_over_udp() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut sink = OscSink::new(receiver.local_addr().unwrap()).unwrap();
+        sink.on_step(&StepEvent {
+            track: 2,
+            step: 5,
+            cycle: 0,
+            tick: 5,
+            note: 36,
+            velocity: 100,
+            ratchet: 1,
+            fill: false,
+        });
+
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            encode_message("/euclid/track/2/trigger", &[100, 5])
+        );
+        assert_eq!(sink.errors(), 0);
+    }
+
+    #[test]
+    fn sends_to_ipv6_targets() {
+        // Hosts without IPv6 have nothing to test.
+        let Ok(receiver) = UdpSocket::bind("[::1]:0") else {
+            return;
+        };
+        let sink = OscSink::new(receiver.local_addr().unwrap()).unwrap();
+        assert!(sink.socket.local_addr().unwrap().is_ipv6());
+        sink.send(&StepEvent {
+            track: 0,
+            step: 1,
+            cycle: 0,
+            tick: 1,
+            note: 36,
+            velocity: 90,
+            ratchet: 1,
+            fill: false,
+        })
+        .unwrap();
+
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            encode_message("/euclid/track/0/trigger", &[90, 1])
+        );
+    }
+}
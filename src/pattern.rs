@@ -0,0 +1,531 @@
+//! The [`Pattern`] type.
+//!
+//! A thin owned wrapper around step data. It dereferences to `[bool]`, so all
+//! slice methods and the crate's free functions work on it directly.
+//!
+//! Transformations consume and return a `Pattern`, so they chain:
+//!
+//! ```
+//! use euclidean_rhythm::Pattern;
+//!
+//! let hats = Pattern::euclidean(8, 3).rotate(1).invert().stretch(2);
+//! assert_eq!(hats.to_string_with('x', '.'), "x.x...x.x...x...");
+//! ```
+
+use crate::error::{Error, Result, check_input_steps};
+use crate::meter::Meter;
+use crate::{
+    euclidean, pattern_to_string, rotate_pattern, stretch_pattern, try_euclidean,
+    try_euclidean_large,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+
+/// Metrical position of one step, as yielded by [`Pattern::steps_with_meta`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StepInfo {
+    /// Step index within the pattern.
+    pub index: usize,
+    /// Whether the step is a pulse.
+    pub is_pulse: bool,
+    /// Zero-based bar number.
+    pub bar: usize,
+    /// Zero-based beat within the bar.
+    pub beat: usize,
+    /// Zero-based step within the beat.
+    pub position_in_beat: usize,
+    /// Whether the step is the first of its bar.
+    pub is_downbeat: bool,
+}
+
+/// An owned rhythm pattern: `true` for a pulse, `false` for a rest.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Pattern, euclidean};
+///
+/// let pattern = Pattern::from(euclidean(8, 3, 0));
+/// assert_eq!(pattern.len(), 8);
+/// assert_eq!(pattern.pulse_count(), 3);
+/// assert!(pattern[0]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    steps: Vec<bool>,
+}
+
+impl Pattern {
+    /// Wraps step data in a pattern.
+    pub fn new(steps: Vec<bool>) -> Self {
+        Self { steps }
+    }
+
+    /// The Euclidean rhythm E(`pulses`, `steps`).
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `pulses > steps`.
+    pub fn euclidean(steps: u8, pulses: u8) -> Self {
+        Self::new(euclidean(steps, pulses, 0))
+    }
+
+    /// The Euclidean rhythm E(`pulses`, `steps`), or an error for invalid
+    /// parameters. See [`try_euclidean`].
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `pulses > steps`.
+    pub fn try_euclidean(steps: u8, pulses: u8) -> Result<Self> {
+        try_euclidean(steps, pulses, 0).map(Self::new)
+    }
+
+    /// Parses Euclidean notation as written in papers and live-coding tools:
+    /// `E(pulses,steps)` or `E(pulses,steps,rotation)`, e.g. `"E(3,8)"` or
+    /// `"e(5, 12, 2)"`. The rotation is as for [`euclidean`]; a negative one
+    /// rotates right. For `x..x..x.` strings, use [`str::parse`] instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::{Pattern, euclidean};
+    ///
+    /// assert_eq!(Pattern::parse("E(3,8)").unwrap(), Pattern::euclidean(8, 3));
+    /// assert_eq!(Pattern::parse("E(5,12,2)").unwrap().as_slice(), euclidean(12, 5, 2));
+    /// assert!(Pattern::parse("E(9,8)").is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidParameter`] for malformed notation,
+    /// [`Error::TooManySteps`] for more than
+    /// [`MAX_INPUT_STEPS`](crate::MAX_INPUT_STEPS) steps, and the errors of
+    /// [`try_euclidean_large`] for invalid parameters.
+    pub fn parse(notation: &str) -> Result<Self> {
+        let args = notation
+            .trim()
+            .strip_prefix(['E', 'e'])
+            .map(str::trim_start)
+            .and_then(|rest| rest.strip_prefix('('))
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or(invalid(
+                "expected E(pulses,steps) or E(pulses,steps,rotation)",
+            ))?;
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        let count = |arg: &str| {
+            arg.parse::<usize>()
+                .map_err(|_| invalid("malformed number"))
+        };
+        let (pulses, steps, rotation) = match args[..] {
+            [pulses, steps] => (count(pulses)?, count(steps)?, 0),
+            [pulses, steps, rotation] => (
+                count(pulses)?,
+                count(steps)?,
+                rotation
+                    .parse::<i64>()
+                    .map_err(|_| invalid("malformed number"))?,
+            ),
+            _ => return Err(invalid("expected two or three arguments")),
+        };
+        check_input_steps(steps)?;
+        let rotation = match i64::try_from(steps) {
+            Ok(len) if len > 0 => rotation.rem_euclid(len) as usize,
+            _ => 0,
+        };
+        try_euclidean_large(steps, pulses, rotation).map(Self::new)
+    }
+
+    /// Rotates by `rotation` steps; positive rotates left (earlier), negative
+    /// rotates right. See [`rotate_pattern`].
+    #[must_use]
+    pub fn rotate(self, rotation: i32) -> Self {
+        Self::new(rotate_pattern(&self.steps, rotation))
+    }
+
+    /// Swaps pulses and rests, giving the complement of the pattern. See
+    /// [`invert_pattern`](crate::invert_pattern).
+    #[must_use]
+    pub fn invert(mut self) -> Self {
+        for step in &mut self.steps {
+            *step = !*step;
+        }
+        self
+    }
+
+    /// Returns `true` if the pattern is `other` started on a different step.
+    /// See [`is_rotation_of`](crate::is_rotation_of).
+    pub fn is_rotation_of(&self, other: &[bool]) -> bool {
+        crate::is_rotation_of(&self.steps, other)
+    }
+
+    /// Plays the pattern backwards (its retrograde). See
+    /// [`reverse_pattern`](crate::reverse_pattern).
+    #[must_use]
+    pub fn reverse(mut self) -> Self {
+        self.steps.reverse();
+        self
+    }
+
+    /// Expands every step into `factor` substeps, keeping each pulse on the
+    /// first substep. A factor of 0 yields an empty pattern. See
+    /// [`stretch_pattern`].
+    #[must_use]
+    pub fn stretch(self, factor: usize) -> Self {
+        Self::new(stretch_pattern(&self.steps, factor))
+    }
+
+    /// Renders the pattern with the given characters. See [`pattern_to_string`].
+    pub fn to_string_with(&self, pulse_char: char, rest_char: char) -> String {
+        pattern_to_string(&self.steps, pulse_char, rest_char)
+    }
+
+    /// Parses a rhythm string drawn with the given characters, the inverse of
+    /// [`to_string_with`](Self::to_string_with). Other whitespace and `|`
+    /// are skipped, so grouped strings such as `"x..x|..x."` parse too.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::Pattern;
+    ///
+    /// let pattern = Pattern::from_str_with("1001 0010", '1', '0').unwrap();
+    /// assert_eq!(pattern, Pattern::euclidean(8, 3));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidSymbol`] for any other character, with its
+    /// character index in `text`.
+    pub fn from_str_with(text: &str, pulse_char: char, rest_char: char) -> Result<Self> {
+        parse_steps(text, |symbol| match symbol {
+            _ if symbol == pulse_char => Some(true),
+            _ if symbol == rest_char => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Number of pulses in the pattern.
+    pub fn pulse_count(&self) -> usize {
+        self.steps.iter().filter(|&&s| s).count()
+    }
+
+    /// Indices of the pulses, in step order.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::Pattern;
+    ///
+    /// let onsets: Vec<usize> = Pattern::euclidean(8, 3).onsets().collect();
+    /// assert_eq!(onsets, [0, 3, 6]);
+    /// ```
+    pub fn onsets(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.steps.len()).filter(|&i| self.steps[i])
+    }
+
+    /// Returns the steps as a slice.
+    pub fn as_slice(&self) -> &[bool] {
+        &self.steps
+    }
+
+    /// Returns `len` steps starting at `start`, wrapping around the end of the
+    /// cycle as often as needed. An empty pattern yields an empty slice.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::Pattern;
+    ///
+    /// // The last beat of x..x..x. followed by the first, across the loop point.
+    /// let pattern = Pattern::euclidean(8, 3);
+    /// assert_eq!(pattern.slice_cyclic(6, 4).to_string_with('x', '.'), "x.x.");
+    /// ```
+    pub fn slice_cyclic(&self, start: usize, len: usize) -> Pattern {
+        let n = self.steps.len();
+        if n == 0 {
+            return Pattern::default();
+        }
+        (0..len).map(|i| self.steps[(start + i) % n]).collect()
+    }
+
+    /// Splits the pattern into bars of `meter`. If the length is not a whole
+    /// number of bars, the last bar is completed by wrapping around to the
+    /// start of the pattern, so every bar has [`Meter::bar_len`] steps.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::{Meter, Pattern};
+    ///
+    /// let bars = Pattern::euclidean(12, 5).split_at_bars(&Meter::additive(&[3, 2, 3]));
+    /// assert_eq!(bars.len(), 2);
+    /// assert_eq!(bars[1].len(), 8);
+    /// ```
+    pub fn split_at_bars(&self, meter: &Meter) -> Vec<Pattern> {
+        let bar_len = meter.bar_len();
+        (0..self.steps.len().div_ceil(bar_len))
+            .map(|bar| self.slice_cyclic(bar * bar_len, bar_len))
+            .collect()
+    }
+
+    /// Returns every window of `size` steps, one starting at each step, with
+    /// windows near the end wrapping around to the start.
+    ///
+    /// Yields exactly `len()` windows (none for an empty pattern).
+    pub fn windows_cyclic(&self, size: usize) -> impl Iterator<Item = Pattern> + '_ {
+        (0..self.steps.len()).map(move |start| self.slice_cyclic(start, size))
+    }
+
+    /// Iterates over the steps together with their bar, beat and position in
+    /// `meter`.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::{Meter, Pattern};
+    ///
+    /// let meter = Meter::additive(&[3, 3, 2]);
+    /// let pattern = Pattern::euclidean(16, 5);
+    /// let tenth = pattern.steps_with_meta(&meter).nth(10).unwrap();
+    /// assert_eq!((tenth.bar, tenth.beat, tenth.position_in_beat), (1, 0, 2));
+    /// assert!(!tenth.is_downbeat);
+    /// ```
+    pub fn steps_with_meta<'a>(&'a self, meter: &Meter) -> impl Iterator<Item = StepInfo> + 'a {
+        let bar_len = meter.bar_len();
+        let positions: Vec<(usize, usize)> = meter
+            .groups()
+            .iter()
+            .enumerate()
+            .flat_map(|(beat, &len)| (0..usize::from(len)).map(move |pos| (beat, pos)))
+            .collect();
+        self.steps
+            .iter()
+            .enumerate()
+            .map(move |(index, &is_pulse)| {
+                let (beat, position_in_beat) = positions[index % bar_len];
+                StepInfo {
+                    index,
+                    is_pulse,
+                    bar: index / bar_len,
+                    beat,
+                    position_in_beat,
+                    is_downbeat: index % bar_len == 0,
+                }
+            })
+    }
+
+    /// Consumes the pattern and returns the step data.
+    pub fn into_vec(self) -> Vec<bool> {
+        self.steps
+    }
+}
+
+impl Deref for Pattern {
+    type Target = [bool];
+
+    fn deref(&self) -> &[bool] {
+        &self.steps
+    }
+}
+
+/// Renders pulses as `x` and rests as `.`, e.g. `x..x..x.`; see
+/// [`to_string_with`](Pattern::to_string_with) for other characters.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_with('x', '.'))
+    }
+}
+
+/// Parses `x` or `X` as a pulse and `.` or `-` as a rest, skipping
+/// whitespace and `|`, so the [`Display`](fmt::Display) output round-trips;
+/// see [`from_str_with`](Pattern::from_str_with) for other characters.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::Pattern;
+///
+/// let pattern: Pattern = "x..x..x.".parse().unwrap();
+/// assert_eq!(pattern, Pattern::euclidean(8, 3));
+/// assert_eq!(pattern.to_string().parse(), Ok(pattern));
+/// ```
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        parse_steps(text, |symbol| match symbol {
+            'x' | 'X' => Some(true),
+            '.' | '-' => Some(false),
+            _ => None,
+        })
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "notation",
+        reason,
+    }
+}
+
+/// Reads one step per symbol that `step` recognizes, skipping whitespace
+/// and `|`.
+fn parse_steps(text: &str, step: impl Fn(char) -> Option<bool>) -> Result<Pattern> {
+    let mut steps = Vec::with_capacity(text.len());
+    for (position, symbol) in text.chars().enumerate() {
+        match step(symbol) {
+            Some(pulse) => steps.push(pulse),
+            None if symbol.is_whitespace() || symbol == '|' => {}
+            None => return Err(Error::InvalidSymbol { symbol, position }),
+        }
+    }
+    Ok(Pattern::new(steps))
+}
+
+impl AsRef<[bool]> for Pattern {
+    fn as_ref(&self) -> &[bool] {
+        &self.steps
+    }
+}
+
+impl From<Vec<bool>> for Pattern {
+    fn from(steps: Vec<bool>) -> Self {
+        Self::new(steps)
+    }
+}
+
+impl From<&[bool]> for Pattern {
+    fn from(steps: &[bool]) -> Self {
+        Self::new(steps.to_vec())
+    }
+}
+
+impl FromIterator<bool> for Pattern {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl From<Pattern> for Vec<bool> {
+    fn from(pattern: Pattern) -> Self {
+        pattern.steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_and_unwraps_steps() {
+        let steps = euclidean(12, 5, 0);
+        let pattern = Pattern::from(steps.clone());
+        assert_eq!(pattern.as_slice(), steps.as_slice());
+        assert_eq!(pattern.pulse_count(), 5);
+        assert_eq!(Vec::from(pattern), steps);
+        assert!(Pattern::default().is_empty());
+        assert_eq!(Pattern::euclidean(8, 3).to_string(), "x..x..x.");
+        assert_eq!(Pattern::euclidean(4, 0).onsets().count(), 0);
+    }
+
+    #[test]
+    fn parses_rhythm_strings() {
+        let tresillo = Pattern::euclidean(8, 3);
+        assert_eq!("X..x | --x-".parse(), Ok(tresillo.clone()));
+        assert_eq!(Pattern::from_str_with("o  o  o ", 'o', ' '), Ok(tresillo));
+        assert_eq!(
+            "x.o".parse::<Pattern>(),
+            Err(Error::InvalidSymbol {
+                symbol: 'o',
+                position: 2
+            })
+        );
+        assert_eq!("".parse(), Ok(Pattern::default()));
+    }
+
+    #[test]
+    fn parses_euclidean_notation() {
+        assert_eq!(
+            Pattern::parse(" e( 5, 12 , -10 ) "),
+            Pattern::try_euclidean(12, 5).map(|p| p.rotate(2))
+        );
+        assert_eq!(Pattern::parse("E(0,4)"), Ok(Pattern::new(vec![false; 4])));
+        assert_eq!(Pattern::parse("E(3,300)").map(|p| p.len()), Ok(300));
+        assert_eq!(Pattern::parse("E(3,0)"), Err(Error::ZeroSteps));
+        assert_eq!(
+            Pattern::parse("E(1,99999999999999)"),
+            Err(Error::TooManySteps {
+                steps: 99_999_999_999_999,
+                max: crate::MAX_INPUT_STEPS
+            })
+        );
+        for notation in [
+            "E(3)",
+            "E(3,8,1,1)",
+            "E(3,8",
+            "(3,8)",
+            "E(x,8)",
+            "E(3,8,1.5)",
+            "F(3,8)",
+        ] {
+            assert!(matches!(
+                Pattern::parse(notation),
+                Err(Error::InvalidParameter {
+                    name: "notation",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn chained_transformations() {
+        let pattern = Pattern::euclidean(16, 7).rotate(2).invert().stretch(2);
+        assert_eq!(pattern.len(), 32);
+        assert_eq!(pattern.pulse_count(), 9);
+        assert_eq!(
+            Pattern::euclidean(8, 3).rotate(-1).to_string_with('x', '.'),
+            ".x..x..x"
+        );
+        assert!(Pattern::euclidean(4, 2).stretch(0).is_empty());
+    }
+
+    #[test]
+    fn cyclic_slices_and_windows() {
+        let pattern = Pattern::euclidean(5, 2);
+        assert_eq!(
+            pattern.slice_cyclic(3, 7).to_string_with('x', '.'),
+            "..x.x.."
+        );
+        assert!(Pattern::default().slice_cyclic(2, 3).is_empty());
+
+        let windows: Vec<String> = pattern
+            .windows_cyclic(3)
+            .map(|w| w.to_string_with('x', '.'))
+            .collect();
+        assert_eq!(windows, ["x.x", ".x.", "x..", "..x", ".x."]);
+
+        // 16 steps in 6-step bars: the third bar borrows two steps from the start.
+        let sixteen = Pattern::euclidean(16, 5);
+        let bars = sixteen.split_at_bars(&Meter::simple(3, 2));
+        assert_eq!(bars.len(), 3);
+        assert!(bars.iter().all(|bar| bar.len() == 6));
+        assert_eq!(bars[2][4..], sixteen[..2]);
+    }
+
+    #[test]
+    fn step_metadata_follows_meter() {
+        let pattern = Pattern::euclidean(8, 3);
+        let meta: Vec<StepInfo> = pattern.steps_with_meta(&Meter::simple(2, 2)).collect();
+        assert_eq!(meta.len(), 8);
+        assert_eq!(
+            meta[5],
+            StepInfo {
+                index: 5,
+                is_pulse: false,
+                bar: 1,
+                beat: 0,
+                position_in_beat: 1,
+                is_downbeat: false,
+            }
+        );
+        let downbeats: Vec<usize> = meta
+            .iter()
+            .filter(|s| s.is_downbeat)
+            .map(|s| s.index)
+            .collect();
+        assert_eq!(downbeats, vec![0, 4]);
+        assert_eq!(meta.iter().filter(|s| s.is_pulse).count(), 3);
+    }
+}
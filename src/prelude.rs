@@ -0,0 +1,15 @@
+//! Commonly used items in one import.
+//!
+//! ```
+//! use euclidean_rhythm::prelude::*;
+//!
+//! let pattern = Pattern::from(euclidean(8, 3, 0));
+//! assert_eq!(pattern_to_string(&pattern, 'x', '.'), "x..x..x.");
+//! ```
+
+pub use crate::bank::PatternBank;
+pub use crate::meter::Meter;
+pub use crate::pattern::Pattern;
+#[cfg(feature = "sequencer")]
+pub use crate::sequencer::{Sequencer, SequencerTrack, StepEvent, StepListener};
+pub use crate::{euclidean, pattern_to_string, rotate_pattern};
@@ -0,0 +1,399 @@
+//! Recording and replaying sequencer runs.
+//!
+//! A [`Recorder`] plays a [`Sequencer`] and logs everything that happens:
+//! each [`StepEvent`] and [`DriftEvent`] it emits, and each
+//! [`SequencerChange`] made while it plays, all stamped with the tick they
+//! belong to. Together with a copy of the sequencer as it was when recording
+//! started, the resulting [`EventLog`] replays the run exactly, seeded
+//! probability rolls included. Use it to step back through a run while
+//! debugging, or to bounce a live-tweaked jam to MIDI afterwards by handing
+//! [`EventLog::step_events`] to `events_to_midi`.
+
+use crate::error::{Error, Result};
+use crate::sequencer::{DriftEvent, RotationDrift, Sequencer, SequencerTrack, StepEvent};
+use crate::trig::{ParamLock, TrigCondition};
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "track",
+        reason,
+    }
+}
+
+/// A change made to a sequencer while it plays.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequencerChange {
+    /// Adds a track.
+    AddTrack(SequencerTrack),
+    /// Replaces a track's Euclidean parameters.
+    Params {
+        /// Index of the track.
+        track: usize,
+        /// Step count.
+        steps: u8,
+        /// Pulse count.
+        pulses: u8,
+        /// Rotation.
+        rotation: u8,
+    },
+    /// Changes the tempo.
+    Bpm(f64),
+    /// Turns the sequencer's fill on or off.
+    Fill(bool),
+    /// Mutes or unmutes a track.
+    Muted {
+        /// Index of the track.
+        track: usize,
+        /// Whether the track is muted.
+        muted: bool,
+    },
+    /// Solos or unsolos a track.
+    Soloed {
+        /// Index of the track.
+        track: usize,
+        /// Whether the track is soloed.
+        soloed: bool,
+    },
+    /// Puts a track into fill or takes it out.
+    TrackFill {
+        /// Index of the track.
+        track: usize,
+        /// Whether the track is in fill.
+        fill: bool,
+    },
+    /// Sets the play condition of a step.
+    Condition {
+        /// Index of the track.
+        track: usize,
+        /// Step within the track's pattern.
+        step: usize,
+        /// The new condition.
+        condition: TrigCondition,
+    },
+    /// Sets the parameter lock of a step.
+    Lock {
+        /// Index of the track.
+        track: usize,
+        /// Step within the track's pattern.
+        step: usize,
+        /// The new lock.
+        lock: ParamLock,
+    },
+    /// Sets or removes a track's rotation drift.
+    Drift {
+        /// Index of the track.
+        track: usize,
+        /// The new drift.
+        drift: Option<RotationDrift>,
+    },
+}
+
+impl SequencerChange {
+    /// Makes the change to `sequencer`.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidParameter`] named `"track"` for a track index
+    /// the sequencer does not have, and the usual parameter errors for
+    /// invalid Euclidean parameters. The sequencer is unchanged on error.
+    pub fn apply(&self, sequencer: &mut Sequencer) -> Result<()> {
+        let index = match *self {
+            SequencerChange::AddTrack(ref track) => {
+                sequencer.add_track(track.clone());
+                return Ok(());
+            }
+            SequencerChange::Bpm(bpm) => {
+                sequencer.set_bpm(bpm);
+                return Ok(());
+            }
+            SequencerChange::Fill(fill) => {
+                sequencer.set_fill(fill);
+                return Ok(());
+            }
+            SequencerChange::Params { track, .. }
+            | SequencerChange::Muted { track, .. }
+            | SequencerChange::Soloed { track, .. }
+            | SequencerChange::TrackFill { track, .. }
+            | SequencerChange::Condition { track, .. }
+            | SequencerChange::Lock { track, .. }
+            | SequencerChange::Drift { track, .. } => track,
+        };
+        let track = sequencer.track_mut(index).ok_or(invalid("no such track"))?;
+        match *self {
+            SequencerChange::Params {
+                steps,
+                pulses,
+                rotation,
+                ..
+            } => track.try_set_params(steps, pulses, rotation)?,
+            SequencerChange::Muted { muted, .. } => track.set_muted(muted),
+            SequencerChange::Soloed { soloed, .. } => track.set_soloed(soloed),
+            SequencerChange::TrackFill { fill, .. } => track.set_fill(fill),
+            SequencerChange::Condition {
+                step, condition, ..
+            } => track.set_condition(step, condition),
+            SequencerChange::Lock { step, lock, .. } => track.set_lock(step, lock),
+            SequencerChange::Drift { drift, .. } => track.set_drift(drift),
+            SequencerChange::AddTrack(_) | SequencerChange::Bpm(_) | SequencerChange::Fill(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Something that happened during a recorded run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoggedEvent {
+    /// A step was played.
+    Step(StepEvent),
+    /// A track's rotation drifted.
+    Drift(DriftEvent),
+    /// The sequencer was changed, before the step at the entry's tick.
+    Change(SequencerChange),
+}
+
+/// One entry of an [`EventLog`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// The sequencer tick the event belongs to.
+    pub tick: u64,
+    /// What happened.
+    pub event: LoggedEvent,
+}
+
+/// Plays a [`Sequencer`] and logs the run.
+///
+/// Changes must go through [`change`](Self::change) to be logged, so the
+/// sequencer itself is only lent out read-only. Rotation drift is logged
+/// rather than returned.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Recorder, Sequencer, SequencerChange, SequencerTrack};
+///
+/// let seq = Sequencer::new(120.0).with_track(SequencerTrack::new("kick", 4, 1, 0));
+/// let mut recorder = Recorder::new(seq);
+/// recorder.tick();
+/// recorder.change(SequencerChange::Params { track: 0, steps: 4, pulses: 4, rotation: 0 })?;
+/// recorder.tick();
+///
+/// let log = recorder.finish();
+/// let ticks: Vec<u64> = log.step_events().iter().map(|e| e.tick).collect();
+/// assert_eq!(ticks, [0, 1]);
+/// assert_eq!(log.replay(), log.entries());
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorder {
+    sequencer: Sequencer,
+    log: EventLog,
+}
+
+impl Recorder {
+    /// Starts recording `sequencer` from its current state.
+    pub fn new(sequencer: Sequencer) -> Self {
+        Self {
+            log: EventLog {
+                start: sequencer.clone(),
+                entries: Vec::new(),
+                end: sequencer.position(),
+            },
+            sequencer,
+        }
+    }
+
+    /// The sequencer being recorded.
+    pub fn sequencer(&self) -> &Sequencer {
+        &self.sequencer
+    }
+
+    /// The log so far.
+    pub fn log(&self) -> &EventLog {
+        &self.log
+    }
+
+    /// Makes a change to the sequencer and logs it at the current position,
+    /// so it applies from the next step.
+    ///
+    /// # Errors
+    /// Fails as [`SequencerChange::apply`] does; failed changes are not
+    /// logged.
+    pub fn change(&mut self, change: SequencerChange) -> Result<()> {
+        change.apply(&mut self.sequencer)?;
+        self.log
+            .push(self.sequencer.position(), LoggedEvent::Change(change));
+        Ok(())
+    }
+
+    /// Plays one step, as [`Sequencer::tick`] does, and logs it.
+    pub fn tick(&mut self) -> Vec<StepEvent> {
+        let events = self.sequencer.tick();
+        self.record(&events);
+        events
+    }
+
+    /// Advances the clock, as [`Sequencer::advance`] does, and logs every
+    /// step played.
+    pub fn advance(&mut self, seconds: f64) -> Vec<StepEvent> {
+        let events = self.sequencer.advance(seconds);
+        self.record(&events);
+        events
+    }
+
+    /// Stops recording and returns the log.
+    pub fn finish(self) -> EventLog {
+        self.log
+    }
+
+    /// Logs the events of the steps just played, each tick's drift first as
+    /// the sequencer rolls it first.
+    fn record(&mut self, events: &[StepEvent]) {
+        let mut drift = self.sequencer.take_drift_events().into_iter().peekable();
+        for event in events {
+            while let Some(d) = drift.next_if(|d| d.tick <= event.tick) {
+                self.log.push(d.tick, LoggedEvent::Drift(d));
+            }
+            self.log.push(event.tick, LoggedEvent::Step(*event));
+        }
+        for d in drift {
+            self.log.push(d.tick, LoggedEvent::Drift(d));
+        }
+        self.log.end = self.sequencer.position();
+    }
+}
+
+/// A recorded run: the sequencer as it started and everything that happened
+/// since, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLog {
+    start: Sequencer,
+    entries: Vec<LogEntry>,
+    end: u64,
+}
+
+impl EventLog {
+    /// The sequencer as it was when recording started.
+    pub fn start(&self) -> &Sequencer {
+        &self.start
+    }
+
+    /// Every logged event, in order.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// The tick the recording started on.
+    pub fn start_tick(&self) -> u64 {
+        self.start.position()
+    }
+
+    /// The tick after the last one recorded.
+    pub fn end_tick(&self) -> u64 {
+        self.end
+    }
+
+    /// The step events alone, e.g. to export with `events_to_midi` using
+    /// [`end_tick`](Self::end_tick) as the length.
+    pub fn step_events(&self) -> Vec<StepEvent> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry.event {
+                LoggedEvent::Step(event) => Some(event),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Plays the run again from the start, making each change at its tick,
+    /// and returns the log it produces. A faithful recording replays to its
+    /// own [`entries`](Self::entries).
+    pub fn replay(&self) -> Vec<LogEntry> {
+        self.replay_until(self.end).1
+    }
+
+    /// The sequencer as it was just before playing `tick`, with every change
+    /// logged up to then made. Ticks past the end give the final state.
+    pub fn replay_to(&self, tick: u64) -> Sequencer {
+        self.replay_until(tick).0
+    }
+
+    fn replay_until(&self, tick: u64) -> (Sequencer, Vec<LogEntry>) {
+        let mut recorder = Recorder::new(self.start.clone());
+        let mut changes = self
+            .entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                LoggedEvent::Change(change) => Some((entry.tick, change)),
+                _ => None,
+            })
+            .peekable();
+        let end = tick.min(self.end);
+        loop {
+            let position = recorder.sequencer.position();
+            while let Some((_, change)) = changes.next_if(|&(at, _)| at <= position) {
+                // Logged changes succeeded when recorded, and replay from
+                // the same state.
+                let _ = recorder.change(change.clone());
+            }
+            if position >= end {
+                break;
+            }
+            recorder.tick();
+        }
+        (recorder.sequencer, recorder.log.entries)
+    }
+
+    fn push(&mut self, tick: u64, event: LoggedEvent) {
+        self.entries.push(LogEntry { tick, event });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_a_tweaked_run_exactly() {
+        let drift = RotationDrift {
+            percent: 50,
+            range: 2,
+        };
+        let seq = Sequencer::new(120.0).with_seed(9).with_track(
+            SequencerTrack::new("k", 4, 1, 0)
+                .with_drift(drift)
+                .with_condition(0, TrigCondition::Probability(60)),
+        );
+        let mut recorder = Recorder::new(seq);
+        recorder.advance(1.0);
+        recorder
+            .change(SequencerChange::AddTrack(SequencerTrack::new("h", 3, 2, 0)))
+            .unwrap();
+        recorder.advance(1.0);
+        recorder.change(SequencerChange::Bpm(90.0)).unwrap();
+        assert!(recorder.change(SequencerChange::Fill(true)).is_ok());
+        assert!(
+            recorder
+                .change(SequencerChange::Muted {
+                    track: 5,
+                    muted: true
+                })
+                .is_err()
+        );
+        recorder.tick();
+
+        let log = recorder.finish();
+        assert!(log.entries().windows(2).all(|w| w[0].tick <= w[1].tick));
+        assert!(
+            log.entries()
+                .iter()
+                .any(|e| matches!(e.event, LoggedEvent::Drift(_)))
+        );
+        assert_eq!(log.replay(), log.entries());
+        assert_eq!(log.end_tick(), 18);
+
+        // The tempo changed before tick 17 was played.
+        let before = log.replay_to(16);
+        assert_eq!((before.position(), before.tracks().len()), (16, 2));
+        assert_eq!(before.bpm(), 120.0);
+        assert_eq!(log.replay_to(17).bpm(), 90.0);
+        assert_eq!(log.replay_to(100).position(), 18);
+    }
+}
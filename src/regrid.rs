@@ -0,0 +1,182 @@
+//! Moving patterns between step resolutions.
+//!
+//! A 12-step pattern has to land on a 16-step grid before it can play next to
+//! one. [`regrid`] maps each onset onto the new grid by its position in the
+//! cycle, so the rhythm keeps its shape as closely as the new grid allows.
+//!
+//! When the old grid is a whole multiple of the new one, [`compress_pattern`]
+//! merges each group of steps instead, the inverse of `stretch_pattern`, with
+//! a [`CompressStrategy`] deciding which groups become pulses.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Where [`regrid`] puts an onset on the new grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RegridPolicy {
+    /// The new step nearest to the onset's time, rounding halfway cases up
+    /// (later). Onsets near the end of the cycle may wrap round to step 0.
+    #[default]
+    Nearest,
+    /// The new step whose span contains the onset's time, so no onset moves
+    /// later than it was.
+    Proportional,
+}
+
+/// How [`compress_pattern`] turns a group of steps into one step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CompressStrategy {
+    /// A pulse if any step in the group is one, so no onset is lost.
+    #[default]
+    AnyOnset,
+    /// A pulse if the group's first step is one: the exact inverse of
+    /// `stretch_pattern`, dropping onsets between the new steps.
+    FirstOnset,
+    /// A pulse if more than half of the group's steps are pulses; a tie is a
+    /// rest.
+    Majority,
+}
+
+/// Merges every `factor` consecutive steps of `pattern` into one, for
+/// turning a high-resolution grid into a compact pattern. A trailing group
+/// shorter than `factor` still gives a step. A factor of 0 yields an empty
+/// pattern.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{CompressStrategy, compress_pattern, pattern_to_string};
+///
+/// // A take on 32nds whose second hit landed a 32nd late.
+/// let take: Vec<bool> = "x......x....x.x.".chars().map(|c| c == 'x').collect();
+/// let any = compress_pattern(&take, 2, CompressStrategy::AnyOnset);
+/// assert_eq!(pattern_to_string(&any, 'x', '.'), "x..x..xx");
+/// let first = compress_pattern(&take, 2, CompressStrategy::FirstOnset);
+/// assert_eq!(pattern_to_string(&first, 'x', '.'), "x.....xx");
+///
+/// // A roll thinned to quarter notes: only mostly-filled beats survive.
+/// let roll: Vec<bool> = "xxx.x...xx..xxxx".chars().map(|c| c == 'x').collect();
+/// let beats = compress_pattern(&roll, 4, CompressStrategy::Majority);
+/// assert_eq!(pattern_to_string(&beats, 'x', '.'), "x..x");
+/// ```
+pub fn compress_pattern(pattern: &[bool], factor: usize, strategy: CompressStrategy) -> Vec<bool> {
+    if factor == 0 {
+        return Vec::new();
+    }
+    pattern
+        .chunks(factor)
+        .map(|group| match strategy {
+            CompressStrategy::AnyOnset => group.contains(&true),
+            CompressStrategy::FirstOnset => group[0],
+            CompressStrategy::Majority => {
+                2 * group.iter().filter(|&&pulse| pulse).count() > group.len()
+            }
+        })
+        .collect()
+}
+
+/// Resamples `pattern` onto a grid of `new_steps` steps covering the same
+/// cycle.
+///
+/// When two onsets land on the same step, the later one takes the nearest
+/// free step instead (the next one on a tie), so every onset survives as
+/// long as the new grid has room. Returns an empty pattern if `new_steps`
+/// is zero or `pattern` is empty.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{RegridPolicy, euclidean, pattern_to_string, regrid};
+///
+/// // E(7,12), x.xx.x.xx.x., onto sixteenths.
+/// let bell = euclidean(12, 7, 0);
+/// let sixteenths = regrid(&bell, 16, RegridPolicy::Nearest);
+/// assert_eq!(pattern_to_string(&sixteenths, 'x', '.'), "x..xx..x.x.x.x..");
+/// assert_eq!(sixteenths.iter().filter(|&&p| p).count(), 7);
+/// ```
+pub fn regrid(pattern: &[bool], new_steps: usize, policy: RegridPolicy) -> Vec<bool> {
+    let old_steps = pattern.len();
+    if old_steps == 0 || new_steps == 0 {
+        return Vec::new();
+    }
+    let mut result = vec![false; new_steps];
+    for onset in (0..old_steps).filter(|&i| pattern[i]) {
+        let scaled = onset * new_steps;
+        let target = match policy {
+            // round(scaled / old_steps) with halves rounding up.
+            RegridPolicy::Nearest => (2 * scaled + old_steps) / (2 * old_steps) % new_steps,
+            RegridPolicy::Proportional => scaled / old_steps,
+        };
+        if let Some(free) = nearest_free(&result, target) {
+            result[free] = true;
+        }
+    }
+    result
+}
+
+/// The unoccupied step closest to `target`, looking around the cycle and
+/// preferring later steps on a tie.
+fn nearest_free(steps: &[bool], target: usize) -> Option<usize> {
+    let len = steps.len();
+    (0..=len / 2)
+        .flat_map(|d| [(target + d) % len, (target + len - d) % len])
+        .find(|&i| !steps[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn round_trips_through_finer_grids() {
+        for pulses in 0..=12 {
+            let pattern = euclidean(12, pulses, 0);
+            for policy in [RegridPolicy::Nearest, RegridPolicy::Proportional] {
+                let fine = regrid(&pattern, 24, policy);
+                assert_eq!(regrid(&fine, 12, policy), pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn compress_strategies() {
+        let pattern = euclidean(12, 5, 1);
+        for strategy in [CompressStrategy::AnyOnset, CompressStrategy::FirstOnset] {
+            let stretched = crate::stretch_pattern(&pattern, 3);
+            assert_eq!(compress_pattern(&stretched, 3, strategy), pattern);
+        }
+        let groups = [true, true, false, false, true, false, false, true, true];
+        let any = compress_pattern(&groups, 3, CompressStrategy::AnyOnset);
+        let first = compress_pattern(&groups, 3, CompressStrategy::FirstOnset);
+        let majority = compress_pattern(&groups, 3, CompressStrategy::Majority);
+        assert_eq!(any, [true, true, true]);
+        assert_eq!(first, [true, false, false]);
+        assert_eq!(majority, [true, false, true]);
+        assert_eq!(
+            compress_pattern(&groups, 2, CompressStrategy::Majority).len(),
+            5
+        );
+        assert!(compress_pattern(&groups, 0, CompressStrategy::AnyOnset).is_empty());
+    }
+
+    #[test]
+    fn collisions_and_policies() {
+        // Three adjacent onsets squeezed onto a 4-step grid keep their count.
+        let dense = [true, true, true, false, false, false, false, false];
+        let squeezed = regrid(&dense, 4, RegridPolicy::Proportional);
+        assert_eq!(squeezed, [true, true, true, false]);
+        let crowded = regrid(&[true; 8], 4, RegridPolicy::Nearest);
+        assert_eq!(crowded, [true; 4]);
+
+        // Step 7 of 8 is 3.5 steps of 4: nearest rounds up to wrap, proportional stays.
+        let last = [false, false, false, false, false, false, false, true];
+        assert_eq!(
+            regrid(&last, 4, RegridPolicy::Nearest),
+            [true, false, false, false]
+        );
+        assert_eq!(
+            regrid(&last, 4, RegridPolicy::Proportional),
+            [false, false, false, true]
+        );
+        assert!(regrid(&last, 0, RegridPolicy::Nearest).is_empty());
+    }
+}
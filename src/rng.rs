@@ -0,0 +1,77 @@
+//! Small deterministic random number generator.
+//!
+//! The crate has no dependencies, so generative features use this SplitMix64
+//! generator. It is not cryptographically secure; it only needs to be fast and
+//! reproducible for a given seed.
+
+/// SplitMix64 pseudo-random number generator.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a seed.
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator for one cycle of a seeded process, so that cycle
+    /// `n` always produces the same values regardless of which cycles were
+    /// generated before it.
+    pub(crate) fn for_cycle(seed: u64, cycle: u64) -> Self {
+        let mut mixer = Self::new(seed ^ cycle.wrapping_mul(0xA076_1D64_78BD_642F));
+        Self::new(mixer.next_u64())
+    }
+
+    /// Returns the next 64 random bits.
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed value in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns `true` with probability `p` (clamped to `[0, 1]`).
+    pub(crate) fn chance(&mut self, p: f32) -> bool {
+        self.next_f64() < f64::from(p.clamp(0.0, 1.0))
+    }
+
+    /// Returns a uniformly distributed index in `0..n`. `n` must be non-zero.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..16 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn ranges_are_respected() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            assert!(rng.below(5) < 5);
+        }
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+    }
+}
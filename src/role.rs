@@ -0,0 +1,173 @@
+//! Musical roles for the tracks of a kit.
+//!
+//! A [`TrackRole`] names what a track plays and carries defaults that suit
+//! it: a Euclidean pattern, a range of sensible densities, a General MIDI
+//! note and a velocity. Kits built from roles sound like a groove from the
+//! first bar, and exporters can read the note and name off the role.
+//!
+//! ```
+//! use euclidean_rhythm::{PatternBank, TrackRole, pattern_to_string};
+//!
+//! let kit = PatternBank::new()
+//!     .with_role(TrackRole::Kick)
+//!     .with_role(TrackRole::Snare)
+//!     .with_role(TrackRole::ClosedHat);
+//! let drawn: Vec<String> = kit
+//!     .tracks()
+//!     .iter()
+//!     .map(|track| pattern_to_string(&track.pattern, 'x', '.'))
+//!     .collect();
+//! assert_eq!(drawn, ["x...x...x...x...", "....x.......x...", "xx.xx.xx.xx.xxx."]);
+//! assert_eq!(kit.tracks()[2].name, "closed-hat");
+//! ```
+
+use crate::euclidean;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+/// What a track plays in a kit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TrackRole {
+    /// Bass drum: four on the floor.
+    Kick,
+    /// Snare: the backbeat on steps 4 and 12.
+    Snare,
+    /// Closed hi-hat: a dense, syncopated sixteenth figure.
+    ClosedHat,
+    /// Open hi-hat: sparse off-beats.
+    OpenHat,
+    /// Hand percussion (a clap by default): a syncopated accent figure.
+    Perc,
+    /// Bass line: the bossa nova rhythm E(7,16). Its note is a pitch (C2),
+    /// not a drum.
+    Bass,
+}
+
+impl TrackRole {
+    /// Every role, in kit order.
+    pub const ALL: [TrackRole; 6] = [
+        TrackRole::Kick,
+        TrackRole::Snare,
+        TrackRole::ClosedHat,
+        TrackRole::OpenHat,
+        TrackRole::Perc,
+        TrackRole::Bass,
+    ];
+
+    /// The role's track name, e.g. `"closed-hat"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            TrackRole::Kick => "kick",
+            TrackRole::Snare => "snare",
+            TrackRole::ClosedHat => "closed-hat",
+            TrackRole::OpenHat => "open-hat",
+            TrackRole::Perc => "perc",
+            TrackRole::Bass => "bass",
+        }
+    }
+
+    /// Default step count: one bar of sixteenths for every role.
+    pub fn steps(self) -> u8 {
+        16
+    }
+
+    /// Default pulse count.
+    pub fn pulses(self) -> u8 {
+        match self {
+            TrackRole::Kick => 4,
+            TrackRole::Snare => 2,
+            TrackRole::ClosedHat => 11,
+            TrackRole::OpenHat => 2,
+            TrackRole::Perc => 5,
+            TrackRole::Bass => 7,
+        }
+    }
+
+    /// Pulse counts that suit the role over [`steps`](Self::steps) steps,
+    /// e.g. for randomizing a kit without losing its character.
+    pub fn density(self) -> RangeInclusive<u8> {
+        match self {
+            TrackRole::Kick => 2..=5,
+            TrackRole::Snare => 1..=3,
+            TrackRole::ClosedHat => 6..=13,
+            TrackRole::OpenHat => 1..=4,
+            TrackRole::Perc => 2..=7,
+            TrackRole::Bass => 3..=9,
+        }
+    }
+
+    /// Default rotation, as passed to [`euclidean`]; it moves the snare onto
+    /// the backbeat and the open hat off the beat.
+    pub fn rotation(self) -> u8 {
+        match self {
+            TrackRole::Kick | TrackRole::Bass => 0,
+            TrackRole::Snare => 4,
+            TrackRole::ClosedHat | TrackRole::OpenHat => 2,
+            TrackRole::Perc => 3,
+        }
+    }
+
+    /// General MIDI note: a percussion key on channel 10 for the drums, C2
+    /// for the bass.
+    pub fn note(self) -> u8 {
+        match self {
+            TrackRole::Kick | TrackRole::Bass => 36,
+            TrackRole::Snare => 38,
+            TrackRole::ClosedHat => 42,
+            TrackRole::OpenHat => 46,
+            TrackRole::Perc => 39,
+        }
+    }
+
+    /// Default velocity, with hats and percussion sitting under the kick and
+    /// snare.
+    pub fn velocity(self) -> u8 {
+        match self {
+            TrackRole::Kick | TrackRole::Snare => 110,
+            TrackRole::ClosedHat | TrackRole::Perc => 80,
+            TrackRole::OpenHat | TrackRole::Bass => 96,
+        }
+    }
+
+    /// The default pattern: E([`pulses`](Self::pulses),
+    /// [`steps`](Self::steps)) rotated by [`rotation`](Self::rotation).
+    pub fn pattern(self) -> Vec<bool> {
+        euclidean(self.steps(), self.pulses(), self.rotation())
+    }
+
+    /// The dynamic shape that suits the role, for `velocity_contour`.
+    #[cfg(feature = "analysis")]
+    pub fn contour(self) -> crate::dynamics::VelocityContour {
+        use crate::dynamics::VelocityContour;
+        match self {
+            TrackRole::Kick | TrackRole::Snare | TrackRole::OpenHat => VelocityContour::AccentFirst,
+            TrackRole::ClosedHat => VelocityContour::EuclideanAccents(4),
+            TrackRole::Perc => VelocityContour::Sine,
+            TrackRole::Bass => VelocityContour::EuclideanAccents(3),
+        }
+    }
+}
+
+impl fmt::Display for TrackRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_fit_their_own_ranges() {
+        for role in TrackRole::ALL {
+            assert!(role.density().contains(&role.pulses()));
+            assert!(*role.density().end() <= role.steps());
+            assert_eq!(role.pattern().len(), usize::from(role.steps()));
+            assert_eq!(role.to_string(), role.name());
+        }
+        let snare = TrackRole::Snare.pattern();
+        assert!(snare[4] && snare[12]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Run-length encoding of patterns.
+//!
+//! Long patterns are mostly long stretches of rests, so storing them as
+//! `(state, length)` runs is compact. The text form writes each run as a
+//! count followed by `x` (pulses) or `.` (rests): tresillo is `1x 2. 1x 2. 1x 1.`.
+
+use crate::error::{Error, Result, check_input_steps};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Splits a pattern into runs of equal steps, as `(is_pulse, length)` pairs.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, to_runs};
+///
+/// assert_eq!(
+///     to_runs(&euclidean(8, 3, 0)),
+///     vec![(true, 1), (false, 2), (true, 1), (false, 2), (true, 1), (false, 1)]
+/// );
+/// ```
+pub fn to_runs(pattern: &[bool]) -> Vec<(bool, usize)> {
+    let mut runs: Vec<(bool, usize)> = Vec::new();
+    for &step in pattern {
+        match runs.last_mut() {
+            Some((state, len)) if *state == step => *len += 1,
+            _ => runs.push((step, 1)),
+        }
+    }
+    runs
+}
+
+/// Expands runs back into a pattern. Zero-length runs are skipped, so
+/// `from_runs(&to_runs(p)) == p` for every pattern.
+pub fn from_runs(runs: &[(bool, usize)]) -> Vec<bool> {
+    let mut pattern = Vec::with_capacity(runs.iter().map(|&(_, len)| len).sum());
+    for &(state, len) in runs {
+        pattern.extend(core::iter::repeat_n(state, len));
+    }
+    pattern
+}
+
+/// Writes a pattern in run notation, e.g. `"3x 2. 1x"`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, parse_runs, runs_to_string};
+///
+/// let text = runs_to_string(&euclidean(8, 3, 0));
+/// assert_eq!(text, "1x 2. 1x 2. 1x 1.");
+/// assert_eq!(parse_runs(&text)?, euclidean(8, 3, 0));
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+pub fn runs_to_string(pattern: &[bool]) -> String {
+    let mut out = String::new();
+    for (i, (state, len)) in to_runs(pattern).into_iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        let _ = write!(out, "{len}{}", if state { 'x' } else { '.' });
+    }
+    out
+}
+
+/// Parses run notation: whitespace-separated runs, each a count followed by
+/// `x`/`X` for pulses or `.`/`-` for rests. A missing count means 1.
+///
+/// # Errors
+/// Returns [`Error::InvalidSymbol`] for any other character, with its
+/// character index in `text`, and [`Error::TooManySteps`] if the pattern
+/// would be longer than [`MAX_INPUT_STEPS`](crate::MAX_INPUT_STEPS).
+pub fn parse_runs(text: &str) -> Result<Vec<bool>> {
+    let mut pattern = Vec::new();
+    let mut count: Option<usize> = None;
+    for (position, symbol) in text.chars().enumerate() {
+        let state = match symbol {
+            '0'..='9' => {
+                let digit = symbol as usize - '0' as usize;
+                count = Some(
+                    count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|c| c.checked_add(digit))
+                        .ok_or(Error::InvalidParameter {
+                            name: "run length",
+                            reason: "too large",
+                        })?,
+                );
+                continue;
+            }
+            c if c.is_whitespace() && count.is_none() => continue,
+            'x' | 'X' => true,
+            '.' | '-' => false,
+            _ => return Err(Error::InvalidSymbol { symbol, position }),
+        };
+        let len = count.take().unwrap_or(1);
+        check_input_steps(pattern.len().saturating_add(len))?;
+        pattern.extend(core::iter::repeat_n(state, len));
+    }
+    match count {
+        None => Ok(pattern),
+        Some(_) => Err(Error::InvalidParameter {
+            name: "run notation",
+            reason: "count without a step symbol at the end",
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MAX_INPUT_STEPS;
+    use crate::euclidean;
+
+    #[test]
+    fn round_trips() {
+        for pulses in 0..=16 {
+            let pattern = euclidean(16, pulses, 3);
+            assert_eq!(from_runs(&to_runs(&pattern)), pattern);
+            assert_eq!(parse_runs(&runs_to_string(&pattern)), Ok(pattern));
+        }
+        assert!(to_runs(&[]).is_empty());
+        assert_eq!(from_runs(&[(true, 0), (false, 2)]), vec![false, false]);
+    }
+
+    #[test]
+    fn parse_errors_and_shorthand() {
+        assert_eq!(
+            parse_runs("x 12. X"),
+            Ok(from_runs(&[(true, 1), (false, 12), (true, 1)]))
+        );
+        assert_eq!(
+            parse_runs("3x 2o"),
+            Err(Error::InvalidSymbol {
+                symbol: 'o',
+                position: 4
+            })
+        );
+        assert_eq!(
+            parse_runs("3 x"),
+            Err(Error::InvalidSymbol {
+                symbol: ' ',
+                position: 1
+            })
+        );
+        assert!(parse_runs("3x 4").is_err());
+        assert_eq!(
+            parse_runs("1000000000000x"),
+            Err(Error::TooManySteps {
+                steps: 1_000_000_000_000,
+                max: MAX_INPUT_STEPS
+            })
+        );
+        assert_eq!(
+            parse_runs(&format!("{MAX_INPUT_STEPS}. x")),
+            Err(Error::TooManySteps {
+                steps: MAX_INPUT_STEPS + 1,
+                max: MAX_INPUT_STEPS
+            })
+        );
+    }
+}
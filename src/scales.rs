@@ -0,0 +1,360 @@
+//! Euclidean scales in the pitch domain.
+//!
+//! A maximally even distribution of `k` notes over the `n` steps of an
+//! `n`-EDO octave is a scale, just as `k` onsets over `n` steps is a rhythm:
+//! E(7,12) is the diatonic scale and E(5,12) the pentatonic.
+//!
+//! Moment-of-symmetry (MOS) scales, built by stacking a generator interval,
+//! are the same structures seen from the other side: every MOS has exactly two
+//! step sizes, and its small steps fall among the large ones like the onsets
+//! of a Euclidean rhythm.
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use crate::try_euclidean;
+
+/// A set of pitch classes within an equal division of the octave.
+///
+/// Pitch classes are kept sorted and relative to the scale root, which is
+/// always pitch class 0.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::Scale;
+///
+/// // E(7,12) lands on the Aeolian mode of the diatonic scale...
+/// let diatonic = Scale::euclidean(12, 7);
+/// assert_eq!(diatonic.pitch_classes(), &[0, 2, 3, 5, 7, 8, 10]);
+/// assert_eq!(diatonic.intervals(), vec![2, 1, 2, 2, 1, 2, 2]);
+///
+/// // ...and its third mode is the major scale.
+/// assert_eq!(diatonic.mode(2).pitch_classes(), &[0, 2, 4, 5, 7, 9, 11]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Scale {
+    edo: u8,
+    pitch_classes: Vec<u8>,
+}
+
+impl Scale {
+    /// Builds the Euclidean scale with `notes` pitch classes in `edo`-EDO.
+    ///
+    /// # Panics
+    /// Panics if `edo == 0`, `notes == 0` or `notes > edo`.
+    pub fn euclidean(edo: u8, notes: u8) -> Self {
+        unwrap_or_panic(Self::try_euclidean(edo, notes))
+    }
+
+    /// Like [`euclidean`](Self::euclidean), but reports invalid parameters as
+    /// an error.
+    ///
+    /// # Errors
+    /// Fails if `edo == 0`, `notes == 0` or `notes > edo`.
+    pub fn try_euclidean(edo: u8, notes: u8) -> Result<Self> {
+        if notes == 0 {
+            return Err(Error::InvalidParameter {
+                name: "notes",
+                reason: "a scale needs at least one note",
+            });
+        }
+        Self::try_from_pattern(&try_euclidean(edo, notes, 0)?)
+    }
+
+    /// Interprets a step pattern as a pitch-class set, one step per EDO step.
+    ///
+    /// The scale is rooted on the first pulse, so a pattern starting with rests
+    /// yields the same scale as its rotation that starts on a pulse.
+    ///
+    /// # Panics
+    /// Panics if the pattern is longer than 255 steps or has no pulses.
+    pub fn from_pattern(pattern: &[bool]) -> Self {
+        unwrap_or_panic(Self::try_from_pattern(pattern))
+    }
+
+    /// Like [`from_pattern`](Self::from_pattern), but reports unusable
+    /// patterns as an error.
+    ///
+    /// # Errors
+    /// Returns [`Error::TooManySteps`] for patterns longer than 255 steps and
+    /// [`Error::InvalidParameter`] for patterns without pulses.
+    pub fn try_from_pattern(pattern: &[bool]) -> Result<Self> {
+        let edo = u8::try_from(pattern.len()).map_err(|_| Error::TooManySteps {
+            steps: pattern.len(),
+            max: usize::from(u8::MAX),
+        })?;
+        let first = pattern
+            .iter()
+            .position(|&p| p)
+            .ok_or(Error::InvalidParameter {
+                name: "pattern",
+                reason: "a scale needs at least one pulse",
+            })?;
+        let pitch_classes = (0..pattern.len())
+            .filter(|&i| pattern[(i + first) % pattern.len()])
+            .map(|i| i as u8)
+            .collect();
+        Ok(Self { edo, pitch_classes })
+    }
+
+    /// Builds a moment-of-symmetry style scale by stacking `generator` steps
+    /// `size` times within `edo`-EDO (reduced to the octave).
+    ///
+    /// Not every size gives a true MOS; check with [`is_mos`](Self::is_mos)
+    /// or pick a size from [`mos_sizes`](Self::mos_sizes).
+    ///
+    /// # Panics
+    /// Panics if `edo == 0`, `size == 0`, or the generator repeats a pitch
+    /// class before `size` notes are reached.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::Scale;
+    ///
+    /// // Stacked fifths in 12-EDO: the Lydian mode.
+    /// let lydian = Scale::mos(12, 7, 7);
+    /// assert_eq!(lydian.pitch_classes(), &[0, 2, 4, 6, 7, 9, 11]);
+    ///
+    /// // Porcupine[7] in 22-EDO, generated by a 3-step "neutral second".
+    /// let porcupine = Scale::mos(22, 3, 7);
+    /// assert!(porcupine.is_mos());
+    /// assert_eq!(porcupine.step_sizes(), vec![3, 4]);
+    /// ```
+    pub fn mos(edo: u8, generator: u8, size: u8) -> Self {
+        unwrap_or_panic(Self::try_mos(edo, generator, size))
+    }
+
+    /// Like [`mos`](Self::mos), but reports invalid parameters as an error.
+    ///
+    /// # Errors
+    /// Fails if `edo == 0`, `size == 0`, or the generator repeats a pitch
+    /// class before `size` notes are reached.
+    pub fn try_mos(edo: u8, generator: u8, size: u8) -> Result<Self> {
+        if edo == 0 {
+            return Err(Error::ZeroSteps);
+        }
+        if size == 0 {
+            return Err(Error::InvalidParameter {
+                name: "size",
+                reason: "a scale needs at least one note",
+            });
+        }
+        let edo16 = u16::from(edo);
+        let mut pitch_classes: Vec<u8> = (0..u16::from(size))
+            .map(|k| (k * u16::from(generator) % edo16) as u8)
+            .collect();
+        pitch_classes.sort_unstable();
+        pitch_classes.dedup();
+        if pitch_classes.len() != usize::from(size) {
+            return Err(Error::InvalidParameter {
+                name: "generator",
+                reason: "repeats a pitch class before reaching the scale size",
+            });
+        }
+        Ok(Self { edo, pitch_classes })
+    }
+
+    /// Scale sizes below `edo` for which stacking `generator` yields a scale
+    /// with exactly two step sizes.
+    pub fn mos_sizes(edo: u8, generator: u8) -> Vec<u8> {
+        let edo16 = u16::from(edo);
+        let mut sizes = Vec::new();
+        let mut pitch_classes: Vec<u8> = Vec::new();
+        for k in 0..edo16 {
+            let pc = (k * u16::from(generator) % edo16) as u8;
+            if pitch_classes.contains(&pc) {
+                break;
+            }
+            pitch_classes.push(pc);
+            let mut sorted = pitch_classes.clone();
+            sorted.sort_unstable();
+            let scale = Self {
+                edo,
+                pitch_classes: sorted,
+            };
+            if scale.step_sizes().len() == 2 {
+                sizes.push(pitch_classes.len() as u8);
+            }
+        }
+        sizes
+    }
+
+    /// Distinct step sizes of the scale, smallest first.
+    pub fn step_sizes(&self) -> Vec<u8> {
+        let mut sizes = self.intervals();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    /// Returns `true` if the scale has exactly two step sizes, the defining
+    /// property of a moment-of-symmetry scale.
+    pub fn is_mos(&self) -> bool {
+        self.step_sizes().len() == 2
+    }
+
+    /// Number of equal divisions of the octave.
+    pub fn edo(&self) -> u8 {
+        self.edo
+    }
+
+    /// Sorted pitch classes, starting with the root (0).
+    pub fn pitch_classes(&self) -> &[u8] {
+        &self.pitch_classes
+    }
+
+    /// Number of notes per octave.
+    pub fn len(&self) -> usize {
+        self.pitch_classes.len()
+    }
+
+    /// Always `false`; scales have at least one note.
+    pub fn is_empty(&self) -> bool {
+        self.pitch_classes.is_empty()
+    }
+
+    /// Steps between successive degrees, including the step from the last
+    /// degree back up to the octave. Sums to [`edo`](Self::edo).
+    pub fn intervals(&self) -> Vec<u8> {
+        let pcs = &self.pitch_classes;
+        (0..pcs.len())
+            .map(|i| {
+                let next = pcs
+                    .get(i + 1)
+                    .copied()
+                    .map_or(u16::from(self.edo), u16::from);
+                (next - u16::from(pcs[i])) as u8
+            })
+            .collect()
+    }
+
+    /// The scale re-rooted on `degree` (0-based, wraps).
+    pub fn mode(&self, degree: usize) -> Self {
+        let degree = degree % self.len();
+        let root = self.pitch_classes[degree];
+        let mut pitch_classes: Vec<u8> = self
+            .pitch_classes
+            .iter()
+            .map(|&pc| {
+                ((u16::from(pc) + u16::from(self.edo) - u16::from(root)) % u16::from(self.edo))
+                    as u8
+            })
+            .collect();
+        pitch_classes.sort_unstable();
+        Self {
+            edo: self.edo,
+            pitch_classes,
+        }
+    }
+
+    /// Returns `true` if the pitch class (taken modulo the EDO) is in the scale.
+    pub fn contains(&self, pitch_class: i32) -> bool {
+        let pc = pitch_class.rem_euclid(i32::from(self.edo)) as u8;
+        self.pitch_classes.binary_search(&pc).is_ok()
+    }
+
+    /// Maps a scale degree to an absolute step above the root, continuing into
+    /// higher and lower octaves. Degree 0 is the root; negative degrees descend.
+    ///
+    /// ```
+    /// use euclidean_rhythm::Scale;
+    ///
+    /// let major = Scale::euclidean(12, 7).mode(2);
+    /// assert_eq!(major.degree_to_step(7), 12);
+    /// assert_eq!(major.degree_to_step(-1), -1);
+    /// ```
+    pub fn degree_to_step(&self, degree: i32) -> i32 {
+        let len = self.len() as i32;
+        let octave = degree.div_euclid(len);
+        let index = degree.rem_euclid(len) as usize;
+        octave * i32::from(self.edo) + i32::from(self.pitch_classes[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn pentatonic_and_diatonic() {
+        let pentatonic = Scale::euclidean(12, 5);
+        assert_eq!(pentatonic.pitch_classes(), &[0, 3, 5, 8, 10]);
+        assert_eq!(
+            pentatonic
+                .intervals()
+                .iter()
+                .map(|&i| u32::from(i))
+                .sum::<u32>(),
+            12
+        );
+        // Major pentatonic is one of its modes.
+        assert_eq!(pentatonic.mode(1).pitch_classes(), &[0, 2, 5, 7, 9]);
+        assert_eq!(pentatonic.mode(3).pitch_classes(), &[0, 2, 4, 7, 9]);
+    }
+
+    #[test]
+    fn microtonal_edo() {
+        let scale = Scale::euclidean(19, 8);
+        assert_eq!(scale.len(), 8);
+        assert_eq!(scale.edo(), 19);
+        let mut sizes = scale.intervals();
+        sizes.sort_unstable();
+        sizes.dedup();
+        // Maximally even: only two step sizes.
+        assert_eq!(sizes, vec![2, 3]);
+    }
+
+    #[test]
+    fn from_pattern_roots_on_first_pulse() {
+        let rotated = euclidean(12, 7, 1);
+        let scale = Scale::from_pattern(&rotated);
+        assert_eq!(scale.pitch_classes()[0], 0);
+        assert!(scale.contains(12 + scale.pitch_classes()[1] as i32));
+        assert_eq!(scale.pitch_classes(), &[0, 1, 3, 5, 6, 8, 10]);
+        assert!(!scale.contains(2));
+        assert_eq!(scale.degree_to_step(-7), -12);
+    }
+
+    #[test]
+    fn mos_scales() {
+        assert!(Scale::mos(12, 7, 5).is_mos());
+        assert!(!Scale::mos(12, 7, 6).is_mos());
+        assert_eq!(Scale::mos_sizes(12, 7), vec![2, 3, 5, 7, 8, 9, 10, 11]);
+        // Meantone[7] in 31-EDO: the two small steps are spread like E(2,7).
+        let mos = Scale::mos(31, 18, 7);
+        assert_eq!(mos.step_sizes(), vec![3, 5]);
+        let small: Vec<bool> = mos.intervals().iter().map(|&s| s == 3).collect();
+        assert!((0..7).any(|r| euclidean(7, 2, r) == small));
+    }
+
+    #[test]
+    #[should_panic]
+    fn mos_generator_collision() {
+        let _ = Scale::mos(12, 4, 4);
+    }
+
+    #[test]
+    fn try_constructors_report_errors() {
+        assert_eq!(Scale::try_euclidean(12, 7), Ok(Scale::euclidean(12, 7)));
+        assert_eq!(
+            Scale::try_euclidean(5, 7),
+            Err(Error::PulsesExceedSteps {
+                pulses: 7,
+                steps: 5
+            })
+        );
+        assert!(matches!(
+            Scale::try_from_pattern(&[false; 4]),
+            Err(Error::InvalidParameter {
+                name: "pattern",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Scale::try_mos(12, 4, 4),
+            Err(Error::InvalidParameter {
+                name: "generator",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+//! Lookahead scheduling of sequencer events.
+//!
+//! Audio and MIDI output should not fire notes the moment a step comes due:
+//! the polling thread wakes up late by a varying amount, and that jitter is
+//! audible. The usual cure is to schedule ahead, handing the output each
+//! note with a timestamp a little before it has to sound. [`EventScheduler`]
+//! does that bookkeeping for a [`Sequencer`]: poll it regularly and it
+//! returns every event due within the lookahead window, each stamped with
+//! the exact time (on a [`ClockSource`]) and beat it belongs to.
+
+use crate::sequencer::{Sequencer, StepEvent};
+use std::time::Instant;
+
+/// A monotonic time source, in seconds.
+///
+/// Implemented for [`SystemClock`] and for any `Fn() -> f64` closure, e.g.
+/// one reading an audio device's clock.
+pub trait ClockSource {
+    /// The current time in seconds. Must never go backwards.
+    fn now(&self) -> f64;
+}
+
+impl<F: Fn() -> f64> ClockSource for F {
+    fn now(&self) -> f64 {
+        self()
+    }
+}
+
+/// Seconds since the clock was created, from [`Instant`].
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    /// Starts a clock at 0.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClockSource for SystemClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// An event with the time it should sound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduledEvent {
+    /// When the event should sound, on the scheduler's clock, in seconds.
+    pub time: f64,
+    /// Where the event falls, in beats since the sequencer's step 0.
+    pub beat: f64,
+    /// The event itself.
+    pub event: StepEvent,
+}
+
+/// Runs a [`Sequencer`] ahead of a clock.
+///
+/// The first [`poll`](Self::poll) puts the sequencer's current step at the
+/// current time; after that steps follow each other at the sequencer's tempo,
+/// so tempo changes take effect from the next step not yet scheduled. Odd
+/// steps are delayed by the sequencer's [swing](Sequencer::set_swing). Steps
+/// that are already due when polled (because polling stalled) are still
+/// returned, with their original times.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{EventScheduler, Sequencer, SequencerTrack};
+/// use std::cell::Cell;
+///
+/// let now = Cell::new(0.0);
+/// let seq = Sequencer::new(120.0).with_track(SequencerTrack::new("kick", 4, 4, 0));
+/// let mut scheduler = EventScheduler::new(seq, || now.get()).with_lookahead(0.3);
+///
+/// // 120 BPM sixteenths are 125 ms apart: steps at 0, 0.125 and 0.25 s.
+/// let times: Vec<f64> = scheduler.poll().iter().map(|e| e.time).collect();
+/// assert_eq!(times, [0.0, 0.125, 0.25]);
+///
+/// now.set(0.1);
+/// let next = scheduler.poll();
+/// assert_eq!((next[0].time, next[0].beat), (0.375, 0.75));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventScheduler<C> {
+    sequencer: Sequencer,
+    clock: C,
+    lookahead: f64,
+    next_time: Option<f64>,
+}
+
+impl<C: ClockSource> EventScheduler<C> {
+    /// Schedules `sequencer` against `clock` with a lookahead of 100 ms.
+    pub fn new(sequencer: Sequencer, clock: C) -> Self {
+        Self {
+            sequencer,
+            clock,
+            lookahead: 0.1,
+            next_time: None,
+        }
+    }
+
+    /// Sets how far ahead of the clock events are scheduled, in seconds
+    /// (negative values count as 0). Poll more often than this.
+    #[must_use]
+    pub fn with_lookahead(mut self, seconds: f64) -> Self {
+        self.lookahead = seconds.max(0.0);
+        self
+    }
+
+    /// The lookahead window in seconds.
+    pub fn lookahead(&self) -> f64 {
+        self.lookahead
+    }
+
+    /// The sequencer being scheduled.
+    pub fn sequencer(&self) -> &Sequencer {
+        &self.sequencer
+    }
+
+    /// Mutable access to the sequencer, e.g. to change the tempo or tracks.
+    pub fn sequencer_mut(&mut self) -> &mut Sequencer {
+        &mut self.sequencer
+    }
+
+    /// The time the next unscheduled step will sound, or `None` before the
+    /// first poll.
+    pub fn next_time(&self) -> Option<f64> {
+        self.next_time
+    }
+
+    /// Returns the events of every step that sounds before the clock time
+    /// plus the lookahead and has not been returned yet, in order. Nothing
+    /// is scheduled while the tempo is not positive.
+    pub fn poll(&mut self) -> Vec<ScheduledEvent> {
+        let now = self.clock.now();
+        let horizon = now + self.lookahead;
+        let mut time = *self.next_time.get_or_insert(now);
+        let mut scheduled = Vec::new();
+        while time <= horizon {
+            let duration = self.sequencer.step_duration();
+            if !duration.is_finite() || duration <= 0.0 {
+                break;
+            }
+            let beat =
+                self.sequencer.position() as f64 / f64::from(self.sequencer.steps_per_beat());
+            scheduled.extend(
+                self.sequencer
+                    .tick()
+                    .into_iter()
+                    .map(|event| ScheduledEvent { time, beat, event }),
+            );
+            let played_odd = (self.sequencer.position() - 1) % 2 == 1;
+            let delay = self.sequencer.swing_delay();
+            time += duration * if played_odd { 1.0 - delay } else { 1.0 + delay };
+        }
+        self.next_time = Some(time);
+        scheduled
+    }
+
+    /// Rewinds the sequencer; the next poll starts again from step 0 at the
+    /// clock's current time.
+    pub fn reset(&mut self) {
+        self.sequencer.reset();
+        self.next_time = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SequencerTrack;
+    use std::cell::Cell;
+
+    #[test]
+    fn schedules_each_step_once_with_exact_times() {
+        let now = Cell::new(10.0);
+        let seq = Sequencer::new(60.0).with_track(SequencerTrack::new("k", 4, 2, 0));
+        let mut scheduler = EventScheduler::new(seq, || now.get());
+        // 60 BPM sixteenths: 0.25 s per step, pulses on even steps.
+        let first = scheduler.poll();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].time, 10.0);
+        assert!(scheduler.poll().is_empty());
+
+        // A stalled poll still gets every step, at its own time.
+        now.set(11.0);
+        let late: Vec<(f64, usize)> = scheduler
+            .poll()
+            .iter()
+            .map(|e| (e.time, e.event.step))
+            .collect();
+        assert_eq!(late, [(10.5, 2), (11.0, 0)]);
+        assert_eq!(scheduler.next_time(), Some(11.25));
+
+        scheduler.sequencer_mut().set_bpm(0.0);
+        now.set(20.0);
+        assert!(scheduler.poll().is_empty());
+        scheduler.reset();
+        scheduler.sequencer_mut().set_bpm(60.0);
+        assert_eq!(scheduler.poll()[0].time, 20.0);
+    }
+
+    #[test]
+    fn swing_delays_odd_steps() {
+        let now = Cell::new(0.0);
+        let mut seq = Sequencer::new(60.0).with_track(SequencerTrack::new("all", 4, 4, 0));
+        seq.set_swing(75);
+        let mut scheduler = EventScheduler::new(seq, || now.get()).with_lookahead(1.0);
+        // 0.375 s for even steps, 0.125 s for odd ones, as with `advance`.
+        let times: Vec<f64> = scheduler.poll().iter().map(|e| e.time).collect();
+        assert_eq!(times, [0.0, 0.375, 0.5, 0.875, 1.0]);
+    }
+}
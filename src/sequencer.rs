@@ -0,0 +1,1096 @@
+//! A step sequencer that plays Euclidean tracks.
+//!
+//! The [`Sequencer`] owns a set of [`SequencerTrack`]s and turns time into
+//! [`StepEvent`]s. It does no I/O and has no clock of its own: drive it one step
+//! at a time with [`tick`](Sequencer::tick), or with elapsed wall/game time via
+//! [`advance`](Sequencer::advance) (e.g. from a game engine's frame delta).
+//! Tracks may have different lengths, giving polymetric loops.
+//!
+//! The crate has no game-engine integration of its own (there is no `bevy`
+//! feature). In an ECS, keep the sequencer in a resource and advance it
+//! from a per-frame system, turning its events into the engine's own:
+//!
+//! ```text
+//! fn play(time: Res<Time>, mut seq: ResMut<Drums>, mut onsets: EventWriter<Onset>) {
+//!     for event in seq.0.advance(time.delta_secs_f64()) {
+//!         onsets.send(Onset(event));
+//!     }
+//! }
+//! ```
+//!
+//! To play along with other applications, feed the state of a shared clock
+//! (such as an Ableton Link session) to [`follow`](Sequencer::follow) every
+//! frame; the sequencer then takes its tempo, phase and transport from it.
+//!
+//! Individual steps can carry a [`TrigCondition`], evaluated against the
+//! track's cycle count and the sequencer's fill flag each time they come up,
+//! and a [`ParamLock`] overriding the note, velocity, ratchet count or
+//! probability of that step alone. A whole probability field, such as one
+//! from a second Euclidean layer, can be laid over a track at once with
+//! [`set_probability_mask`](SequencerTrack::set_probability_mask).
+//!
+//! A track can also be given a [`RotationDrift`]: at each of its cycle
+//! boundaries the rotation may wander one step either way, within bounds,
+//! for slowly evolving loops. Every change is reported as a [`DriftEvent`].
+//!
+//! For performance, tracks can be muted, soloed or put into fill on their
+//! own. These changes wait for the next quantize boundary (every step by
+//! default; see [`set_quantize`](Sequencer::set_quantize)) so they land in
+//! time.
+
+use crate::error::{Result, unwrap_or_panic};
+use crate::generator::{Algorithm, RhythmGenerator};
+use crate::rng::Rng;
+use crate::role::TrackRole;
+use crate::trig::{ParamLock, TrigCondition};
+use crate::try_euclidean;
+use std::collections::BTreeMap;
+
+/// One voice of the sequencer: Euclidean parameters plus output settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencerTrack {
+    /// Display name of the track.
+    pub name: String,
+    /// MIDI note (or sample slot) triggered on pulses.
+    pub note: u8,
+    /// Velocity of triggered notes, 1-127.
+    pub velocity: u8,
+    steps: u8,
+    pulses: u8,
+    rotation: u8,
+    pattern: Vec<bool>,
+    generator: Algorithm,
+    conditions: BTreeMap<usize, TrigCondition>,
+    locks: BTreeMap<usize, ParamLock>,
+    state: TrackState,
+    requested: TrackState,
+    drift: Option<RotationDrift>,
+    drift_offset: i16,
+}
+
+/// Random walk of a track's rotation, applied at every cycle boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RotationDrift {
+    /// Chance, in percent, that the rotation moves one step (earlier or
+    /// later with equal odds) when the cycle wraps.
+    pub percent: u8,
+    /// The furthest the rotation may wander from the track's own rotation,
+    /// in steps either way.
+    pub range: u8,
+}
+
+/// Reports that a track's rotation drifted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriftEvent {
+    /// Index of the track in the sequencer.
+    pub track: usize,
+    /// The cycle that starts with the new rotation.
+    pub cycle: u64,
+    /// Global step counter at the boundary.
+    pub tick: u64,
+    /// Drift from the track's rotation, in steps; positive is earlier, like
+    /// rotation itself.
+    pub offset: i16,
+}
+
+/// Performance switches of a track.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct TrackState {
+    muted: bool,
+    soloed: bool,
+    fill: bool,
+}
+
+impl SequencerTrack {
+    /// Creates a track playing E(`pulses`, `steps`) rotated by `rotation`,
+    /// triggering note 36 at velocity 100.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `pulses > steps`.
+    pub fn new(name: impl Into<String>, steps: u8, pulses: u8, rotation: u8) -> Self {
+        unwrap_or_panic(Self::try_new(name, steps, pulses, rotation))
+    }
+
+    /// Like [`new`](Self::new), but reports invalid parameters as an error.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `pulses > steps`.
+    pub fn try_new(name: impl Into<String>, steps: u8, pulses: u8, rotation: u8) -> Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            note: 36,
+            velocity: 100,
+            steps,
+            pulses,
+            rotation,
+            pattern: try_euclidean(steps, pulses, rotation)?,
+            generator: Algorithm::Bjorklund,
+            conditions: BTreeMap::new(),
+            locks: BTreeMap::new(),
+            state: TrackState::default(),
+            requested: TrackState::default(),
+            drift: None,
+            drift_offset: 0,
+        })
+    }
+
+    /// Creates a track with the defaults of `role`: its name, pattern, note
+    /// and velocity.
+    pub fn for_role(role: TrackRole) -> Self {
+        Self::new(role.name(), role.steps(), role.pulses(), role.rotation())
+            .with_note(role.note())
+            .with_velocity(role.velocity())
+    }
+
+    /// Sets the triggered note.
+    #[must_use]
+    pub fn with_note(mut self, note: u8) -> Self {
+        self.note = note;
+        self
+    }
+
+    /// Sets the velocity of triggered notes.
+    #[must_use]
+    pub fn with_velocity(mut self, velocity: u8) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// Attaches a play condition to `step`, for builder-style construction.
+    #[must_use]
+    pub fn with_condition(mut self, step: usize, condition: TrigCondition) -> Self {
+        self.set_condition(step, condition);
+        self
+    }
+
+    /// Sets the play condition of `step`; [`TrigCondition::Always`] clears it.
+    ///
+    /// Conditions are kept when the parameters change and apply to whatever
+    /// pulse lands on that step; steps past the pattern length are ignored.
+    pub fn set_condition(&mut self, step: usize, condition: TrigCondition) {
+        if condition == TrigCondition::Always {
+            self.conditions.remove(&step);
+        } else {
+            self.conditions.insert(step, condition);
+        }
+    }
+
+    /// The play condition of `step`.
+    pub fn condition(&self, step: usize) -> TrigCondition {
+        self.conditions.get(&step).copied().unwrap_or_default()
+    }
+
+    /// The steps that have a play condition, in step order.
+    pub fn conditions(&self) -> impl Iterator<Item = (usize, TrigCondition)> + '_ {
+        self.conditions
+            .iter()
+            .map(|(&step, &condition)| (step, condition))
+    }
+
+    /// Attaches a parameter lock to `step`, for builder-style construction.
+    #[must_use]
+    pub fn with_lock(mut self, step: usize, lock: ParamLock) -> Self {
+        self.set_lock(step, lock);
+        self
+    }
+
+    /// Sets the parameter lock of `step`; an empty lock clears it. Like
+    /// conditions, locks stay on their step when the parameters change.
+    pub fn set_lock(&mut self, step: usize, lock: ParamLock) {
+        if lock.is_empty() {
+            self.locks.remove(&step);
+        } else {
+            self.locks.insert(step, lock);
+        }
+    }
+
+    /// The parameter lock of `step`, if any.
+    pub fn lock(&self, step: usize) -> Option<&ParamLock> {
+        self.locks.get(&step)
+    }
+
+    /// The steps that have a parameter lock, in step order.
+    pub fn locks(&self) -> impl Iterator<Item = (usize, &ParamLock)> {
+        self.locks.iter().map(|(&step, lock)| (step, lock))
+    }
+
+    /// Sets a probability field, for builder-style construction.
+    #[must_use]
+    pub fn with_probability_mask(mut self, mask: &[f32]) -> Self {
+        self.set_probability_mask(mask);
+        self
+    }
+
+    /// Locks the probability of step `i` to `mask[i]` (0.0-1.0, rounded to
+    /// whole percent), e.g. a field from `probability_mask`. Certain steps
+    /// lose their probability lock; other locked parameters are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::SequencerTrack;
+    ///
+    /// let track = SequencerTrack::new("hat", 4, 4, 0).with_probability_mask(&[1.0, 0.3, 0.6, 0.3]);
+    /// assert_eq!(track.lock(0), None);
+    /// assert_eq!(track.lock(1).unwrap().probability, Some(30));
+    /// ```
+    pub fn set_probability_mask(&mut self, mask: &[f32]) {
+        for (step, &p) in mask.iter().enumerate() {
+            let p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+            let percent = (p * 100.0).round() as u8;
+            let mut lock = self.lock(step).copied().unwrap_or_default();
+            lock.probability = (percent < 100).then_some(percent);
+            self.set_lock(step, lock);
+        }
+    }
+
+    /// Whether the track is muted. A muted track plays nothing, even when
+    /// soloed.
+    pub fn muted(&self) -> bool {
+        self.state.muted
+    }
+
+    /// Mutes or unmutes the track from the next quantize boundary.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.requested.muted = muted;
+    }
+
+    /// Whether the track is soloed. While any track is soloed, only soloed
+    /// tracks play.
+    pub fn soloed(&self) -> bool {
+        self.state.soloed
+    }
+
+    /// Solos or unsolos the track from the next quantize boundary.
+    pub fn set_soloed(&mut self, soloed: bool) {
+        self.requested.soloed = soloed;
+    }
+
+    /// Whether the track is in fill. Its [`TrigCondition::Fill`] and
+    /// [`TrigCondition::NotFill`] steps then behave as if the sequencer's
+    /// fill were on.
+    pub fn fill(&self) -> bool {
+        self.state.fill
+    }
+
+    /// Puts the track into fill, or takes it out, from the next quantize
+    /// boundary; hold it for a one-track fill.
+    pub fn set_fill(&mut self, fill: bool) {
+        self.requested.fill = fill;
+    }
+
+    /// The mute, solo and fill switches as last set, including changes still
+    /// waiting for the next quantize boundary.
+    pub(crate) fn requested_switches(&self) -> (bool, bool, bool) {
+        let TrackState {
+            muted,
+            soloed,
+            fill,
+        } = self.requested;
+        (muted, soloed, fill)
+    }
+
+    /// Lets the rotation drift, for builder-style construction.
+    #[must_use]
+    pub fn with_drift(mut self, drift: RotationDrift) -> Self {
+        self.set_drift(Some(drift));
+        self
+    }
+
+    /// Sets or removes the rotation drift. Removing it returns the pattern to
+    /// the track's own rotation; a narrower range pulls the drift back inside
+    /// it.
+    pub fn set_drift(&mut self, drift: Option<RotationDrift>) {
+        self.drift = drift;
+        let range = drift.map_or(0, |drift| i16::from(drift.range));
+        self.shift_drift(self.drift_offset.clamp(-range, range));
+    }
+
+    /// The rotation drift, if any.
+    pub fn drift(&self) -> Option<RotationDrift> {
+        self.drift
+    }
+
+    /// How far the rotation has drifted from [`params`](Self::params), in
+    /// steps; positive is earlier.
+    pub fn drift_offset(&self) -> i16 {
+        self.drift_offset
+    }
+
+    /// Returns the pattern to the track's own rotation, keeping the drift
+    /// setting.
+    pub fn reset_drift(&mut self) {
+        self.shift_drift(0);
+    }
+
+    /// Moves the pattern to drift `offset` from the track's rotation.
+    fn shift_drift(&mut self, offset: i16) {
+        let len = self.pattern.len() as i32;
+        let by = (i32::from(offset) - i32::from(self.drift_offset)).rem_euclid(len);
+        self.pattern.rotate_left(by as usize);
+        self.drift_offset = offset;
+    }
+
+    /// Returns `(steps, pulses, rotation)`.
+    pub fn params(&self) -> (u8, u8, u8) {
+        (self.steps, self.pulses, self.rotation)
+    }
+
+    /// Replaces the Euclidean parameters and regenerates the pattern with the
+    /// track's [`generator`](Self::generator). Any rotation drift starts
+    /// again from the new rotation.
+    ///
+    /// # Panics
+    /// Panics if `steps == 0` or `pulses > steps`.
+    pub fn set_params(&mut self, steps: u8, pulses: u8, rotation: u8) {
+        unwrap_or_panic(self.try_set_params(steps, pulses, rotation));
+    }
+
+    /// Like [`set_params`](Self::set_params), but reports invalid parameters
+    /// as an error and leaves the track unchanged.
+    ///
+    /// # Errors
+    /// Fails if `steps == 0` or `pulses > steps`.
+    pub fn try_set_params(&mut self, steps: u8, pulses: u8, rotation: u8) -> Result<()> {
+        self.pattern = self
+            .generator
+            .try_generate(steps, pulses, rotation)?
+            .into_vec();
+        self.steps = steps;
+        self.pulses = pulses;
+        self.rotation = rotation;
+        self.drift_offset = 0;
+        Ok(())
+    }
+
+    /// Sets the algorithm, for builder-style construction.
+    #[must_use]
+    pub fn with_generator(mut self, generator: Algorithm) -> Self {
+        self.set_generator(generator);
+        self
+    }
+
+    /// Switches to another of the crate's algorithms and regenerates the
+    /// pattern from the current parameters, keeping conditions and locks.
+    /// The choice is kept when the parameters change and is saved with the
+    /// session. Any rotation drift starts again from the track's own
+    /// rotation.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::SequencerTrack;
+    /// use euclidean_rhythm::generator::Algorithm;
+    ///
+    /// let mut track = SequencerTrack::new("roll", 8, 3, 0);
+    /// track.set_generator(Algorithm::Clustered);
+    /// assert_eq!(track.pattern(), [true, true, true, false, false, false, false, false]);
+    /// track.set_params(8, 2, 0);
+    /// assert_eq!(track.pattern(), [true, true, false, false, false, false, false, false]);
+    /// ```
+    pub fn set_generator(&mut self, generator: Algorithm) {
+        self.generator = generator;
+        self.regenerate(&generator);
+    }
+
+    /// The algorithm that generates the pattern (Bjorklund's by default).
+    pub fn generator(&self) -> Algorithm {
+        self.generator
+    }
+
+    /// Regenerates the pattern from the current parameters with any
+    /// generator, including user-defined ones, keeping conditions and
+    /// locks. Unlike [`set_generator`](Self::set_generator) the choice is
+    /// not stored: changing the parameters goes back to the track's
+    /// [`generator`](Self::generator). Any rotation drift starts again from
+    /// the track's own rotation.
+    pub fn regenerate(&mut self, generator: &dyn RhythmGenerator) {
+        // The parameters were validated when they were set.
+        self.pattern = generator
+            .generate(self.steps, self.pulses, self.rotation)
+            .into_vec();
+        self.drift_offset = 0;
+    }
+
+    /// The generated pattern.
+    pub fn pattern(&self) -> &[bool] {
+        &self.pattern
+    }
+}
+
+/// A pulse fired by the sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepEvent {
+    /// Index of the track in the sequencer.
+    pub track: usize,
+    /// Step within the track's pattern.
+    pub step: usize,
+    /// How many times the track's pattern has completed before this step.
+    pub cycle: u64,
+    /// Global step counter since the last reset.
+    pub tick: u64,
+    /// Note to trigger.
+    pub note: u8,
+    /// Velocity to trigger with.
+    pub velocity: u8,
+    /// Number of evenly spaced hits to play within the step; 1 for a
+    /// single hit.
+    pub ratchet: u8,
+    /// Whether fill was on for the step, on the sequencer or the track.
+    pub fill: bool,
+}
+
+/// Receives step events as the sequencer plays them.
+///
+/// Implemented for any `FnMut(&StepEvent)` closure, and by output sinks such
+/// as the OSC sender.
+pub trait StepListener {
+    /// Called once per fired step event, in order.
+    fn on_step(&mut self, event: &StepEvent);
+
+    /// Called when a track's rotation drifts. Does nothing by default.
+    fn on_drift(&mut self, _event: &DriftEvent) {}
+}
+
+impl<F: FnMut(&StepEvent)> StepListener for F {
+    fn on_step(&mut self, event: &StepEvent) {
+        self(event)
+    }
+}
+
+/// The state of an external shared clock at one moment, e.g. read from an
+/// Ableton Link session.
+///
+/// No Link binding is bundled (there is no `link` feature); read the
+/// session state with whichever binding the application uses. With
+/// `rusty_link`, for example:
+///
+/// ```text
+/// link.capture_app_session_state(&mut state);
+/// let clock = ClockSnapshot {
+///     tempo: state.tempo(),
+///     beat: state.beat_at_time(link.clock_micros(), 4.0),
+///     playing: state.is_playing(),
+/// };
+/// ```
+///
+/// Enabling Link's start/stop sync on the binding makes `playing` follow
+/// the other peers' transport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockSnapshot {
+    /// Session tempo in beats per minute.
+    pub tempo: f64,
+    /// Beat position on the session timeline.
+    pub beat: f64,
+    /// Whether the session transport is running.
+    pub playing: bool,
+}
+
+/// The fastest tempo the sequencer plays, as in Ableton Link; faster tempos
+/// are clamped to it.
+pub const MAX_BPM: f64 = 999.0;
+
+/// Steps the sequencer will play to catch up with an external clock before
+/// it jumps straight to the clock's position instead.
+const MAX_CATCH_UP_STEPS: u64 = 4;
+
+/// The furthest step [`Sequencer::follow`] moves to, the largest count a
+/// beat position still resolves to whole steps; later beats are clamped to
+/// it.
+const MAX_FOLLOW_STEP: u64 = 1 << 53;
+
+/// Mixed into the seed so drift rolls are independent of probability rolls.
+const DRIFT_STREAM: u64 = 0xD81F_7A5E_0000_0000;
+
+/// Plays a set of tracks against a shared step clock.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{Sequencer, SequencerTrack};
+///
+/// let mut seq = Sequencer::new(120.0)
+///     .with_track(SequencerTrack::new("kick", 16, 4, 0).with_note(36))
+///     .with_track(SequencerTrack::new("hat", 16, 11, 2).with_note(42));
+///
+/// // Feed frame deltas; events come out on step boundaries.
+/// let mut kicks = 0;
+/// for _ in 0..60 {
+///     for event in seq.advance(1.0 / 60.0) {
+///         if event.note == 36 {
+///             kicks += 1;
+///         }
+///     }
+/// }
+/// // One second at 120 BPM sixteenths is 8 steps: kicks on 0 and 4.
+/// assert_eq!(kicks, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequencer {
+    tracks: Vec<SequencerTrack>,
+    bpm: f64,
+    steps_per_beat: u32,
+    position: u64,
+    until_next: f64,
+    fill: bool,
+    seed: u64,
+    quantize: u64,
+    swing: u8,
+    drift_events: Vec<DriftEvent>,
+}
+
+impl Sequencer {
+    /// Creates an empty sequencer at `bpm` with sixteenth-note steps. See
+    /// [`set_bpm`](Self::set_bpm) for the accepted tempos.
+    pub fn new(bpm: f64) -> Self {
+        let mut seq = Self {
+            tracks: Vec::new(),
+            bpm: 0.0,
+            steps_per_beat: 4,
+            position: 0,
+            until_next: 0.0,
+            fill: false,
+            seed: 0,
+            quantize: 1,
+            swing: 50,
+            drift_events: Vec::new(),
+        };
+        seq.set_bpm(bpm);
+        seq
+    }
+
+    /// Sets the seed for [`TrigCondition::Probability`] and
+    /// [`RotationDrift`] rolls, which depend only on the seed, track and
+    /// tick, so a run can be replayed exactly.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The seed for probability and drift rolls.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Adds a track and returns the sequencer, for builder-style construction.
+    #[must_use]
+    pub fn with_track(mut self, track: SequencerTrack) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    /// Adds a track and returns its index.
+    pub fn add_track(&mut self, track: SequencerTrack) -> usize {
+        self.tracks.push(track);
+        self.tracks.len() - 1
+    }
+
+    /// The tracks in index order.
+    pub fn tracks(&self) -> &[SequencerTrack] {
+        &self.tracks
+    }
+
+    /// Mutable access to a track, e.g. to change its parameters while playing.
+    pub fn track_mut(&mut self, index: usize) -> Option<&mut SequencerTrack> {
+        self.tracks.get_mut(index)
+    }
+
+    /// Tempo in beats per minute.
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Changes the tempo; takes effect from the next step. Tempos above
+    /// [`MAX_BPM`] are clamped to it, and a tempo that is not positive (or
+    /// NaN) stops the clock.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = if bpm.is_nan() { 0.0 } else { bpm.min(MAX_BPM) };
+    }
+
+    /// Steps per beat (4 = sixteenth notes).
+    pub fn steps_per_beat(&self) -> u32 {
+        self.steps_per_beat
+    }
+
+    /// Changes the step resolution; takes effect from the next step.
+    pub fn set_steps_per_beat(&mut self, steps_per_beat: u32) {
+        self.steps_per_beat = steps_per_beat.max(1);
+    }
+
+    /// Duration of one step in seconds (infinite for a non-positive tempo).
+    pub fn step_duration(&self) -> f64 {
+        if self.bpm <= 0.0 {
+            return f64::INFINITY;
+        }
+        60.0 / self.bpm / f64::from(self.steps_per_beat)
+    }
+
+    /// Whether fill is active.
+    pub fn fill(&self) -> bool {
+        self.fill
+    }
+
+    /// Turns fill on or off, e.g. while a performer holds a fill button.
+    /// Takes effect from the next step.
+    pub fn set_fill(&mut self, fill: bool) {
+        self.fill = fill;
+    }
+
+    /// Steps between the boundaries where track mute, solo and fill changes
+    /// take effect.
+    pub fn quantize(&self) -> u64 {
+        self.quantize
+    }
+
+    /// Holds track mute, solo and fill changes until the position is a
+    /// multiple of `steps` (at least 1), e.g. 16 to switch on the bar with
+    /// sixteenth-note steps. The sequencer's own fill is not quantized.
+    pub fn set_quantize(&mut self, steps: u64) {
+        self.quantize = steps.max(1);
+    }
+
+    /// How much of each pair of steps the first one takes, in percent; 50
+    /// plays straight.
+    pub fn swing(&self) -> u8 {
+        self.swing
+    }
+
+    /// Sets the swing, clamped to 50-75 percent. Odd steps are delayed so
+    /// each even step lasts `percent` of its pair, e.g. 66 for a triplet
+    /// shuffle. Applies to [`advance`](Self::advance),
+    /// [`follow`](Self::follow) and [`EventScheduler`](crate::EventScheduler)
+    /// polling; [`tick`](Self::tick) has no timing.
+    pub fn set_swing(&mut self, percent: u8) {
+        self.swing = percent.clamp(50, 75);
+    }
+
+    /// How late odd steps play, as a fraction of a step.
+    pub(crate) fn swing_delay(&self) -> f64 {
+        f64::from(self.swing) / 50.0 - 1.0
+    }
+
+    /// Global step counter: the tick that the next call to
+    /// [`tick`](Self::tick) will play.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Rewinds to step 0 and returns every track to its own rotation; the
+    /// next [`advance`](Self::advance) fires step 0 at once.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.until_next = 0.0;
+        for track in &mut self.tracks {
+            track.reset_drift();
+        }
+    }
+
+    /// Removes and returns the rotation changes since the last call, in
+    /// order; they are kept until taken. [`advance_with`](Self::advance_with)
+    /// hands them to the listener (before that call's step events) instead.
+    pub fn take_drift_events(&mut self) -> Vec<DriftEvent> {
+        core::mem::take(&mut self.drift_events)
+    }
+
+    /// Plays the current step of every track and moves to the next one.
+    pub fn tick(&mut self) -> Vec<StepEvent> {
+        let tick = self.position;
+        if tick.is_multiple_of(self.quantize) {
+            for track in &mut self.tracks {
+                track.state = track.requested;
+            }
+        }
+        self.roll_drift(tick);
+        let solo = self.tracks.iter().any(|track| track.state.soloed);
+        let events = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, track)| {
+                let len = track.pattern.len() as u64;
+                let step = (tick % len) as usize;
+                let cycle = tick / len;
+                let lock = track.lock(step).copied().unwrap_or_default();
+                let mut rng = Rng::for_cycle(self.seed ^ index as u64, tick);
+                let fill = self.fill || track.state.fill;
+                let audible = !track.state.muted && (!solo || track.state.soloed);
+                let plays = audible
+                    && track.pattern[step]
+                    && track.condition(step).is_met(cycle, fill, || rng.next_f64())
+                    && lock.probability.is_none_or(|p| {
+                        TrigCondition::Probability(p).is_met(cycle, fill, || rng.next_f64())
+                    });
+                plays.then_some(StepEvent {
+                    track: index,
+                    step,
+                    cycle,
+                    tick,
+                    note: lock.note.unwrap_or(track.note),
+                    velocity: lock.velocity.unwrap_or(track.velocity),
+                    ratchet: lock.ratchet.unwrap_or(1).max(1),
+                    fill,
+                })
+            })
+            .collect();
+        self.position += 1;
+        events
+    }
+
+    /// Rolls the drift of every track starting a new cycle on `tick`.
+    fn roll_drift(&mut self, tick: u64) {
+        for (index, track) in self.tracks.iter_mut().enumerate() {
+            let len = track.pattern.len() as u64;
+            let Some(drift) = track.drift else { continue };
+            if tick == 0 || !tick.is_multiple_of(len) {
+                continue;
+            }
+            let mut rng = Rng::for_cycle(self.seed ^ DRIFT_STREAM ^ index as u64, tick);
+            if !TrigCondition::Probability(drift.percent).is_met(0, false, || rng.next_f64()) {
+                continue;
+            }
+            let step = if rng.chance(0.5) { 1 } else { -1 };
+            let range = i16::from(drift.range);
+            let offset = (track.drift_offset + step).clamp(-range, range);
+            if offset != track.drift_offset {
+                track.shift_drift(offset);
+                self.drift_events.push(DriftEvent {
+                    track: index,
+                    cycle: tick / len,
+                    tick,
+                    offset,
+                });
+            }
+        }
+    }
+
+    /// Advances the clock by `seconds` and returns the events of every step
+    /// boundary crossed, in order. The first call after creation or
+    /// [`reset`](Self::reset) fires step 0 immediately. Nothing plays while
+    /// the tempo is stopped, and a negative or non-finite `seconds` counts
+    /// as no time.
+    pub fn advance(&mut self, seconds: f64) -> Vec<StepEvent> {
+        let mut events = Vec::new();
+        if seconds.is_finite() {
+            self.until_next -= seconds.max(0.0);
+        }
+        while self.until_next <= 0.0 {
+            let duration = self.step_duration();
+            if !duration.is_finite() || duration <= 0.0 {
+                // Stopped: play the next step as soon as the tempo resumes.
+                self.until_next = 0.0;
+                break;
+            }
+            events.extend(self.tick());
+            let played_odd = (self.position - 1) % 2 == 1;
+            let delay = self.swing_delay();
+            let stretch = if played_odd { 1.0 - delay } else { 1.0 + delay };
+            self.until_next += duration * stretch;
+        }
+        events
+    }
+
+    /// Like [`advance`](Self::advance), but hands each event to `listener`
+    /// instead of returning them.
+    pub fn advance_with<L: StepListener + ?Sized>(&mut self, seconds: f64, listener: &mut L) {
+        let events = self.advance(seconds);
+        for event in self.take_drift_events() {
+            listener.on_drift(&event);
+        }
+        for event in events {
+            listener.on_step(&event);
+        }
+    }
+
+    /// Phase-locks to an external clock and returns the events of every step
+    /// the clock has reached since the previous call.
+    ///
+    /// The tempo is taken from the snapshot, and step `n` plays once the clock
+    /// reaches beat `n / steps_per_beat` (later for odd steps when swung), so
+    /// every peer sharing the beat grid hears the downbeats together. Nothing plays while the clock is stopped
+    /// or before beat 0. A clock that moves backwards (a restart) or far ahead
+    /// (a dropped frame) repositions the sequencer instead of replaying steps.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::{ClockSnapshot, Sequencer, SequencerTrack};
+    ///
+    /// let mut seq = Sequencer::new(120.0).with_track(SequencerTrack::new("k", 4, 4, 0));
+    /// let at = |beat| ClockSnapshot { tempo: 128.0, beat, playing: true };
+    ///
+    /// assert_eq!(seq.follow(at(0.0)).len(), 1); // step 0
+    /// assert_eq!(seq.follow(at(0.2)).len(), 0);
+    /// assert_eq!(seq.follow(at(0.5)).len(), 2); // steps 1 and 2
+    /// assert_eq!(seq.bpm(), 128.0);
+    /// ```
+    pub fn follow(&mut self, clock: ClockSnapshot) -> Vec<StepEvent> {
+        self.set_bpm(clock.tempo);
+        if !clock.playing || clock.beat.is_nan() || clock.beat < 0.0 {
+            return Vec::new();
+        }
+        let delay = self.swing_delay();
+        let at = (clock.beat * f64::from(self.steps_per_beat)).min(MAX_FOLLOW_STEP as f64);
+        let mut target = at.floor() as u64;
+        if target % 2 == 1 && at - (target as f64) < delay {
+            target -= 1;
+        }
+        // `position` is one past the last played step, so `target + 1` means "no news".
+        if target + 1 < self.position || target > self.position.saturating_add(MAX_CATCH_UP_STEPS) {
+            self.position = target;
+        }
+
+        let mut events = Vec::new();
+        while self.position <= target {
+            events.extend(self.tick());
+        }
+        // Keep time-driven advancing consistent with the clock's phase.
+        let next_step = self.position as f64 + if self.position % 2 == 1 { delay } else { 0.0 };
+        let next_beat = next_step / f64::from(self.steps_per_beat);
+        self.until_next = (next_beat - clock.beat) * 60.0 / self.bpm.max(f64::MIN_POSITIVE);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn tick_plays_polymetric_tracks() {
+        let mut seq = Sequencer::new(120.0)
+            .with_track(SequencerTrack::new("a", 3, 1, 0))
+            .with_track(SequencerTrack::new("b", 4, 1, 0));
+        let hits: Vec<Vec<usize>> = (0..12)
+            .map(|_| seq.tick().iter().map(|e| e.track).collect())
+            .collect();
+        assert_eq!(hits[0], vec![0, 1]);
+        assert_eq!(hits[3], vec![0]);
+        assert_eq!(hits[4], vec![1]);
+        assert_eq!(seq.position(), 12);
+    }
+
+    #[test]
+    fn advance_follows_time_and_tempo() {
+        let mut seq = Sequencer::new(60.0).with_track(SequencerTrack::new("all", 4, 4, 0));
+        // 60 BPM sixteenths: 0.25 s per step.
+        assert_eq!(seq.advance(0.0).len(), 1);
+        assert_eq!(seq.advance(0.2).len(), 0);
+        assert_eq!(seq.advance(0.1).len(), 1);
+        assert_eq!(seq.advance(1.0).len(), 4);
+
+        seq.set_bpm(120.0);
+        seq.reset();
+        let events = seq.advance(0.5);
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[4].cycle, 1);
+
+        for bpm in [0.0, -5.0, f64::NAN] {
+            seq.set_bpm(bpm);
+            assert!(seq.advance(1.0).is_empty());
+        }
+        seq.set_bpm(f64::INFINITY);
+        assert_eq!(seq.bpm(), MAX_BPM);
+        assert_eq!(seq.advance(f64::INFINITY).len(), 1);
+        assert!(seq.advance(f64::NAN).is_empty());
+        // 999 BPM sixteenths: about 15 ms per step.
+        assert_eq!(seq.advance(0.1).len(), 6);
+    }
+
+    #[test]
+    fn swing_delays_odd_steps() {
+        let mut seq = Sequencer::new(60.0).with_track(SequencerTrack::new("all", 4, 4, 0));
+        seq.set_swing(90);
+        assert_eq!(seq.swing(), 75);
+        // 0.375 s for even steps, 0.125 s for odd ones.
+        assert_eq!(seq.advance(0.0).len(), 1);
+        assert_eq!(seq.advance(0.3).len(), 0);
+        assert_eq!(seq.advance(0.1)[0].step, 1);
+        assert_eq!(seq.advance(0.1)[0].step, 2);
+
+        seq.reset();
+        let at = |beat| ClockSnapshot {
+            tempo: 60.0,
+            beat,
+            playing: true,
+        };
+        assert_eq!(seq.follow(at(0.0)).len(), 1);
+        assert!(seq.follow(at(0.3)).is_empty());
+        assert_eq!(seq.follow(at(0.4))[0].step, 1);
+    }
+
+    #[test]
+    fn changing_params_while_playing() {
+        let mut seq = Sequencer::new(120.0).with_track(SequencerTrack::new("k", 8, 1, 0));
+        seq.tick();
+        seq.track_mut(0).unwrap().set_params(8, 8, 0);
+        assert_eq!(seq.tick().len(), 1);
+        assert_eq!(seq.tracks()[0].params(), (8, 8, 0));
+    }
+
+    #[test]
+    fn mute_solo_and_fill_wait_for_the_boundary() {
+        let mut seq = Sequencer::new(120.0)
+            .with_track(SequencerTrack::new("kick", 4, 4, 0))
+            .with_track(SequencerTrack::new("snare", 4, 4, 0))
+            .with_track(SequencerTrack::new("hat", 4, 4, 0).with_condition(1, TrigCondition::Fill));
+        seq.set_quantize(4);
+        let tracks = |events: Vec<StepEvent>| events.iter().map(|e| e.track).collect::<Vec<_>>();
+        assert_eq!(tracks(seq.tick()), [0, 1, 2]);
+
+        seq.track_mut(0).unwrap().set_soloed(true);
+        seq.track_mut(0).unwrap().set_muted(true);
+        seq.track_mut(2).unwrap().set_soloed(true);
+        seq.track_mut(2).unwrap().set_fill(true);
+        assert_eq!(tracks(seq.tick()), [0, 1]); // hat's fill step waits
+        assert!(!seq.tracks()[2].soloed());
+        seq.tick();
+        seq.tick();
+
+        // Bar line: the muted solo stays silent and hat plays in fill.
+        let events = seq.tick();
+        assert_eq!(tracks(events.clone()), [2]);
+        assert!(events[0].fill && seq.tracks()[0].muted());
+        assert_eq!(tracks(seq.tick()), [2]);
+    }
+
+    #[test]
+    fn rotation_drifts_within_bounds() {
+        let drift = RotationDrift {
+            percent: 100,
+            range: 1,
+        };
+        let mut seq = Sequencer::new(120.0)
+            .with_seed(3)
+            .with_track(SequencerTrack::new("k", 4, 1, 0).with_drift(drift));
+        let mut offsets = Vec::new();
+        for _ in 0..40 {
+            seq.tick();
+            offsets.extend(seq.take_drift_events().iter().map(|e| e.offset));
+        }
+        // Every boundary moves unless the walk is pinned at a bound.
+        assert!(!offsets.is_empty());
+        assert!(offsets.iter().all(|offset| offset.abs() <= 1));
+        assert!(offsets.windows(2).all(|w| w[0] != w[1]));
+        let track = &seq.tracks()[0];
+        let expected = euclidean(4, 1, 0);
+        let mut rotated = expected.clone();
+        rotated.rotate_left(track.drift_offset().rem_euclid(4) as usize);
+        assert_eq!(track.pattern(), rotated);
+
+        seq.reset();
+        assert_eq!(seq.tracks()[0].drift_offset(), 0);
+        assert_eq!(seq.tracks()[0].pattern(), expected);
+    }
+
+    #[test]
+    fn regenerating_a_drifted_track_restarts_the_drift() {
+        let mut track = SequencerTrack::new("t", 8, 3, 0).with_drift(RotationDrift {
+            percent: 100,
+            range: 2,
+        });
+        track.shift_drift(1);
+        track.regenerate(&crate::generator::Clustered);
+        assert_eq!(track.drift_offset(), 0);
+        track.shift_drift(-1);
+        assert_eq!(
+            track.pattern(),
+            [false, true, true, true, false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn probability_mask_thins_off_mask_pulses() {
+        let mask = [1.0, 0.0, 0.0, 0.0];
+        let track = SequencerTrack::new("h", 4, 4, 0)
+            .with_lock(1, ParamLock::default().note(40))
+            .with_probability_mask(&mask);
+        assert_eq!(
+            track.lock(1),
+            Some(&ParamLock::default().note(40).probability(0))
+        );
+        let mut seq = Sequencer::new(120.0).with_track(track);
+        let played: Vec<usize> = (0..8).flat_map(|_| seq.tick()).map(|e| e.step).collect();
+        assert_eq!(played, [0, 0]);
+    }
+
+    #[test]
+    fn closures_are_listeners() {
+        let mut seq = Sequencer::new(120.0).with_track(SequencerTrack::new("k", 4, 2, 0));
+        let mut steps = Vec::new();
+        seq.advance_with(0.5, &mut |e: &StepEvent| steps.push(e.step));
+        assert_eq!(steps, vec![0, 2, 0]);
+    }
+
+    #[test]
+    fn trig_conditions_shape_the_phrase() {
+        let track = SequencerTrack::new("k", 2, 2, 0)
+            .with_condition(0, "1:2".parse().unwrap())
+            .with_condition(1, TrigCondition::Fill);
+        let mut seq = Sequencer::new(120.0).with_track(track);
+        let played: Vec<usize> = (0..6).flat_map(|_| seq.tick()).map(|e| e.step).collect();
+        // Step 0 plays in cycles 0 and 2 only; step 1 waits for fill.
+        assert_eq!(played, vec![0, 0]);
+
+        seq.set_fill(true);
+        let filled: Vec<usize> = (0..2).flat_map(|_| seq.tick()).map(|e| e.step).collect();
+        assert_eq!(filled, vec![1]);
+
+        let chance =
+            SequencerTrack::new("h", 1, 1, 0).with_condition(0, TrigCondition::Probability(50));
+        let run = |seed| {
+            let mut seq = Sequencer::new(120.0)
+                .with_seed(seed)
+                .with_track(chance.clone());
+            (0..64).map(|_| seq.tick().len()).collect::<Vec<_>>()
+        };
+        let hits: usize = run(9).iter().sum();
+        assert!((16..48).contains(&hits));
+        assert_eq!(run(9), run(9));
+    }
+
+    #[test]
+    fn param_locks_override_one_step() {
+        let track = SequencerTrack::new("s", 4, 2, 0)
+            .with_note(38)
+            .with_lock(2, ParamLock::default().note(40).velocity(127).ratchet(2))
+            .with_lock(0, ParamLock::default().probability(0));
+        let mut seq = Sequencer::new(120.0).with_track(track);
+        let events: Vec<StepEvent> = (0..4).flat_map(|_| seq.tick()).collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            (events[0].note, events[0].velocity, events[0].ratchet),
+            (40, 127, 2)
+        );
+
+        let track = &mut seq.track_mut(0).unwrap();
+        track.set_lock(0, ParamLock::default());
+        assert!(track.lock(0).is_none());
+    }
+
+    #[test]
+    fn follow_handles_transport_and_jumps() {
+        let mut seq = Sequencer::new(120.0).with_track(SequencerTrack::new("k", 4, 4, 0));
+        let clock = |beat, playing| ClockSnapshot {
+            tempo: 120.0,
+            beat,
+            playing,
+        };
+        assert!(seq.follow(clock(1.0, false)).is_empty());
+        assert!(seq.follow(clock(-0.25, true)).is_empty());
+        assert_eq!(seq.position(), 0);
+
+        assert_eq!(seq.follow(clock(0.0, true)).len(), 1);
+        // Far ahead: jump and play only the current step.
+        let events = seq.follow(clock(10.0, true));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tick, 40);
+        // Backwards: restart from the clock's position.
+        let events = seq.follow(clock(0.0, true));
+        assert_eq!(events[0].tick, 0);
+        // Beats too far out to count in steps, or NaN, do not overflow.
+        let events = seq.follow(clock(1e30, true));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tick, MAX_FOLLOW_STEP);
+        assert!(seq.follow(clock(f64::INFINITY, true)).is_empty());
+        assert!(seq.follow(clock(f64::NAN, true)).is_empty());
+        assert_eq!(seq.follow(clock(0.0, true))[0].tick, 0);
+    }
+}
@@ -0,0 +1,312 @@
+//! `serde` support for patterns and pattern banks.
+//!
+//! A [`Pattern`] is stored as its Euclidean parameters next to the expanded
+//! steps, so presets stay readable and still load when the steps were
+//! edited by hand:
+//!
+//! ```json
+//! {"steps":8,"pulses":3,"rotation":0,"pattern":[true,false,false,true,false,false,true,false]}
+//! ```
+//!
+//! `rotation` is left out for patterns that are not a rotated Euclidean
+//! rhythm. On loading, the `pattern` array wins and `steps` and `pulses` are
+//! checked against it; without the array, the pattern is generated from the
+//! parameters, so `{"steps":8,"pulses":3}` is a valid preset too.
+//!
+//! A [`Track`] is `{"name": ..., "pattern": {...}}`, a [`PatternBank`] is
+//! `{"tracks": [...]}` plus a `"resolution"` when it has one, and a
+//! [`Resolution`] is its kebab-case name, such as `"sixteenth-triplets"`.
+//! Fields are optional or named, so the formats are meant to be
+//! self-describing ones such as JSON, TOML or YAML.
+
+#[cfg(feature = "sequencer")]
+mod session;
+
+use crate::bank::{PatternBank, Resolution, Track};
+use crate::pattern::Pattern;
+use crate::{detect_euclidean, try_euclidean_large};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+const PATTERN_FIELDS: &[&str] = &["steps", "pulses", "rotation", "pattern"];
+const TRACK_FIELDS: &[&str] = &["name", "pattern"];
+const BANK_FIELDS: &[&str] = &["tracks", "resolution"];
+const RESOLUTIONS: &[&str] = &[
+    "eighths",
+    "eighth-triplets",
+    "sixteenths",
+    "sixteenth-triplets",
+    "thirty-seconds",
+];
+
+/// Serializes step data in the compact form described in the module docs.
+struct Steps<'a>(&'a [bool]);
+
+impl Serialize for Steps<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let steps = self.0.len();
+        let pulses = self.0.iter().filter(|&&pulse| pulse).count();
+        let rotation = detect_euclidean(self.0).map(|(_, _, rotation)| rotation);
+        let mut state = serializer.serialize_struct("Pattern", 4)?;
+        state.serialize_field("steps", &steps)?;
+        state.serialize_field("pulses", &pulses)?;
+        match rotation {
+            Some(rotation) => state.serialize_field("rotation", &rotation)?,
+            None => state.skip_field("rotation")?,
+        }
+        state.serialize_field("pattern", self.0)?;
+        state.end()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Steps(self).serialize(serializer)
+    }
+}
+
+struct PatternVisitor;
+
+impl<'de> Visitor<'de> for PatternVisitor {
+    type Value = Pattern;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a pattern with a `pattern` array or `steps` and `pulses`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Pattern, A::Error> {
+        let mut steps: Option<usize> = None;
+        let mut pulses: Option<usize> = None;
+        let mut rotation: Option<usize> = None;
+        let mut pattern: Option<Vec<bool>> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "steps" => steps = Some(map.next_value()?),
+                "pulses" => pulses = Some(map.next_value()?),
+                "rotation" => rotation = Some(map.next_value()?),
+                "pattern" => pattern = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if let Some(pattern) = pattern {
+            let count = pattern.iter().filter(|&&pulse| pulse).count();
+            if let Some(steps) = steps
+                && steps != pattern.len()
+            {
+                return Err(de::Error::custom(format_args!(
+                    "`steps` is {steps} but `pattern` has {} steps",
+                    pattern.len()
+                )));
+            }
+            if let Some(pulses) = pulses
+                && pulses != count
+            {
+                return Err(de::Error::custom(format_args!(
+                    "`pulses` is {pulses} but `pattern` has {count} pulses"
+                )));
+            }
+            return Ok(Pattern::new(pattern));
+        }
+        let steps = steps.ok_or_else(|| de::Error::missing_field("steps"))?;
+        let pulses = pulses.ok_or_else(|| de::Error::missing_field("pulses"))?;
+        try_euclidean_large(steps, pulses, rotation.unwrap_or(0))
+            .map(Pattern::new)
+            .map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Pattern", PATTERN_FIELDS, PatternVisitor)
+    }
+}
+
+impl Serialize for Track {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Track", 2)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("pattern", &Steps(&self.pattern))?;
+        state.end()
+    }
+}
+
+struct TrackVisitor;
+
+impl<'de> Visitor<'de> for TrackVisitor {
+    type Value = Track;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a track with a `name` and a `pattern`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Track, A::Error> {
+        let mut name: Option<String> = None;
+        let mut pattern: Option<Pattern> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "pattern" => pattern = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(Track {
+            name: name.ok_or_else(|| de::Error::missing_field("name"))?,
+            pattern: pattern
+                .ok_or_else(|| de::Error::missing_field("pattern"))?
+                .into_vec(),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Track {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Track", TRACK_FIELDS, TrackVisitor)
+    }
+}
+
+impl Serialize for PatternBank {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PatternBank", 2)?;
+        state.serialize_field("tracks", self.tracks())?;
+        match self.resolution() {
+            Some(resolution) => state.serialize_field("resolution", &resolution)?,
+            None => state.skip_field("resolution")?,
+        }
+        state.end()
+    }
+}
+
+struct BankVisitor;
+
+impl<'de> Visitor<'de> for BankVisitor {
+    type Value = PatternBank;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a pattern bank with a `tracks` array")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<PatternBank, A::Error> {
+        let mut tracks: Vec<Track> = Vec::new();
+        let mut resolution: Option<Resolution> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "tracks" => tracks = map.next_value()?,
+                "resolution" => resolution = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let mut bank = PatternBank::new();
+        for track in tracks {
+            bank.push(track.name, track.pattern);
+        }
+        bank.set_resolution(resolution);
+        Ok(bank)
+    }
+}
+
+impl<'de> Deserialize<'de> for PatternBank {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("PatternBank", BANK_FIELDS, BankVisitor)
+    }
+}
+
+impl Resolution {
+    /// Position in [`RESOLUTIONS`].
+    fn index(self) -> u32 {
+        match self {
+            Resolution::Eighths => 0,
+            Resolution::EighthTriplets => 1,
+            Resolution::Sixteenths => 2,
+            Resolution::SixteenthTriplets => 3,
+            Resolution::ThirtySeconds => 4,
+        }
+    }
+}
+
+impl Serialize for Resolution {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = self.index();
+        serializer.serialize_unit_variant("Resolution", index, RESOLUTIONS[index as usize])
+    }
+}
+
+struct ResolutionVisitor;
+
+impl<'de> Visitor<'de> for ResolutionVisitor {
+    type Value = Resolution;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a resolution name such as \"sixteenths\"")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Resolution, E> {
+        match name {
+            "eighths" => Ok(Resolution::Eighths),
+            "eighth-triplets" => Ok(Resolution::EighthTriplets),
+            "sixteenths" => Ok(Resolution::Sixteenths),
+            "sixteenth-triplets" => Ok(Resolution::SixteenthTriplets),
+            "thirty-seconds" => Ok(Resolution::ThirtySeconds),
+            _ => Err(de::Error::unknown_variant(name, RESOLUTIONS)),
+        }
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<Resolution, A::Error> {
+        use serde::de::VariantAccess;
+        let (name, variant): (String, _) = data.variant()?;
+        variant.unit_variant()?;
+        self.visit_str(&name)
+    }
+}
+
+impl<'de> Deserialize<'de> for Resolution {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Resolution", RESOLUTIONS, ResolutionVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{Error, MapDeserializer};
+
+    #[test]
+    fn compact_presets_load() {
+        let preset = [("steps", 8u8), ("pulses", 3), ("rotation", 2), ("swing", 9)];
+        let pattern = Pattern::deserialize(MapDeserializer::<_, Error>::new(preset.into_iter()));
+        assert_eq!(pattern.unwrap(), Pattern::from(euclidean(8, 3, 2)));
+
+        let bad = [("steps", 4u8), ("pulses", 5)];
+        let err = Pattern::deserialize(MapDeserializer::<_, Error>::new(bad.into_iter()));
+        assert!(err.unwrap_err().to_string().contains("must not exceed"));
+        let missing = [("steps", 4u8)];
+        assert!(
+            Pattern::deserialize(MapDeserializer::<_, Error>::new(missing.into_iter())).is_err()
+        );
+
+        let name: de::value::StrDeserializer<'_, Error> = "eighth-triplets".into_deserializer();
+        assert_eq!(
+            Resolution::deserialize(name),
+            Ok(Resolution::EighthTriplets)
+        );
+        for (index, &name) in RESOLUTIONS.iter().enumerate() {
+            let name: de::value::StrDeserializer<'_, Error> = name.into_deserializer();
+            assert_eq!(
+                Resolution::deserialize(name).map(Resolution::index),
+                Ok(index as u32)
+            );
+        }
+        let name: de::value::StrDeserializer<'_, Error> = "quarters".into_deserializer();
+        assert!(Resolution::deserialize(name).is_err());
+    }
+}
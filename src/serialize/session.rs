@@ -0,0 +1,533 @@
+//! `serde` support for sequencer sessions.
+//!
+//! A [`Sequencer`] holds the same settings as a text session (see the
+//! [`session`](crate::session) module) under the same names, plus the
+//! format version:
+//!
+//! ```json
+//! {"version":5,"bpm":120.0,"steps_per_beat":4,"seed":7,"fill":false,
+//!  "quantize":16,"swing":50,"tracks":[
+//!   {"name":"kick","steps":16,"pulses":4,"rotation":0,"note":36,"velocity":100,
+//!    "generator":"bjorklund",
+//!    "conditions":[{"step":4,"condition":"1:2"}],
+//!    "locks":[{"step":12,"velocity":60,"ratchet":3}],
+//!    "drift":{"percent":25,"range":2},"switches":["muted"]}]}
+//! ```
+//!
+//! Every field but a track's `name`, `steps` and `pulses` may be left out
+//! and takes its default, which is how documents written before a setting
+//! existed keep loading. Documents from a newer version are rejected.
+
+use crate::error::Error;
+use crate::generator::Algorithm;
+use crate::sequencer::{RotationDrift, Sequencer, SequencerTrack};
+use crate::session::{SESSION_VERSION, check_bpm};
+use crate::trig::{ParamLock, TrigCondition};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
+
+const SEQUENCER_FIELDS: &[&str] = &[
+    "version",
+    "bpm",
+    "steps_per_beat",
+    "seed",
+    "fill",
+    "quantize",
+    "swing",
+    "tracks",
+];
+const TRACK_FIELDS: &[&str] = &[
+    "name",
+    "steps",
+    "pulses",
+    "rotation",
+    "note",
+    "velocity",
+    "generator",
+    "conditions",
+    "locks",
+    "drift",
+    "switches",
+];
+const CONDITION_FIELDS: &[&str] = &["step", "condition"];
+const LOCK_FIELDS: &[&str] = &["step", "velocity", "note", "ratchet", "probability"];
+const DRIFT_FIELDS: &[&str] = &["percent", "range"];
+const SWITCHES: &[&str] = &["muted", "soloed", "fill"];
+
+impl Serialize for Sequencer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Sequencer", SEQUENCER_FIELDS.len())?;
+        state.serialize_field("version", &SESSION_VERSION)?;
+        state.serialize_field("bpm", &self.bpm())?;
+        state.serialize_field("steps_per_beat", &self.steps_per_beat())?;
+        state.serialize_field("seed", &self.seed())?;
+        state.serialize_field("fill", &self.fill())?;
+        state.serialize_field("quantize", &self.quantize())?;
+        state.serialize_field("swing", &self.swing())?;
+        state.serialize_field("tracks", self.tracks())?;
+        state.end()
+    }
+}
+
+struct SequencerVisitor;
+
+impl<'de> Visitor<'de> for SequencerVisitor {
+    type Value = Sequencer;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequencer session")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Sequencer, A::Error> {
+        let mut seq = Sequencer::new(120.0);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => {
+                    let version: u32 = map.next_value()?;
+                    if version > SESSION_VERSION {
+                        return Err(de::Error::custom(format_args!(
+                            "session version {version} is newer than {SESSION_VERSION}"
+                        )));
+                    }
+                }
+                "bpm" => seq.set_bpm(check_bpm(map.next_value()?).map_err(de::Error::custom)?),
+                "steps_per_beat" => seq.set_steps_per_beat(map.next_value()?),
+                "seed" => seq = seq.with_seed(map.next_value()?),
+                "fill" => seq.set_fill(map.next_value()?),
+                "quantize" => seq.set_quantize(map.next_value()?),
+                "swing" => seq.set_swing(map.next_value()?),
+                "tracks" => {
+                    for track in map.next_value::<Vec<SequencerTrack>>()? {
+                        seq.add_track(track);
+                    }
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(seq)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sequencer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Sequencer", SEQUENCER_FIELDS, SequencerVisitor)
+    }
+}
+
+impl Serialize for SequencerTrack {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (steps, pulses, rotation) = self.params();
+        let mut state = serializer.serialize_struct("SequencerTrack", TRACK_FIELDS.len())?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("steps", &steps)?;
+        state.serialize_field("pulses", &pulses)?;
+        state.serialize_field("rotation", &rotation)?;
+        state.serialize_field("note", &self.note)?;
+        state.serialize_field("velocity", &self.velocity)?;
+        state.serialize_field("generator", &self.generator())?;
+        state.serialize_field("conditions", &Conditions(self))?;
+        state.serialize_field("locks", &Locks(self))?;
+        match self.drift() {
+            Some(drift) => state.serialize_field("drift", &drift)?,
+            None => state.skip_field("drift")?,
+        }
+        state.serialize_field("switches", &Switches(self))?;
+        state.end()
+    }
+}
+
+struct TrackVisitor;
+
+impl<'de> Visitor<'de> for TrackVisitor {
+    type Value = SequencerTrack;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequencer track with a `name`, `steps` and `pulses`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<SequencerTrack, A::Error> {
+        let mut name: Option<String> = None;
+        let mut steps: Option<u8> = None;
+        let mut pulses: Option<u8> = None;
+        let mut rotation = 0;
+        let mut note = None;
+        let mut velocity = None;
+        let mut generator: Option<Algorithm> = None;
+        let mut conditions: Vec<ConditionEntry> = Vec::new();
+        let mut locks: Vec<LockEntry> = Vec::new();
+        let mut drift: Option<RotationDrift> = None;
+        let mut switches: Vec<String> = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "steps" => steps = Some(map.next_value()?),
+                "pulses" => pulses = Some(map.next_value()?),
+                "rotation" => rotation = map.next_value()?,
+                "note" => note = Some(map.next_value()?),
+                "velocity" => velocity = Some(map.next_value()?),
+                "generator" => generator = Some(map.next_value()?),
+                "conditions" => conditions = map.next_value()?,
+                "locks" => locks = map.next_value()?,
+                "drift" => drift = Some(map.next_value()?),
+                "switches" => switches = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let name = name.ok_or_else(|| de::Error::missing_field("name"))?;
+        let steps = steps.ok_or_else(|| de::Error::missing_field("steps"))?;
+        let pulses = pulses.ok_or_else(|| de::Error::missing_field("pulses"))?;
+        let mut track =
+            SequencerTrack::try_new(name, steps, pulses, rotation).map_err(de::Error::custom)?;
+        if let Some(note) = note {
+            track.note = note;
+        }
+        if let Some(velocity) = velocity {
+            track.velocity = velocity;
+        }
+        if let Some(generator) = generator {
+            track.set_generator(generator);
+        }
+        for ConditionEntry(step, condition) in conditions {
+            track.set_condition(step, condition);
+        }
+        for LockEntry(step, lock) in locks {
+            track.set_lock(step, lock);
+        }
+        track.set_drift(drift);
+        for switch in switches {
+            match switch.as_str() {
+                "muted" => track.set_muted(true),
+                "soloed" => track.set_soloed(true),
+                "fill" => track.set_fill(true),
+                _ => return Err(de::Error::unknown_variant(&switch, SWITCHES)),
+            }
+        }
+        Ok(track)
+    }
+}
+
+impl<'de> Deserialize<'de> for SequencerTrack {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("SequencerTrack", TRACK_FIELDS, TrackVisitor)
+    }
+}
+
+/// Serializes a track's trig conditions as `{"step", "condition"}` entries.
+struct Conditions<'a>(&'a SequencerTrack);
+
+impl Serialize for Conditions<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        for (step, condition) in self.0.conditions() {
+            seq.serialize_element(&ConditionEntry(step, condition))?;
+        }
+        seq.end()
+    }
+}
+
+struct ConditionEntry(usize, TrigCondition);
+
+impl Serialize for ConditionEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Condition", 2)?;
+        state.serialize_field("step", &self.0)?;
+        state.serialize_field("condition", &self.1)?;
+        state.end()
+    }
+}
+
+struct ConditionVisitor;
+
+impl<'de> Visitor<'de> for ConditionVisitor {
+    type Value = ConditionEntry;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a `step` and a `condition`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ConditionEntry, A::Error> {
+        let mut step: Option<usize> = None;
+        let mut condition: Option<TrigCondition> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "step" => step = Some(map.next_value()?),
+                "condition" => condition = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(ConditionEntry(
+            step.ok_or_else(|| de::Error::missing_field("step"))?,
+            condition.ok_or_else(|| de::Error::missing_field("condition"))?,
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for ConditionEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Condition", CONDITION_FIELDS, ConditionVisitor)
+    }
+}
+
+/// Serializes a track's parameter locks as `{"step", ...}` entries holding
+/// the locked fields.
+struct Locks<'a>(&'a SequencerTrack);
+
+impl Serialize for Locks<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        for (step, lock) in self.0.locks() {
+            seq.serialize_element(&LockEntry(step, *lock))?;
+        }
+        seq.end()
+    }
+}
+
+struct LockEntry(usize, ParamLock);
+
+impl Serialize for LockEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let lock = &self.1;
+        let mut state = serializer.serialize_struct("Lock", LOCK_FIELDS.len())?;
+        state.serialize_field("step", &self.0)?;
+        for (key, value) in [
+            ("velocity", lock.velocity),
+            ("note", lock.note),
+            ("ratchet", lock.ratchet),
+            ("probability", lock.probability),
+        ] {
+            match value {
+                Some(value) => state.serialize_field(key, &value)?,
+                None => state.skip_field(key)?,
+            }
+        }
+        state.end()
+    }
+}
+
+struct LockVisitor;
+
+impl<'de> Visitor<'de> for LockVisitor {
+    type Value = LockEntry;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a `step` and the locked parameters")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<LockEntry, A::Error> {
+        let mut step: Option<usize> = None;
+        let mut lock = ParamLock::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "step" => step = Some(map.next_value()?),
+                "velocity" => lock.velocity = Some(map.next_value()?),
+                "note" => lock.note = Some(map.next_value()?),
+                "ratchet" => lock.ratchet = Some(map.next_value()?),
+                "probability" => lock.probability = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let step = step.ok_or_else(|| de::Error::missing_field("step"))?;
+        Ok(LockEntry(step, lock))
+    }
+}
+
+impl<'de> Deserialize<'de> for LockEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Lock", LOCK_FIELDS, LockVisitor)
+    }
+}
+
+/// Serializes the names of a track's switches that are on.
+struct Switches<'a>(&'a SequencerTrack);
+
+impl Serialize for Switches<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (muted, soloed, fill) = self.0.requested_switches();
+        let mut seq = serializer.serialize_seq(None)?;
+        for (&name, on) in SWITCHES.iter().zip([muted, soloed, fill]) {
+            if on {
+                seq.serialize_element(name)?;
+            }
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for TrigCondition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct TrigConditionVisitor;
+
+impl Visitor<'_> for TrigConditionVisitor {
+    type Value = TrigCondition;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a trig condition such as \"1:2\", \"fill\" or \"25%\"")
+    }
+
+    fn visit_str<E: de::Error>(self, condition: &str) -> Result<TrigCondition, E> {
+        condition.parse().map_err(|err: Error| E::custom(err))
+    }
+}
+
+impl<'de> Deserialize<'de> for TrigCondition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(TrigConditionVisitor)
+    }
+}
+
+impl Serialize for Algorithm {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct AlgorithmVisitor;
+
+impl Visitor<'_> for AlgorithmVisitor {
+    type Value = Algorithm;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a generator such as \"bjorklund\" or \"random:7\"")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Algorithm, E> {
+        name.parse().map_err(|err: Error| E::custom(err))
+    }
+}
+
+impl<'de> Deserialize<'de> for Algorithm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(AlgorithmVisitor)
+    }
+}
+
+impl Serialize for RotationDrift {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("RotationDrift", 2)?;
+        state.serialize_field("percent", &self.percent)?;
+        state.serialize_field("range", &self.range)?;
+        state.end()
+    }
+}
+
+struct DriftVisitor;
+
+impl<'de> Visitor<'de> for DriftVisitor {
+    type Value = RotationDrift;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a rotation drift with a `percent` and a `range`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RotationDrift, A::Error> {
+        let mut percent: Option<u8> = None;
+        let mut range: Option<u8> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "percent" => percent = Some(map.next_value()?),
+                "range" => range = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(RotationDrift {
+            percent: percent.ok_or_else(|| de::Error::missing_field("percent"))?,
+            range: range.ok_or_else(|| de::Error::missing_field("range"))?,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for RotationDrift {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("RotationDrift", DRIFT_FIELDS, DriftVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{Error, MapDeserializer, StrDeserializer};
+
+    #[test]
+    fn loads_settings_and_checks_the_version() {
+        let session = [
+            ("version", 3u32),
+            ("steps_per_beat", 3),
+            ("seed", 9),
+            ("quantize", 16),
+            ("swing", 60),
+            ("position", 2),
+        ];
+        let seq = Sequencer::deserialize(MapDeserializer::<_, Error>::new(session.into_iter()));
+        let seq = seq.unwrap();
+        assert_eq!(
+            (
+                seq.steps_per_beat(),
+                seq.seed(),
+                seq.quantize(),
+                seq.swing()
+            ),
+            (3, 9, 16, 60)
+        );
+        assert_eq!(seq.bpm(), 120.0);
+        assert!(seq.tracks().is_empty());
+
+        for bpm in [f64::INFINITY, 0.0, 1e308] {
+            let tempo = [("bpm", bpm)];
+            let err = Sequencer::deserialize(MapDeserializer::<_, Error>::new(tempo.into_iter()));
+            assert!(err.is_err(), "{bpm}");
+        }
+
+        let newer = [("version", SESSION_VERSION + 1)];
+        let err = Sequencer::deserialize(MapDeserializer::<_, Error>::new(newer.into_iter()));
+        assert!(err.unwrap_err().to_string().contains("newer"));
+
+        let drift = [("percent", 30u8), ("range", 2)];
+        assert_eq!(
+            RotationDrift::deserialize(MapDeserializer::<_, Error>::new(drift.into_iter())),
+            Ok(RotationDrift {
+                percent: 30,
+                range: 2
+            })
+        );
+        let lock = [("step", 5u8), ("ratchet", 3)];
+        let LockEntry(step, lock) =
+            LockEntry::deserialize(MapDeserializer::<_, Error>::new(lock.into_iter())).unwrap();
+        assert_eq!((step, lock), (5, ParamLock::default().ratchet(3)));
+
+        let condition: StrDeserializer<'_, Error> = "3:4".into_deserializer();
+        assert_eq!(
+            TrigCondition::deserialize(condition),
+            Ok(TrigCondition::Cycle { a: 3, b: 4 })
+        );
+        let condition: StrDeserializer<'_, Error> = "sometimes".into_deserializer();
+        assert!(TrigCondition::deserialize(condition).is_err());
+        let generator: StrDeserializer<'_, Error> = "balanced-polygon".into_deserializer();
+        assert_eq!(
+            Algorithm::deserialize(generator),
+            Ok(Algorithm::BalancedPolygon)
+        );
+
+        let unnamed = [("steps", 8u8), ("pulses", 3)];
+        let err =
+            SequencerTrack::deserialize(MapDeserializer::<_, Error>::new(unnamed.into_iter()));
+        assert!(err.unwrap_err().to_string().contains("name"));
+    }
+}
@@ -0,0 +1,318 @@
+//! Saving and loading sequencer sessions.
+//!
+//! A session is a small line-based text document holding everything needed
+//! to rebuild a [`Sequencer`]: transport settings, tracks and their
+//! generators, trig conditions, parameter locks and each track's mute, solo
+//! and fill switches. The
+//! playhead and any rotation drift so far are not saved; a loaded session
+//! starts from step 0, where the saved switches take effect.
+//!
+//! ```text
+//! euclidean-session 5
+//! bpm 120
+//! steps_per_beat 4
+//! seed 7
+//! fill false
+//! quantize 16
+//! swing 50
+//! track 16 4 0 kick
+//!   note 36
+//!   velocity 100
+//!   generator balanced-polygon
+//!   condition 4 1:2
+//!   lock 12 velocity=60 ratchet=3
+//!   drift 25 2
+//!   switches muted fill
+//! ```
+//!
+//! `generator` names the track's [`Algorithm`] and is left out for
+//! Bjorklund's. `switches` lists the track's switches that are on (any of `muted`,
+//! `soloed` and `fill`) and is left out when all are off. A switch change
+//! still waiting for the quantize boundary is saved as already made.
+//!
+//! The first line names the format version. Documents from older versions
+//! are upgraded one version at a time by the functions in `MIGRATIONS`
+//! before parsing, so old projects keep loading as the format grows. Blank
+//! lines and lines starting with `#` are ignored, and indentation is only
+//! for readability.
+
+use crate::error::{Error, Result};
+use crate::generator::Algorithm;
+use crate::sequencer::{MAX_BPM, RotationDrift, Sequencer, SequencerTrack};
+use crate::trig::{ParamLock, TrigCondition};
+use std::fmt::Write;
+
+/// The session format version written by [`Sequencer::save_session`].
+pub const SESSION_VERSION: u32 = 5;
+
+const HEADER: &str = "euclidean-session";
+
+/// Upgrades a document body from version `i + 1` to version `i + 2`.
+type Migration = fn(Vec<String>) -> Result<Vec<String>>;
+
+/// One entry per format change, oldest first.
+const MIGRATIONS: &[Migration] = &[
+    Ok, // Version 2 added the optional `drift` track setting.
+    Ok, // Version 3 added `quantize` and the optional `switches` track setting.
+    Ok, // Version 4 added `swing`.
+    Ok, // Version 5 added the optional `generator` track setting.
+];
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "session",
+        reason,
+    }
+}
+
+impl Sequencer {
+    /// Writes the sequencer's tracks and transport settings as a session
+    /// document. See the [`session`](crate::session) module for the format.
+    ///
+    /// # Examples
+    /// ```
+    /// use euclidean_rhythm::{ParamLock, Sequencer, SequencerTrack};
+    ///
+    /// let seq = Sequencer::new(96.0)
+    ///     .with_track(SequencerTrack::new("kick", 16, 4, 0).with_lock(4, ParamLock::default().ratchet(2)));
+    /// let saved = seq.save_session();
+    /// assert_eq!(Sequencer::load_session(&saved)?, seq);
+    /// # Ok::<(), euclidean_rhythm::Error>(())
+    /// ```
+    pub fn save_session(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String cannot fail.
+        let _ = writeln!(out, "{HEADER} {SESSION_VERSION}");
+        let _ = writeln!(out, "bpm {}", self.bpm());
+        let _ = writeln!(out, "steps_per_beat {}", self.steps_per_beat());
+        let _ = writeln!(out, "seed {}", self.seed());
+        let _ = writeln!(out, "fill {}", self.fill());
+        let _ = writeln!(out, "quantize {}", self.quantize());
+        let _ = writeln!(out, "swing {}", self.swing());
+        for track in self.tracks() {
+            let (steps, pulses, rotation) = track.params();
+            let _ = writeln!(out, "track {steps} {pulses} {rotation} {}", track.name);
+            let _ = writeln!(out, "  note {}", track.note);
+            let _ = writeln!(out, "  velocity {}", track.velocity);
+            if track.generator() != Algorithm::Bjorklund {
+                let _ = writeln!(out, "  generator {}", track.generator());
+            }
+            for (step, condition) in track.conditions() {
+                let _ = writeln!(out, "  condition {step} {condition}");
+            }
+            for (step, lock) in track.locks() {
+                let _ = write!(out, "  lock {step}");
+                for (key, value) in [
+                    ("velocity", lock.velocity),
+                    ("note", lock.note),
+                    ("ratchet", lock.ratchet),
+                    ("probability", lock.probability),
+                ] {
+                    if let Some(value) = value {
+                        let _ = write!(out, " {key}={value}");
+                    }
+                }
+                out.push('\n');
+            }
+            if let Some(drift) = track.drift() {
+                let _ = writeln!(out, "  drift {} {}", drift.percent, drift.range);
+            }
+            let (muted, soloed, fill) = track.requested_switches();
+            if muted || soloed || fill {
+                out.push_str("  switches");
+                for (name, on) in [("muted", muted), ("soloed", soloed), ("fill", fill)] {
+                    if on {
+                        let _ = write!(out, " {name}");
+                    }
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Rebuilds a sequencer from a session document, upgrading documents
+    /// written by older versions first.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidParameter`] named `"session"` if the document
+    /// is malformed or from a newer format version, and the usual parameter
+    /// errors if a track's Euclidean parameters are invalid.
+    pub fn load_session(text: &str) -> Result<Sequencer> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let version: u32 = lines
+            .next()
+            .and_then(|header| header.strip_prefix(HEADER))
+            .and_then(|version| version.trim().parse().ok())
+            .ok_or(invalid("missing euclidean-session header"))?;
+        if version == 0 || version > SESSION_VERSION {
+            return Err(invalid("unsupported format version"));
+        }
+        let mut body: Vec<String> = lines.map(String::from).collect();
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            body = migrate(body)?;
+        }
+
+        let mut seq = Sequencer::new(120.0);
+        for line in &body {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "bpm" => seq.set_bpm(check_bpm(parse(value)?)?),
+                "steps_per_beat" => seq.set_steps_per_beat(parse(value)?),
+                "seed" => seq = seq.with_seed(parse(value)?),
+                "fill" => seq.set_fill(parse(value)?),
+                "quantize" => seq.set_quantize(parse(value)?),
+                "swing" => seq.set_swing(parse(value)?),
+                "track" => {
+                    let mut fields = value.splitn(4, ' ');
+                    let mut param = || -> Result<u8> { parse(fields.next().unwrap_or("")) };
+                    let (steps, pulses, rotation) = (param()?, param()?, param()?);
+                    let name = fields.next().unwrap_or("");
+                    seq.add_track(SequencerTrack::try_new(name, steps, pulses, rotation)?);
+                }
+                _ => {
+                    let index = seq
+                        .tracks()
+                        .len()
+                        .checked_sub(1)
+                        .ok_or(invalid("track setting before the first track"))?;
+                    let track = seq.track_mut(index).expect("index is in range");
+                    apply_track_setting(track, key, value)?;
+                }
+            }
+        }
+        Ok(seq)
+    }
+}
+
+fn apply_track_setting(track: &mut SequencerTrack, key: &str, value: &str) -> Result<()> {
+    match key {
+        "note" => track.note = parse(value)?,
+        "velocity" => track.velocity = parse(value)?,
+        "generator" => track.set_generator(value.parse()?),
+        "condition" => {
+            let (step, condition) = value
+                .split_once(' ')
+                .ok_or(invalid("malformed condition"))?;
+            let condition: TrigCondition = condition.parse()?;
+            track.set_condition(parse(step)?, condition);
+        }
+        "lock" => {
+            let mut fields = value.split_whitespace();
+            let step = parse(fields.next().unwrap_or(""))?;
+            let mut lock = ParamLock::default();
+            for field in fields {
+                let (name, value) = field.split_once('=').ok_or(invalid("malformed lock"))?;
+                let value = parse(value)?;
+                lock = match name {
+                    "velocity" => lock.velocity(value),
+                    "note" => lock.note(value),
+                    "ratchet" => lock.ratchet(value),
+                    "probability" => lock.probability(value),
+                    _ => return Err(invalid("unknown lock parameter")),
+                };
+            }
+            track.set_lock(step, lock);
+        }
+        "drift" => {
+            let (percent, range) = value.split_once(' ').ok_or(invalid("malformed drift"))?;
+            track.set_drift(Some(RotationDrift {
+                percent: parse(percent)?,
+                range: parse(range)?,
+            }));
+        }
+        "switches" => {
+            for switch in value.split_whitespace() {
+                match switch {
+                    "muted" => track.set_muted(true),
+                    "soloed" => track.set_soloed(true),
+                    "fill" => track.set_fill(true),
+                    _ => return Err(invalid("unknown switch")),
+                }
+            }
+        }
+        _ => return Err(invalid("unknown setting")),
+    }
+    Ok(())
+}
+
+/// Checks a saved tempo, which must be one the sequencer can play.
+pub(crate) fn check_bpm(bpm: f64) -> Result<f64> {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return Err(invalid("bpm must be a positive number"));
+    }
+    if bpm > MAX_BPM {
+        return Err(invalid("bpm is above MAX_BPM"));
+    }
+    Ok(bpm)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T> {
+    value.parse().map_err(|_| invalid("malformed value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_a_full_session() {
+        let mut seq = Sequencer::new(133.5)
+            .with_seed(42)
+            .with_track(
+                SequencerTrack::new("open hat", 12, 5, 3)
+                    .with_note(46)
+                    .with_velocity(80)
+                    .with_generator(Algorithm::Random(11))
+                    .with_condition(2, TrigCondition::Cycle { a: 3, b: 4 })
+                    .with_condition(7, TrigCondition::NotFill)
+                    .with_lock(2, ParamLock::default().note(44).probability(25))
+                    .with_drift(RotationDrift {
+                        percent: 30,
+                        range: 2,
+                    }),
+            )
+            .with_track(SequencerTrack::new("kick", 16, 4, 0));
+        seq.set_steps_per_beat(3);
+        seq.set_fill(true);
+        seq.set_quantize(16);
+        seq.set_swing(58);
+        let hat = seq.track_mut(0).unwrap();
+        hat.set_soloed(true);
+        hat.set_fill(true);
+        seq.track_mut(1).unwrap().set_muted(true);
+
+        let mut loaded = Sequencer::load_session(&seq.save_session()).unwrap();
+        assert_eq!(loaded, seq);
+        assert_eq!(loaded.tracks()[0].name, "open hat");
+        assert_eq!(loaded.quantize(), 16);
+        loaded.tick();
+        let hat = &loaded.tracks()[0];
+        assert!(hat.soloed() && hat.fill() && !hat.muted());
+        assert!(loaded.tracks()[1].muted());
+    }
+
+    #[test]
+    fn rejects_bad_documents() {
+        let load = |text: &str| Sequencer::load_session(text);
+        assert!(load("").is_err());
+        assert!(load("euclidean-session 6\n").is_err());
+        assert!(load("euclidean-session 1\nnote 40\n").is_err());
+        assert!(load("euclidean-session 1\ntrack 8 9 0 x\n").is_err());
+        assert!(load("euclidean-session 1\ntrack 8 3 0 x\nlock 1 swing=5\n").is_err());
+        assert!(load("euclidean-session 1\ntempo 120\n").is_err());
+        assert!(load("# saved by hand\neuclidean-session 1\n\nbpm 90\n").is_ok());
+        assert!(load("euclidean-session 2\ntrack 8 3 0 x\ndrift 50\n").is_err());
+        assert!(load("euclidean-session 5\ntrack 8 3 0 x\ngenerator euclid\n").is_err());
+        for bpm in ["inf", "NaN", "0", "-90", "1e308"] {
+            let text = format!("euclidean-session 4\nbpm {bpm}\ntrack 4 4 0 k\n");
+            assert!(load(&text).is_err(), "{bpm}");
+        }
+        assert!(load("euclidean-session 3\ntrack 8 3 0 x\nswitches loud\n").is_err());
+        assert_eq!(load("euclidean-session 2\nbpm 90\n").unwrap().quantize(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+//! The [`SparsePattern`] type.
+//!
+//! Stores only the onset indices, so a pattern of a million steps with a
+//! handful of onsets costs a handful of words. Operations work on the indices
+//! directly and never build the dense form.
+
+use crate::error::{Error, Result, unwrap_or_panic};
+use crate::pattern::Pattern;
+use alloc::vec::Vec;
+
+/// A pattern stored as its length and sorted onset indices.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{SparsePattern, euclidean};
+///
+/// let tresillo = SparsePattern::from(euclidean(8, 3, 0).as_slice());
+/// assert_eq!(tresillo.onsets(), &[0, 3, 6]);
+///
+/// let pulse = SparsePattern::new(1_000_000, [0, 500_000]);
+/// assert_eq!(pulse.rotate(250_000).onsets(), &[250_000, 750_000]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SparsePattern {
+    steps: usize,
+    onsets: Vec<usize>,
+}
+
+impl SparsePattern {
+    /// Creates a pattern of `steps` steps with pulses at `onsets`, which may
+    /// be in any order and contain duplicates.
+    ///
+    /// # Panics
+    /// Panics if an onset is not less than `steps`.
+    pub fn new(steps: usize, onsets: impl IntoIterator<Item = usize>) -> Self {
+        unwrap_or_panic(Self::try_new(steps, onsets))
+    }
+
+    /// Like [`new`](Self::new), but reports out-of-range onsets as an error.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidParameter`] if an onset is not less than `steps`.
+    pub fn try_new(steps: usize, onsets: impl IntoIterator<Item = usize>) -> Result<Self> {
+        let mut onsets: Vec<usize> = onsets.into_iter().collect();
+        if onsets.iter().any(|&onset| onset >= steps) {
+            return Err(Error::InvalidParameter {
+                name: "onsets",
+                reason: "every onset must be less than steps",
+            });
+        }
+        onsets.sort_unstable();
+        onsets.dedup();
+        Ok(Self { steps, onsets })
+    }
+
+    /// Number of steps.
+    pub fn len(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns `true` if the pattern has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.steps == 0
+    }
+
+    /// The onset indices in ascending order.
+    pub fn onsets(&self) -> &[usize] {
+        &self.onsets
+    }
+
+    /// Number of pulses in the pattern.
+    pub fn pulse_count(&self) -> usize {
+        self.onsets.len()
+    }
+
+    /// Returns `true` if step `step` is a pulse.
+    pub fn is_pulse(&self, step: usize) -> bool {
+        self.onsets.binary_search(&step).is_ok()
+    }
+
+    /// Expands to one `bool` per step.
+    pub fn to_dense(&self) -> Vec<bool> {
+        let mut dense = alloc::vec![false; self.steps];
+        for &onset in &self.onsets {
+            dense[onset] = true;
+        }
+        dense
+    }
+
+    /// Rotates by `rotation` steps with the same direction as
+    /// [`rotate_pattern`](crate::rotate_pattern): positive rotates left
+    /// (earlier), negative rotates right.
+    #[must_use]
+    pub fn rotate(mut self, rotation: i64) -> Self {
+        if self.steps == 0 {
+            return self;
+        }
+        let steps = self.steps as i128;
+        let shift = (i128::from(rotation) % steps + steps) % steps;
+        for onset in &mut self.onsets {
+            *onset = ((*onset as i128 - shift + steps) % steps) as usize;
+        }
+        // Shifting leaves two sorted runs, the onsets that wrapped round to
+        // the end coming first; swap the runs instead of re-sorting.
+        let wrapped = self.onsets.iter().position(|&o| o < self.onsets[0]);
+        if let Some(start) = wrapped {
+            self.onsets.rotate_left(start);
+        }
+        self
+    }
+
+    /// Combines the onsets of both patterns. Patterns of different lengths
+    /// are treated as padded with rests to the longer one.
+    #[must_use]
+    pub fn merge(&self, other: &SparsePattern) -> SparsePattern {
+        let mut onsets: Vec<usize> = self.onsets.iter().chain(&other.onsets).copied().collect();
+        onsets.sort_unstable();
+        onsets.dedup();
+        SparsePattern {
+            steps: self.steps.max(other.steps),
+            onsets,
+        }
+    }
+
+    /// Number of steps where the patterns differ (Hamming distance). Patterns
+    /// of different lengths are treated as padded with rests.
+    pub fn distance(&self, other: &SparsePattern) -> usize {
+        let shared = self
+            .onsets
+            .iter()
+            .filter(|onset| other.onsets.binary_search(onset).is_ok())
+            .count();
+        self.onsets.len() + other.onsets.len() - 2 * shared
+    }
+}
+
+impl From<&[bool]> for SparsePattern {
+    fn from(steps: &[bool]) -> Self {
+        Self {
+            steps: steps.len(),
+            onsets: (0..steps.len()).filter(|&i| steps[i]).collect(),
+        }
+    }
+}
+
+impl From<&Pattern> for SparsePattern {
+    fn from(pattern: &Pattern) -> Self {
+        Self::from(pattern.as_slice())
+    }
+}
+
+impl From<&SparsePattern> for Pattern {
+    fn from(sparse: &SparsePattern) -> Self {
+        Pattern::new(sparse.to_dense())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{euclidean, rotate_pattern};
+
+    #[test]
+    fn matches_dense_operations() {
+        let dense = euclidean(16, 5, 0);
+        let sparse = SparsePattern::from(dense.as_slice());
+        assert_eq!(sparse.to_dense(), dense);
+        for rotation in -20..20 {
+            assert_eq!(
+                sparse.clone().rotate(rotation).to_dense(),
+                rotate_pattern(&dense, rotation as i32)
+            );
+        }
+
+        let other = SparsePattern::from(euclidean(16, 4, 1).as_slice());
+        let merged: Vec<bool> = dense
+            .iter()
+            .zip(other.to_dense())
+            .map(|(a, b)| *a || b)
+            .collect();
+        assert_eq!(sparse.merge(&other).to_dense(), merged);
+        let differing = dense
+            .iter()
+            .zip(other.to_dense())
+            .filter(|(a, b)| **a != *b)
+            .count();
+        assert_eq!(sparse.distance(&other), differing);
+    }
+
+    #[test]
+    fn normalizes_and_validates_onsets() {
+        let pattern = SparsePattern::new(8, [6, 0, 3, 3]);
+        assert_eq!(pattern.onsets(), &[0, 3, 6]);
+        assert!(pattern.is_pulse(3) && !pattern.is_pulse(4));
+        assert!(SparsePattern::try_new(4, [4]).is_err());
+
+        let short = SparsePattern::new(4, [1]);
+        assert_eq!(short.merge(&pattern).len(), 8);
+        assert_eq!(short.distance(&pattern), 4);
+        assert!(SparsePattern::default().rotate(3).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! Endless step streams for playback logic.
+//!
+//! [`Pattern::cycle_steps`] loops a pattern forever, tagging every step with
+//! the cycle it belongs to. The [`StepStream`] adaptors then bound, group and
+//! combine such streams without losing that position, so playback can be
+//! written with ordinary iterator chains:
+//!
+//! ```
+//! use euclidean_rhythm::{Meter, Pattern, StepStream};
+//!
+//! let kick = Pattern::euclidean(16, 4);
+//! let bars: Vec<_> = kick.cycle_steps().until_cycles(2).bars(&Meter::simple(4, 4)).collect();
+//! assert_eq!(bars.len(), 2);
+//! assert_eq!(bars[1].steps[0].cycle, 1);
+//! ```
+
+use crate::meter::Meter;
+use crate::pattern::Pattern;
+use alloc::vec::Vec;
+
+/// One step of a looping pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CycleStep {
+    /// How many times the pattern has looped before this step.
+    pub cycle: u64,
+    /// Step index within the pattern.
+    pub step: usize,
+    /// Whether the step is a pulse.
+    pub is_pulse: bool,
+}
+
+/// Endless iterator over a pattern's steps, returned by
+/// [`Pattern::cycle_steps`].
+#[derive(Debug, Clone)]
+pub struct CycleSteps<'a> {
+    pattern: &'a [bool],
+    cycle: u64,
+    step: usize,
+}
+
+impl Iterator for CycleSteps<'_> {
+    type Item = CycleStep;
+
+    fn next(&mut self) -> Option<CycleStep> {
+        let &is_pulse = self.pattern.get(self.step)?;
+        let item = CycleStep {
+            cycle: self.cycle,
+            step: self.step,
+            is_pulse,
+        };
+        self.step += 1;
+        if self.step == self.pattern.len() {
+            self.step = 0;
+            self.cycle += 1;
+        }
+        Some(item)
+    }
+}
+
+impl Pattern {
+    /// Loops the pattern forever. An empty pattern yields nothing.
+    pub fn cycle_steps(&self) -> CycleSteps<'_> {
+        CycleSteps {
+            pattern: self.as_slice(),
+            cycle: 0,
+            step: 0,
+        }
+    }
+}
+
+/// The steps of one bar, as yielded by [`StepStream::bars`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bar {
+    /// Zero-based bar number counted from the start of the stream.
+    pub index: u64,
+    /// The steps of the bar; only the last bar of a finite stream may be
+    /// shorter than the meter.
+    pub steps: Vec<CycleStep>,
+}
+
+/// Iterator adaptors for streams of [`CycleStep`]s.
+pub trait StepStream: Iterator<Item = CycleStep> + Sized {
+    /// Stops before the first step of cycle `cycles`, i.e. after `cycles`
+    /// complete loops.
+    fn until_cycles(self, cycles: u64) -> UntilCycles<Self> {
+        UntilCycles {
+            inner: self,
+            cycles,
+        }
+    }
+
+    /// Groups steps into bars of `meter`, counted from the start of the
+    /// stream. Bars run independently of the pattern cycle, so a 12-step
+    /// pattern in 4/4 drifts across the barlines.
+    fn bars(self, meter: &Meter) -> Bars<Self> {
+        Bars {
+            inner: self,
+            bar_len: meter.bar_len(),
+            index: 0,
+        }
+    }
+
+    /// Steps two streams in lockstep, for example two tracks of different
+    /// lengths. Ends when either stream ends.
+    fn zip_tracks<S: StepStream>(self, other: S) -> core::iter::Zip<Self, S> {
+        self.zip(other)
+    }
+}
+
+impl<I: Iterator<Item = CycleStep>> StepStream for I {}
+
+/// Iterator returned by [`StepStream::until_cycles`].
+#[derive(Debug, Clone)]
+pub struct UntilCycles<I> {
+    inner: I,
+    cycles: u64,
+}
+
+impl<I: Iterator<Item = CycleStep>> Iterator for UntilCycles<I> {
+    type Item = CycleStep;
+
+    fn next(&mut self) -> Option<CycleStep> {
+        self.inner.next().filter(|s| s.cycle < self.cycles)
+    }
+}
+
+/// Iterator returned by [`StepStream::bars`].
+#[derive(Debug, Clone)]
+pub struct Bars<I> {
+    inner: I,
+    bar_len: usize,
+    index: u64,
+}
+
+impl<I: Iterator<Item = CycleStep>> Iterator for Bars<I> {
+    type Item = Bar;
+
+    fn next(&mut self) -> Option<Bar> {
+        let steps: Vec<CycleStep> = self.inner.by_ref().take(self.bar_len).collect();
+        if steps.is_empty() {
+            return None;
+        }
+        let bar = Bar {
+            index: self.index,
+            steps,
+        };
+        self.index += 1;
+        Some(bar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_carry_position() {
+        let pattern = Pattern::euclidean(3, 1);
+        let steps: Vec<CycleStep> = pattern.cycle_steps().until_cycles(2).collect();
+        assert_eq!(steps.len(), 6);
+        assert_eq!(
+            steps[4],
+            CycleStep {
+                cycle: 1,
+                step: 1,
+                is_pulse: false
+            }
+        );
+        assert_eq!(Pattern::default().cycle_steps().next(), None);
+    }
+
+    #[test]
+    fn polymetric_bars() {
+        let kick = Pattern::euclidean(4, 1);
+        let bell = Pattern::euclidean(3, 1);
+        let together: Vec<(CycleStep, CycleStep)> = kick
+            .cycle_steps()
+            .zip_tracks(bell.cycle_steps())
+            .take(12)
+            .collect();
+        let both = together
+            .iter()
+            .filter(|(a, b)| a.is_pulse && b.is_pulse)
+            .count();
+        assert_eq!(both, 1);
+        assert_eq!(together[11].1.cycle, 3);
+
+        // Nine steps in 4-step bars: the last bar is short.
+        let bars: Vec<Bar> = bell
+            .cycle_steps()
+            .until_cycles(3)
+            .bars(&Meter::simple(2, 2))
+            .collect();
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[2].steps.len(), 1);
+        assert_eq!(bars[2].steps[0].cycle, 2);
+    }
+}
@@ -0,0 +1,195 @@
+//! How a Euclidean rhythm is built, step by step.
+//!
+//! Bjorklund's algorithm is Euclid's algorithm on `steps` and `pulses`: each
+//! round appends the leftover groups to the main groups until at most one
+//! leftover remains. [`structure`] records the groups each round produces
+//! as a tree, next to the continued fraction of `pulses / steps`, which
+//! counts the same subtractions.
+
+use crate::error::{Result, check_params, unwrap_or_panic};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A node in the grouping tree of a Euclidean rhythm.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Group {
+    /// A single pulse.
+    Pulse,
+    /// A single rest.
+    Rest,
+    /// Groups joined in one round of the algorithm.
+    Nested(Vec<Group>),
+}
+
+impl Group {
+    /// Number of steps in the group.
+    pub fn len(&self) -> usize {
+        match self {
+            Group::Pulse | Group::Rest => 1,
+            Group::Nested(children) => children.iter().map(Group::len).sum(),
+        }
+    }
+
+    /// Returns `true` if the group has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The steps of the group, with the nesting removed.
+    pub fn to_pattern(&self) -> Vec<bool> {
+        let mut pattern = Vec::with_capacity(self.len());
+        self.flatten_into(&mut pattern);
+        pattern
+    }
+
+    fn flatten_into(&self, pattern: &mut Vec<bool>) {
+        match self {
+            Group::Pulse => pattern.push(true),
+            Group::Rest => pattern.push(false),
+            Group::Nested(children) => children.iter().for_each(|c| c.flatten_into(pattern)),
+        }
+    }
+}
+
+/// Writes `x` for a pulse, `.` for a rest and parentheses around nested
+/// groups.
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Group::Pulse => f.write_str("x"),
+            Group::Rest => f.write_str("."),
+            Group::Nested(children) => {
+                f.write_str("(")?;
+                children.iter().try_for_each(|c| write!(f, "{c}"))?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// The derivation of E(`pulses`, `steps`), as returned by [`structure`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Structure {
+    /// The continued fraction `[a0; a1, a2, ...]` of `pulses / steps`.
+    pub continued_fraction: Vec<u64>,
+    /// The top-level groups after the last round; they concatenate to the
+    /// unrotated rhythm.
+    pub groups: Vec<Group>,
+}
+
+impl Structure {
+    /// Step counts of the top-level groups, e.g. `[3, 3, 2]` for E(3,8).
+    pub fn group_lengths(&self) -> Vec<usize> {
+        self.groups.iter().map(Group::len).collect()
+    }
+
+    /// The rhythm the groups spell out, equal to `euclidean(steps, pulses, 0)`.
+    pub fn to_pattern(&self) -> Vec<bool> {
+        self.groups.iter().flat_map(Group::to_pattern).collect()
+    }
+}
+
+/// Writes the top-level groups separated by spaces, e.g. `((x.).) ((x.).) (x.)`.
+impl fmt::Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, group) in self.groups.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{group}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Explains how E(`pulses`, `steps`) is derived.
+///
+/// # Panics
+/// Panics if `steps == 0` or `pulses > steps`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, structure};
+///
+/// let tresillo = structure(8, 3);
+/// // 3/8 = 0 + 1/(2 + 1/(1 + 1/2))
+/// assert_eq!(tresillo.continued_fraction, [0, 2, 1, 2]);
+/// assert_eq!(tresillo.group_lengths(), [3, 3, 2]);
+/// assert_eq!(tresillo.to_string(), "((x.).) ((x.).) (x.)");
+/// assert_eq!(tresillo.to_pattern(), euclidean(8, 3, 0));
+/// ```
+pub fn structure(steps: u8, pulses: u8) -> Structure {
+    unwrap_or_panic(try_structure(steps, pulses))
+}
+
+/// Like [`structure`], but reports invalid parameters as an error.
+///
+/// # Errors
+/// Fails if `steps == 0` or `pulses > steps`.
+pub fn try_structure(steps: u8, pulses: u8) -> Result<Structure> {
+    check_params(usize::from(steps), usize::from(pulses))?;
+    Ok(Structure {
+        continued_fraction: continued_fraction(u64::from(pulses), u64::from(steps)),
+        groups: groups(usize::from(steps), usize::from(pulses)),
+    })
+}
+
+fn continued_fraction(mut numerator: u64, mut denominator: u64) -> Vec<u64> {
+    let mut terms = Vec::new();
+    while denominator != 0 {
+        terms.push(numerator / denominator);
+        (numerator, denominator) = (denominator, numerator % denominator);
+    }
+    terms
+}
+
+/// Bjorklund's rounds, keeping each join as a nested group.
+fn groups(steps: usize, pulses: usize) -> Vec<Group> {
+    let mut groups = vec![Group::Pulse; pulses];
+    groups.resize(steps, Group::Rest);
+    if pulses == 0 || pulses == steps {
+        return groups;
+    }
+
+    let mut split = pulses;
+    while groups.len() - split > 1 {
+        let pairs = split.min(groups.len() - split);
+        let right: Vec<Group> = groups.drain(split..split + pairs).collect();
+        for (left, right) in groups.iter_mut().zip(right) {
+            let joined = core::mem::replace(left, Group::Rest);
+            *left = Group::Nested(vec![joined, right]);
+        }
+        split = pairs;
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, euclidean};
+
+    #[test]
+    fn groups_spell_the_euclidean_rhythm() {
+        for steps in 1..=32 {
+            for pulses in 0..=steps {
+                let derived = structure(steps, pulses);
+                assert_eq!(derived.to_pattern(), euclidean(steps, pulses, 0));
+                assert_eq!(
+                    derived.group_lengths().iter().sum::<usize>(),
+                    usize::from(steps)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn continued_fractions_and_errors() {
+        assert_eq!(structure(12, 5).continued_fraction, [0, 2, 2, 2]);
+        assert_eq!(structure(4, 4).continued_fraction, [1]);
+        assert_eq!(structure(4, 0).continued_fraction, [0]);
+        assert_eq!(structure(4, 0).to_string(), ". . . .");
+        assert_eq!(try_structure(0, 0), Err(Error::ZeroSteps));
+    }
+}
@@ -0,0 +1,259 @@
+//! Whole-kit exchange with Strudel and TidalCycles.
+//!
+//! [`to_strudel`] writes a [`PatternBank`] as one stacked Strudel pattern,
+//! using Euclidean mini-notation (`"bd(3,8,2)"`) for every track that is a
+//! rotated Euclidean rhythm and a plain step sequence (`"bd ~ bd bd"`) for
+//! the rest, so a groove can be pasted straight into a live-coding session:
+//!
+//! ```text
+//! stack(
+//!   s("bd(4,16)"),
+//!   s("hh(11,16,2)")
+//! )
+//! ```
+//!
+//! [`from_strudel`] reads that subset back, from Strudel or the Tidal form
+//! `stack [s "bd(4,16)", s "hh(11,16,2)"]`. Rotations follow the crate's
+//! [`euclidean`](crate::euclidean), which puts the pulses where Strudel and Tidal do.
+
+use crate::bank::PatternBank;
+use crate::error::{Error, Result, check_input_steps, unwrap_or_panic};
+use crate::{detect_euclidean, try_euclidean_large};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidParameter {
+        name: "strudel",
+        reason,
+    }
+}
+
+/// Writes every track of `bank` as one `s(...)` line of a Strudel `stack`,
+/// using the track names as sound names. An empty bank is `silence`.
+///
+/// # Panics
+/// Panics if a track cannot be written; see [`try_to_strudel`].
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{PatternBank, euclidean, to_strudel};
+///
+/// let bank = PatternBank::new()
+///     .with_track("bd", euclidean(16, 4, 0))
+///     .with_track("hh", euclidean(16, 11, 2))
+///     .with_track("cp", vec![false, false, true, true]);
+/// assert_eq!(
+///     to_strudel(&bank),
+///     "stack(\n  s(\"bd(4,16)\"),\n  s(\"hh(11,16,2)\"),\n  s(\"~ ~ cp cp\")\n)"
+/// );
+/// ```
+pub fn to_strudel(bank: &PatternBank) -> String {
+    unwrap_or_panic(try_to_strudel(bank))
+}
+
+/// Like [`to_strudel`], but returns an error for a bank that
+/// [`from_strudel`] could not read back.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] named `"strudel"` if a track name is
+/// not a plain sound name (letters, digits, `_`, `.` and `:`; no spaces,
+/// quotes or parentheses) or a track has no steps.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{PatternBank, euclidean, try_to_strudel};
+///
+/// let bank = PatternBank::new().with_track("bd", euclidean(8, 3, 0));
+/// assert_eq!(try_to_strudel(&bank)?, "stack(\n  s(\"bd(3,8)\")\n)");
+///
+/// let bank = PatternBank::new().with_track("big drum", euclidean(8, 3, 0));
+/// assert!(try_to_strudel(&bank).is_err());
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+pub fn try_to_strudel(bank: &PatternBank) -> Result<String> {
+    if bank.is_empty() {
+        return Ok(String::from("silence"));
+    }
+    let lines = bank
+        .tracks()
+        .iter()
+        .map(|track| {
+            let name = sound_name(&track.name)?;
+            if track.pattern.is_empty() {
+                return Err(invalid("a track needs at least one step"));
+            }
+            Ok(format!("  s(\"{}\")", mini_notation(&name, &track.pattern)))
+        })
+        .collect::<Result<Vec<String>>>()?;
+    Ok(format!("stack(\n{}\n)", lines.join(",\n")))
+}
+
+/// The mini-notation of one track: Euclidean if possible, else step by step.
+fn mini_notation(name: &str, pattern: &[bool]) -> String {
+    if let Some((pulses, steps, rotation)) = detect_euclidean(pattern) {
+        return match rotation {
+            0 => format!("{name}({pulses},{steps})"),
+            _ => format!("{name}({pulses},{steps},{rotation})"),
+        };
+    }
+    let cells: Vec<&str> = pattern
+        .iter()
+        .map(|&pulse| if pulse { name } else { "~" })
+        .collect();
+    cells.join(" ")
+}
+
+/// Reads a kit written in the subset [`to_strudel`] produces.
+///
+/// Every double-quoted string is one track, in either Euclidean form
+/// (`"bd(3,8)"`, `"bd(3,8,2)"`; a negative rotation rotates right) or as a
+/// sequence of one sound and rests (`~` or `-`), and gives the track its
+/// name. The surrounding `stack(...)`, `s(...)`, `sound(...)` or Tidal
+/// `stack [s "..."]` is not checked. `silence` is an empty bank.
+///
+/// # Errors
+/// Returns [`Error::InvalidParameter`] named `"strudel"` for strings outside
+/// the subset (such as several sounds, subdivisions or an unclosed string),
+/// [`Error::TooManySteps`] for a Euclidean track longer than
+/// [`MAX_INPUT_STEPS`](crate::MAX_INPUT_STEPS), and the usual parameter errors for invalid
+/// Euclidean parameters.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::{euclidean, from_strudel};
+///
+/// let bank = from_strudel(r#"d1 $ stack [s "bd(3,8)", s "hh(5,8,-1)", s "sn ~ ~ ~"]"#)?;
+/// assert_eq!(bank.get("bd").unwrap().pattern, euclidean(8, 3, 0));
+/// assert_eq!(bank.get("hh").unwrap().pattern, euclidean(8, 5, 7));
+/// assert_eq!(bank.get("sn").unwrap().pattern, [true, false, false, false]);
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+pub fn from_strudel(text: &str) -> Result<PatternBank> {
+    let mut bank = PatternBank::new();
+    let mut quoted = text.split('"');
+    // Pieces alternate between code and the contents of quotes.
+    quoted.next();
+    while let Some(track) = quoted.next() {
+        if quoted.next().is_none() {
+            return Err(invalid("unclosed string"));
+        }
+        let (name, pattern) = parse_track(track)?;
+        bank.push(name, pattern);
+    }
+    if bank.is_empty() && text.trim() != "silence" {
+        return Err(invalid("no quoted patterns"));
+    }
+    Ok(bank)
+}
+
+fn parse_track(track: &str) -> Result<(String, Vec<bool>)> {
+    let track = track.trim();
+    if let Some((name, args)) = track.split_once('(') {
+        let args = args
+            .strip_suffix(')')
+            .ok_or(invalid("expected name(pulses,steps[,rotation])"))?;
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        let number = |arg: &str| arg.parse().map_err(|_| invalid("malformed number"));
+        let (pulses, steps, rotation): (usize, usize, i64) = match args[..] {
+            [pulses, steps] => (number(pulses)?, number(steps)?, 0),
+            [pulses, steps, rotation] => (
+                number(pulses)?,
+                number(steps)?,
+                rotation.parse().map_err(|_| invalid("malformed number"))?,
+            ),
+            _ => return Err(invalid("expected two or three Euclidean arguments")),
+        };
+        check_input_steps(steps)?;
+        let rotation = if steps == 0 {
+            0
+        } else {
+            rotation.rem_euclid(steps as i64) as usize
+        };
+        let pattern = try_euclidean_large(steps, pulses, rotation)?;
+        return Ok((sound_name(name.trim())?, pattern));
+    }
+
+    let mut name: Option<&str> = None;
+    let mut pattern = Vec::new();
+    for cell in track.split_whitespace() {
+        if cell == "~" || cell == "-" {
+            pattern.push(false);
+            continue;
+        }
+        if name.is_some_and(|name| name != cell) {
+            return Err(invalid("one sound per track"));
+        }
+        name = Some(cell);
+        pattern.push(true);
+    }
+    let name = name.ok_or(invalid("a track needs a sound"))?;
+    Ok((sound_name(name)?, pattern))
+}
+
+/// Checks that `name` is a plain sound name, not more mini-notation.
+fn sound_name(name: &str) -> Result<String> {
+    let plain = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | ':');
+    if name.is_empty() || !name.chars().all(plain) {
+        return Err(invalid("unsupported mini-notation"));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MAX_INPUT_STEPS;
+    use crate::{euclidean, euclidean_large};
+
+    #[test]
+    fn kits_round_trip() {
+        let bank = PatternBank::new()
+            .with_track("bd", euclidean(16, 4, 0))
+            .with_track("hh", euclidean(12, 7, 5))
+            .with_track("sd:2", vec![false, true, false, false, false, true])
+            .with_track("oh", vec![false; 4])
+            .with_track("rim", euclidean_large(300, 7, 20));
+        let text = to_strudel(&bank);
+        assert!(text.contains("s(\"oh(0,4)\")"));
+        assert!(text.contains("s(\"rim(7,300,20)\")"));
+        assert_eq!(from_strudel(&text), Ok(bank));
+        assert_eq!(to_strudel(&PatternBank::new()), "silence");
+        assert_eq!(from_strudel(" silence "), Ok(PatternBank::new()));
+
+        // Tracks that would not read back are refused rather than written.
+        for name in ["big drum", "bd\"", "bd(", ""] {
+            let bank = PatternBank::new().with_track(name, euclidean(8, 3, 0));
+            assert!(try_to_strudel(&bank).is_err(), "{name}");
+        }
+        let bank = PatternBank::new().with_track("bd", Vec::new());
+        assert_eq!(
+            try_to_strudel(&bank),
+            Err(invalid("a track needs at least one step"))
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read() {
+        for text in [
+            "",
+            "s(\"bd(3,8)",
+            "s(\"bd sn\")",
+            "s(\"bd*2 ~\")",
+            "s(\"[bd bd] ~\")",
+            "s(\"~ ~\")",
+            "s(\"bd(3)\")",
+            "s(\"bd(9,8)\")",
+        ] {
+            assert!(from_strudel(text).is_err(), "{text}");
+        }
+        assert_eq!(
+            from_strudel("s(\"bd(3,1000000000000)\")"),
+            Err(Error::TooManySteps {
+                steps: 1_000_000_000_000,
+                max: MAX_INPUT_STEPS
+            })
+        );
+    }
+}
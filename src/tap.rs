@@ -0,0 +1,213 @@
+//! Tapped-rhythm capture.
+//!
+//! Feed [`TapCapture`] the times of key presses while someone taps a rhythm,
+//! then ask it for the step grid, tempo, quantized pattern and closest
+//! Euclidean rhythm. Tap one loop of the rhythm and finish with a tap on the
+//! first beat of the next loop; that closing tap sets the loop length and is
+//! not part of the pattern.
+
+use crate::fit::{EuclideanFit, fit_euclidean};
+
+/// Taps closer together than this are treated as one bouncy key press.
+const DEBOUNCE_SECONDS: f64 = 0.03;
+/// Largest deviation from the grid, in steps, for a grid to explain a tap.
+const GRID_TOLERANCE: f64 = 0.2;
+/// The finest grid tried is the shortest gap between taps divided by this.
+const MAX_SUBDIVISION: u32 = 4;
+
+/// Collects tap times and turns them into a pattern.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::TapCapture;
+///
+/// let mut capture = TapCapture::new();
+/// // Tresillo in sixteenths at 120 BPM, then the closing tap.
+/// for time in [10.0, 10.376, 10.749, 11.002] {
+///     capture.tap(time);
+/// }
+/// let rhythm = capture.capture().unwrap();
+/// assert_eq!(rhythm.pattern, [true, false, false, true, false, false, true, false]);
+/// assert!((rhythm.bpm - 120.0).abs() < 1.0);
+/// let fit = rhythm.fit.unwrap();
+/// assert_eq!((fit.steps, fit.pulses, fit.rotation), (8, 3, 0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapCapture {
+    taps: Vec<f64>,
+    steps_per_beat: u32,
+}
+
+/// The result of [`TapCapture::capture`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedRhythm {
+    /// Estimated duration of one step, in seconds.
+    pub step_seconds: f64,
+    /// Tempo implied by the step duration and the capture's steps per beat.
+    pub bpm: f64,
+    /// The tapped loop quantized to the step grid.
+    pub pattern: Vec<bool>,
+    /// One `(step, offset)` pair per tap in the loop: the step it was
+    /// quantized to and how far off the grid it was, in steps (negative is
+    /// early). The first tap defines the grid, so its offset is 0. With
+    /// `step_seconds` these restore the tap times, keeping the feel of the
+    /// performance as a groove.
+    pub offsets: Vec<(usize, f64)>,
+    /// The closest Euclidean rhythm, or `None` if the loop is longer than
+    /// 255 steps.
+    pub fit: Option<EuclideanFit>,
+}
+
+impl Default for TapCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TapCapture {
+    /// Creates an empty capture that reads the grid as sixteenth notes
+    /// (four steps per beat) when reporting the tempo.
+    pub fn new() -> Self {
+        Self {
+            taps: Vec::new(),
+            steps_per_beat: 4,
+        }
+    }
+
+    /// Sets how many grid steps make one beat, for the tempo estimate.
+    ///
+    /// # Panics
+    /// Panics if `steps_per_beat == 0`.
+    #[must_use]
+    pub fn with_steps_per_beat(mut self, steps_per_beat: u32) -> Self {
+        assert!(
+            steps_per_beat > 0,
+            "steps_per_beat must be greater than zero"
+        );
+        self.steps_per_beat = steps_per_beat;
+        self
+    }
+
+    /// Records a tap at `time` seconds on any monotonic clock.
+    ///
+    /// Taps that are not later than the previous one, or that follow it by
+    /// less than 30 ms (key bounce), are ignored.
+    pub fn tap(&mut self, time: f64) {
+        if self
+            .taps
+            .last()
+            .is_none_or(|&last| time - last >= DEBOUNCE_SECONDS)
+        {
+            self.taps.push(time);
+        }
+    }
+
+    /// The accepted tap times.
+    pub fn taps(&self) -> &[f64] {
+        &self.taps
+    }
+
+    /// Forgets all taps.
+    pub fn clear(&mut self) {
+        self.taps.clear();
+    }
+
+    /// Estimates the grid and quantizes the taps.
+    ///
+    /// The grid step is the longest subdivision (up to a quarter) of the
+    /// shortest gap between taps that puts every tap within a fifth of a
+    /// step of the grid; if none does, the best-fitting subdivision wins.
+    /// Returns `None` until at least two taps have been recorded.
+    pub fn capture(&self) -> Option<CapturedRhythm> {
+        let (&first, &last) = (self.taps.first()?, self.taps.last()?);
+        let gaps: Vec<f64> = self.taps.windows(2).map(|w| w[1] - w[0]).collect();
+        let shortest = gaps.iter().copied().reduce(f64::min)?;
+
+        let grid_error = |step: f64| {
+            gaps.iter()
+                .map(|&gap| (gap / step - (gap / step).round()).abs())
+                .fold(0.0, f64::max)
+        };
+        let subdivisions = (1..=MAX_SUBDIVISION).map(|d| shortest / f64::from(d));
+        let rough_step = subdivisions
+            .clone()
+            .find(|&step| grid_error(step) <= GRID_TOLERANCE)
+            .or_else(|| subdivisions.min_by(|a, b| grid_error(*a).total_cmp(&grid_error(*b))))?;
+
+        // Count whole steps per gap, then spread the timing error over the loop.
+        let counts: Vec<usize> = gaps
+            .iter()
+            .map(|&gap| ((gap / rough_step).round() as usize).max(1))
+            .collect();
+        let steps: usize = counts.iter().sum();
+        let step_seconds = (last - first) / steps as f64;
+        let mut pattern = vec![false; steps];
+        let mut offsets = Vec::with_capacity(counts.len());
+        let mut position = 0;
+        for (count, &time) in counts.into_iter().zip(&self.taps) {
+            pattern[position] = true;
+            offsets.push((position, (time - first) / step_seconds - position as f64));
+            position += count;
+        }
+
+        Some(CapturedRhythm {
+            step_seconds,
+            bpm: 60.0 / (step_seconds * f64::from(self.steps_per_beat)),
+            fit: fit_euclidean(&pattern),
+            pattern,
+            offsets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_two_taps_and_debounces() {
+        let mut capture = TapCapture::new();
+        assert_eq!(capture.capture(), None);
+        capture.tap(1.0);
+        capture.tap(1.01);
+        capture.tap(0.5);
+        assert_eq!(capture.taps(), &[1.0]);
+        assert_eq!(capture.capture(), None);
+
+        capture.tap(1.5);
+        let rhythm = capture.capture().unwrap();
+        assert_eq!(rhythm.pattern, [true]);
+        assert!((rhythm.bpm - 30.0).abs() < 1e-9);
+        capture.clear();
+        assert!(capture.taps().is_empty());
+    }
+
+    #[test]
+    fn finds_a_subdivided_grid() {
+        // E(5,8) tapped as eighth notes at 90 BPM: x.xx.xx. then the closing tap.
+        let eighth = 60.0 / 90.0 / 2.0;
+        let mut capture = TapCapture::new().with_steps_per_beat(2);
+        for step in [0, 2, 3, 5, 6, 8] {
+            capture.tap(step as f64 * eighth + if step % 2 == 0 { 0.01 } else { -0.01 });
+        }
+        let rhythm = capture.capture().unwrap();
+        assert_eq!(
+            rhythm.pattern,
+            [true, false, true, true, false, true, true, false]
+        );
+        assert!((rhythm.bpm - 90.0).abs() < 2.0);
+        let fit = rhythm.fit.unwrap();
+        assert_eq!((fit.pulses, fit.distance), (5, 0));
+        // The pushed and dragged taps come back out of the offsets.
+        let restored: Vec<f64> = rhythm
+            .offsets
+            .iter()
+            .map(|&(step, offset)| (step as f64 + offset) * rhythm.step_seconds)
+            .collect();
+        for (restored, tap) in restored.iter().zip(capture.taps()) {
+            assert!((restored + capture.taps()[0] - tap).abs() < 1e-9);
+        }
+        assert_eq!(rhythm.offsets[1].0, 2);
+        assert!(rhythm.offsets[3].1 < 0.0);
+    }
+}
@@ -0,0 +1,193 @@
+//! Conditional trigs and parameter locks.
+//!
+//! A [`TrigCondition`] decides, each time the sequencer reaches a pulse,
+//! whether that pulse actually plays. Conditions on a few steps turn a static
+//! Euclidean loop into a phrase that evolves over several cycles, in the
+//! style of Elektron trig conditions.
+//!
+//! A [`ParamLock`] overrides the track's output settings on a single step.
+
+use crate::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// When a pulse plays.
+///
+/// Parses from and displays as the usual groovebox notation: `1:2`, `3:4`,
+/// `fill`, `not-fill` (or `!fill`), `25%` and `always`.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::TrigCondition;
+///
+/// let second_of_four: TrigCondition = "2:4".parse()?;
+/// let plays: Vec<bool> = (0..8).map(|cycle| second_of_four.is_met(cycle, false, || 0.0)).collect();
+/// assert_eq!(plays, [false, true, false, false, false, true, false, false]);
+/// # Ok::<(), euclidean_rhythm::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TrigCondition {
+    /// Plays every time.
+    #[default]
+    Always,
+    /// Plays on the `a`-th of every `b` cycles (`1 <= a <= b`).
+    Cycle {
+        /// Which cycle of the group plays, 1-based.
+        a: u16,
+        /// Length of the group in cycles.
+        b: u16,
+    },
+    /// Plays only while fill is active.
+    Fill,
+    /// Plays only while fill is not active.
+    NotFill,
+    /// Plays with the given chance in percent (0-100).
+    Probability(u8),
+}
+
+impl TrigCondition {
+    /// Evaluates the condition for a pulse in track cycle `cycle` (0-based).
+    ///
+    /// `roll` is only called for [`Probability`](Self::Probability) and
+    /// must return a uniform value in `0.0..1.0`.
+    pub fn is_met(&self, cycle: u64, fill: bool, roll: impl FnOnce() -> f64) -> bool {
+        match *self {
+            TrigCondition::Always => true,
+            TrigCondition::Cycle { a, b } => {
+                b > 0 && cycle % u64::from(b) == u64::from(a.saturating_sub(1))
+            }
+            TrigCondition::Fill => fill,
+            TrigCondition::NotFill => !fill,
+            TrigCondition::Probability(percent) => roll() * 100.0 < f64::from(percent),
+        }
+    }
+}
+
+impl FromStr for TrigCondition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = |reason| Error::InvalidParameter {
+            name: "trig condition",
+            reason,
+        };
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "" | "always" => return Ok(TrigCondition::Always),
+            "fill" => return Ok(TrigCondition::Fill),
+            "not-fill" | "!fill" => return Ok(TrigCondition::NotFill),
+            _ => {}
+        }
+        if let Some(percent) = s.strip_suffix('%') {
+            return match percent.trim().parse::<u8>() {
+                Ok(p) if p <= 100 => Ok(TrigCondition::Probability(p)),
+                _ => Err(invalid("probability must be 0% to 100%")),
+            };
+        }
+        let (a, b) = s
+            .split_once(':')
+            .ok_or(invalid("expected A:B, fill, not-fill, N% or always"))?;
+        match (a.trim().parse::<u16>(), b.trim().parse::<u16>()) {
+            (Ok(a), Ok(b)) if 1 <= a && a <= b => Ok(TrigCondition::Cycle { a, b }),
+            _ => Err(invalid("A:B needs 1 <= A <= B")),
+        }
+    }
+}
+
+impl fmt::Display for TrigCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrigCondition::Always => f.write_str("always"),
+            TrigCondition::Cycle { a, b } => write!(f, "{a}:{b}"),
+            TrigCondition::Fill => f.write_str("fill"),
+            TrigCondition::NotFill => f.write_str("not-fill"),
+            TrigCondition::Probability(p) => write!(f, "{p}%"),
+        }
+    }
+}
+
+/// Per-step overrides of a track's output settings. Unset fields fall back
+/// to the track.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::ParamLock;
+///
+/// // A quiet triple hit on an otherwise plain step.
+/// let flam = ParamLock::default().velocity(60).ratchet(3);
+/// assert_eq!(flam.ratchet, Some(3));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ParamLock {
+    /// Velocity of the step, 1-127.
+    pub velocity: Option<u8>,
+    /// Note of the step, 0-127.
+    pub note: Option<u8>,
+    /// Number of evenly spaced hits within the step (1 = a single hit).
+    pub ratchet: Option<u8>,
+    /// Chance in percent (0-100) that the step plays, checked in addition to
+    /// its [`TrigCondition`].
+    pub probability: Option<u8>,
+}
+
+impl ParamLock {
+    /// Locks the velocity.
+    #[must_use]
+    pub fn velocity(mut self, velocity: u8) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+
+    /// Locks the note.
+    #[must_use]
+    pub fn note(mut self, note: u8) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    /// Locks the ratchet count.
+    #[must_use]
+    pub fn ratchet(mut self, ratchet: u8) -> Self {
+        self.ratchet = Some(ratchet);
+        self
+    }
+
+    /// Locks the probability, in percent.
+    #[must_use]
+    pub fn probability(mut self, percent: u8) -> Self {
+        self.probability = Some(percent);
+        self
+    }
+
+    /// Returns `true` if no field is locked.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        for text in ["always", "1:2", "3:4", "fill", "not-fill", "40%"] {
+            let condition: TrigCondition = text.parse().unwrap();
+            assert_eq!(condition.to_string(), text);
+        }
+        assert_eq!("!FILL".parse(), Ok(TrigCondition::NotFill));
+        assert!("3:2".parse::<TrigCondition>().is_err());
+        assert!("0:4".parse::<TrigCondition>().is_err());
+        assert!("120%".parse::<TrigCondition>().is_err());
+        assert!("sometimes".parse::<TrigCondition>().is_err());
+    }
+
+    #[test]
+    fn evaluates_fill_and_probability() {
+        assert!(TrigCondition::Fill.is_met(0, true, || 0.0));
+        assert!(!TrigCondition::NotFill.is_met(0, true, || 0.0));
+        assert!(TrigCondition::Probability(50).is_met(0, false, || 0.49));
+        assert!(!TrigCondition::Probability(50).is_met(0, false, || 0.5));
+        assert!(!TrigCondition::Probability(0).is_met(0, false, || 0.0));
+    }
+}
@@ -0,0 +1,157 @@
+//! Creative-coding helpers (enabled with the `viz` feature).
+//!
+//! Polar layout, playhead interpolation and intensity values for drawing
+//! Euclidean rhythms as circles, independent of any graphics framework.
+//!
+//! Angles are in radians, measured clockwise from twelve o'clock, so step 0 is
+//! at the top of the circle. Positions use y-up coordinates centred on the
+//! origin (as in nannou); negate `y` for y-down canvases.
+
+use std::f32::consts::TAU;
+
+/// Angle of `step` in a cycle of `steps` steps.
+pub fn step_angle(step: f32, steps: usize) -> f32 {
+    if steps == 0 {
+        return 0.0;
+    }
+    TAU * step / steps as f32
+}
+
+/// Position of `step` on a circle of the given radius.
+pub fn step_position(step: f32, steps: usize, radius: f32) -> (f32, f32) {
+    let angle = step_angle(step, steps);
+    (radius * angle.sin(), radius * angle.cos())
+}
+
+/// Angles of every onset of a pattern.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::euclidean;
+/// use euclidean_rhythm::viz::onset_angles;
+/// use std::f32::consts::PI;
+///
+/// let angles = onset_angles(&euclidean(4, 2, 0));
+/// assert_eq!(angles, vec![0.0, PI]);
+/// ```
+pub fn onset_angles(pattern: &[bool]) -> Vec<f32> {
+    onsets(pattern)
+        .map(|i| step_angle(i as f32, pattern.len()))
+        .collect()
+}
+
+/// Positions of every onset of a pattern on a circle.
+pub fn onset_positions(pattern: &[bool], radius: f32) -> Vec<(f32, f32)> {
+    onsets(pattern)
+        .map(|i| step_position(i as f32, pattern.len(), radius))
+        .collect()
+}
+
+/// Where the playhead is at a moment in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playhead {
+    /// Index of the current step.
+    pub step: usize,
+    /// Progress through the current step, `0.0..1.0`.
+    pub fraction: f32,
+    /// Continuous angle of the playhead, for smooth animation.
+    pub angle: f32,
+}
+
+/// Computes the playhead for a frame from the elapsed time.
+///
+/// `steps_per_beat` is 4 for sixteenth-note steps. Returns step 0 for an
+/// empty cycle or a non-positive tempo.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::viz::playhead;
+///
+/// // 120 BPM sixteenths: 0.125 s per step.
+/// let head = playhead(0.3125, 120.0, 4, 16);
+/// assert_eq!(head.step, 2);
+/// assert!((head.fraction - 0.5).abs() < 1e-6);
+/// ```
+pub fn playhead(seconds: f64, bpm: f64, steps_per_beat: u32, steps: usize) -> Playhead {
+    if steps == 0 || bpm <= 0.0 || steps_per_beat == 0 {
+        return Playhead {
+            step: 0,
+            fraction: 0.0,
+            angle: 0.0,
+        };
+    }
+    let position = (seconds.max(0.0) * bpm / 60.0 * f64::from(steps_per_beat)) % steps as f64;
+    let step = (position.floor() as usize).min(steps - 1);
+    Playhead {
+        step,
+        fraction: (position - step as f64) as f32,
+        angle: step_angle(position as f32, steps),
+    }
+}
+
+/// Brightness-style intensity in `0.0..=1.0` at a (fractional) playhead
+/// position: 1.0 the moment an onset is hit, then decaying exponentially by
+/// `decay` per step until the next onset. Wraps around the cycle.
+///
+/// # Examples
+/// ```
+/// use euclidean_rhythm::viz::intensity;
+///
+/// let pattern = [true, false, false, false];
+/// assert_eq!(intensity(&pattern, 0.0, 0.5), 1.0);
+/// assert_eq!(intensity(&pattern, 2.0, 0.5), 0.25);
+/// ```
+pub fn intensity(pattern: &[bool], position: f32, decay: f32) -> f32 {
+    let len = pattern.len();
+    if len == 0 || !pattern.contains(&true) {
+        return 0.0;
+    }
+    let position = position.rem_euclid(len as f32);
+    let current = (position.floor() as usize).min(len - 1);
+    // Steps since the most recent onset at or before the current step.
+    let back = (0..len)
+        .find(|&d| pattern[(current + len - d) % len])
+        .unwrap_or(0);
+    let elapsed = back as f32 + (position - current as f32);
+    decay.clamp(0.0, 1.0).powf(elapsed)
+}
+
+fn onsets(pattern: &[bool]) -> impl Iterator<Item = usize> + '_ {
+    pattern
+        .iter()
+        .enumerate()
+        .filter(|&(_, &p)| p)
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclidean;
+
+    #[test]
+    fn positions_are_clockwise_from_top() {
+        let (x, y) = step_position(0.0, 4, 1.0);
+        assert!(x.abs() < 1e-6 && (y - 1.0).abs() < 1e-6);
+        let (x, y) = step_position(1.0, 4, 1.0);
+        assert!((x - 1.0).abs() < 1e-6 && y.abs() < 1e-6);
+        assert_eq!(onset_positions(&euclidean(8, 3, 0), 2.0).len(), 3);
+    }
+
+    #[test]
+    fn playhead_wraps() {
+        let head = playhead(2.0, 120.0, 4, 16);
+        assert_eq!(head.step, 0);
+        assert!(head.fraction.abs() < 1e-6);
+        assert_eq!(playhead(1.0, 0.0, 4, 16).step, 0);
+    }
+
+    #[test]
+    fn intensity_decays_across_loop() {
+        let pattern = [false, false, true, false];
+        assert_eq!(intensity(&pattern, 2.0, 0.5), 1.0);
+        // Step 0 is two steps after the onset at step 2, across the loop.
+        assert_eq!(intensity(&pattern, 0.0, 0.5), 0.25);
+        assert_eq!(intensity(&[false; 4], 1.0, 0.5), 0.0);
+    }
+}
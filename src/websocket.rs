@@ -0,0 +1,621 @@
+//! WebSocket event broadcast (enabled with the `websocket` feature).
+//!
+//! [`WsBroadcaster`] is a [`StepListener`] that sends every step event as a
+//! JSON text message to all connected WebSocket clients, so browser
+//! visualizers and remote UIs can follow the sequencer without linking
+//! against it. The handshake and framing are implemented by hand on
+//! `std::net`, so the feature adds no dependencies.
+//!
+//! The server only talks; messages from clients are read and discarded,
+//! except that pings are answered and a close frame is acknowledged before
+//! the connection is dropped.
+//!
+//! Messages look like this:
+//!
+//! ```text
+//! {"type":"step","track":0,"step":4,"cycle":1,"tick":20,"note":36,"velocity":100,"ratchet":1,"fill":false}
+//! {"type":"transport","playing":true,"bpm":120,"position":20}
+//! ```
+
+use crate::sequencer::{StepEvent, StepListener};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// The GUID every server appends to the client key (RFC 6455, section 1.3).
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Longest handshake request accepted, in bytes.
+const MAX_REQUEST: usize = 8192;
+
+/// How long a connection may take to send its upgrade request by default.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many connections may be waiting to finish their handshake by default.
+const MAX_PENDING: usize = 64;
+
+/// Longest frame payload accepted from a client, in bytes.
+const MAX_CLIENT_FRAME: usize = 8192;
+
+/// Frame opcodes (RFC 6455, section 5.2).
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+/// Broadcasts step and transport events to WebSocket clients.
+///
+/// Nothing blocks on the network while playing: new connections are picked
+/// up whenever an event is sent (or on [`accept`](Self::accept)), and their
+/// handshakes are read a little at a time over as many calls as it takes.
+/// A connection that has not sent a valid upgrade request within the
+/// handshake timeout, and a client that disconnects, sends something
+/// malformed or cannot keep up, are dropped. At most 64 connections wait
+/// for their handshake at a time (see
+/// [`with_max_pending`](Self::with_max_pending)); further ones are closed
+/// straight away until some finish or time out.
+///
+/// # Examples
+/// ```no_run
+/// use euclidean_rhythm::websocket::WsBroadcaster;
+/// use euclidean_rhythm::{Sequencer, SequencerTrack};
+///
+/// let mut server = WsBroadcaster::bind("0.0.0.0:9001")?;
+/// let mut seq = Sequencer::new(120.0).with_track(SequencerTrack::new("kick", 16, 4, 0));
+/// server.send_transport(true, seq.bpm(), seq.position());
+/// seq.advance_with(1.0, &mut server);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct WsBroadcaster {
+    listener: TcpListener,
+    pending: Vec<Pending>,
+    clients: Vec<Client>,
+    handshake_timeout: Duration,
+    max_pending: usize,
+}
+
+/// A connection whose upgrade request has not fully arrived yet.
+#[derive(Debug)]
+struct Pending {
+    stream: TcpStream,
+    request: Vec<u8>,
+    since: Instant,
+}
+
+/// A connection that has completed its handshake.
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    /// Bytes received that do not yet make a whole frame.
+    incoming: Vec<u8>,
+}
+
+/// What reading a pending connection found.
+enum Progress {
+    Waiting,
+    Ready,
+    Failed,
+}
+
+impl WsBroadcaster {
+    /// Listens for WebSocket connections on `address`.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            pending: Vec::new(),
+            clients: Vec::new(),
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+            max_pending: MAX_PENDING,
+        })
+    }
+
+    /// Sets how long a new connection may take to send its upgrade request
+    /// (5 seconds by default).
+    #[must_use]
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Sets how many connections may be waiting to finish their handshake
+    /// at once (64 by default, at least 1). Connections arriving while that
+    /// many are waiting are closed.
+    #[must_use]
+    pub fn with_max_pending(mut self, connections: usize) -> Self {
+        self.max_pending = connections.max(1);
+        self
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Picks up new connections, reads whatever part of their upgrade
+    /// requests has arrived without waiting for more, and returns the number
+    /// of connected clients. Connections that are not valid WebSocket
+    /// upgrades, or that time out, are closed.
+    pub fn accept(&mut self) -> usize {
+        while let Ok((stream, _)) = self.listener.accept() {
+            // Dropping the stream closes it.
+            if self.pending.len() < self.max_pending && stream.set_nonblocking(true).is_ok() {
+                self.pending.push(Pending {
+                    stream,
+                    request: Vec::new(),
+                    since: Instant::now(),
+                });
+            }
+        }
+        let timeout = self.handshake_timeout;
+        let mut index = 0;
+        while index < self.pending.len() {
+            let connection = &mut self.pending[index];
+            match connection.read() {
+                Progress::Waiting if connection.since.elapsed() < timeout => index += 1,
+                Progress::Waiting | Progress::Failed => {
+                    self.pending.swap_remove(index);
+                }
+                Progress::Ready => {
+                    let mut connection = self.pending.swap_remove(index);
+                    if let Ok(incoming) = connection.answer() {
+                        self.clients.push(Client {
+                            stream: connection.stream,
+                            incoming,
+                        });
+                    }
+                }
+            }
+        }
+        self.clients.len()
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Sends one step event to every client.
+    pub fn send_step(&mut self, event: &StepEvent) {
+        self.broadcast(&format!(
+            r#"{{"type":"step","track":{},"step":{},"cycle":{},"tick":{},"note":{},"velocity":{},"ratchet":{},"fill":{}}}"#,
+            event.track,
+            event.step,
+            event.cycle,
+            event.tick,
+            event.note,
+            event.velocity,
+            event.ratchet,
+            event.fill
+        ));
+    }
+
+    /// Sends the transport state: whether it is playing, the tempo and the
+    /// sequencer position in steps. A tempo that is not finite is sent as
+    /// `null`.
+    pub fn send_transport(&mut self, playing: bool, bpm: f64, position: u64) {
+        let bpm = if bpm.is_finite() {
+            bpm.to_string()
+        } else {
+            "null".to_string()
+        };
+        self.broadcast(&format!(
+            r#"{{"type":"transport","playing":{playing},"bpm":{bpm},"position":{position}}}"#
+        ));
+    }
+
+    fn broadcast(&mut self, text: &str) {
+        self.accept();
+        let frame = frame(TEXT, text.as_bytes());
+        self.clients
+            .retain_mut(|client| client.receive() && client.stream.write_all(&frame).is_ok());
+    }
+}
+
+impl StepListener for WsBroadcaster {
+    fn on_step(&mut self, event: &StepEvent) {
+        self.send_step(event);
+    }
+}
+
+impl Pending {
+    /// Reads what has arrived of the upgrade request without blocking.
+    fn read(&mut self) -> Progress {
+        let mut buf = [0u8; 1024];
+        loop {
+            if self.request.windows(4).any(|w| w == b"\r\n\r\n") {
+                return Progress::Ready;
+            }
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Progress::Failed,
+                Ok(len) if self.request.len() + len > MAX_REQUEST => return Progress::Failed,
+                Ok(len) => self.request.extend_from_slice(&buf[..len]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Progress::Waiting,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return Progress::Failed,
+            }
+        }
+    }
+
+    /// Checks the complete upgrade request and sends the response. Returns
+    /// whatever the client sent after the request.
+    fn answer(&mut self) -> io::Result<Vec<u8>> {
+        let end = self
+            .request
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .map_or(self.request.len(), |at| at + 4);
+        let key = upgrade_key(&String::from_utf8_lossy(&self.request[..end]))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket upgrade"))?;
+        write!(
+            self.stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        )?;
+        Ok(self.request.split_off(end))
+    }
+}
+
+impl Client {
+    /// Reads whatever the client has sent without blocking, answers pings
+    /// and discards other messages. Returns `false` once the connection
+    /// should be dropped: it closed, failed or broke the protocol.
+    fn receive(&mut self) -> bool {
+        let mut buf = [0u8; 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return false,
+                Ok(len) => self.incoming.extend_from_slice(&buf[..len]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+            if self.incoming.len() > MAX_CLIENT_FRAME {
+                break;
+            }
+        }
+        loop {
+            let (opcode, payload, len) = match client_frame(&self.incoming) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return true,
+                Err(()) => return false,
+            };
+            self.incoming.drain(..len);
+            match opcode {
+                PING if self.stream.write_all(&frame(PONG, &payload)).is_err() => return false,
+                // Echo the status code, then hang up.
+                CLOSE => {
+                    let status = &payload[..payload.len().min(2)];
+                    let _ = self.stream.write_all(&frame(CLOSE, status));
+                    return false;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The `Sec-WebSocket-Key` of an HTTP request, if it is a `GET` asking to
+/// upgrade to WebSocket.
+fn upgrade_key(request: &str) -> Option<String> {
+    let mut lines = request.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let is_get = request_line.next() == Some("GET")
+        && request_line.next().is_some()
+        && request_line
+            .next()
+            .is_some_and(|version| version.starts_with("HTTP/1."));
+    if !is_get {
+        return None;
+    }
+    let headers: Vec<(&str, &str)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect();
+    let header = |wanted: &str| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|&(_, value)| value)
+    };
+    let upgrades = header("upgrade").is_some_and(|value| {
+        value
+            .split(',')
+            .any(|protocol| protocol.trim().eq_ignore_ascii_case("websocket"))
+    });
+    let key = header("sec-websocket-key").filter(|key| !key.is_empty())?;
+    upgrades.then(|| key.to_string())
+}
+
+/// The `Sec-WebSocket-Accept` value for a client key.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{HANDSHAKE_GUID}").as_bytes()))
+}
+
+/// Wraps `payload` in a single unmasked server-to-client frame.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let len = payload.len();
+    let mut frame = Vec::with_capacity(len + 10);
+    frame.push(0x80 | opcode); // FIN + opcode
+    if len < 126 {
+        frame.push(len as u8);
+    } else if let Ok(len) = u16::try_from(len) {
+        frame.push(126);
+        frame.extend_from_slice(&len.to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Decodes the client frame at the start of `data`: its opcode, unmasked
+/// payload and length in bytes, or `None` if it has not fully arrived.
+/// Client frames must be masked (RFC 6455, section 5.1).
+fn client_frame(data: &[u8]) -> Result<Option<(u8, Vec<u8>, usize)>, ()> {
+    let [first, second, ..] = *data else {
+        return Ok(None);
+    };
+    if second & 0x80 == 0 {
+        return Err(());
+    }
+    let (len, header) = match second & 0x7F {
+        126 if data.len() >= 4 => (usize::from(u16::from_be_bytes([data[2], data[3]])), 4),
+        127 if data.len() >= 10 => {
+            let bytes: [u8; 8] = data[2..10].try_into().unwrap();
+            (
+                usize::try_from(u64::from_be_bytes(bytes)).unwrap_or(usize::MAX),
+                10,
+            )
+        }
+        126 | 127 => return Ok(None),
+        len => (usize::from(len), 2),
+    };
+    if len > MAX_CLIENT_FRAME {
+        return Err(());
+    }
+    let end = header + 4 + len;
+    if data.len() < end {
+        return Ok(None);
+    }
+    let mask = &data[header..header + 4];
+    let payload = data[header + 4..end]
+        .iter()
+        .zip(mask.iter().cycle())
+        .map(|(byte, key)| byte ^ key)
+        .collect();
+    Ok(Some((first & 0x0F, payload, end)))
+}
+
+/// SHA-1 digest, needed only for the handshake.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, temp);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_key_and_frames() {
+        // The example from RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(frame(TEXT, b"hi"), [0x81, 2, b'h', b'i']);
+        let long = "x".repeat(300);
+        assert_eq!(&frame(TEXT, long.as_bytes())[..4], &[0x81, 126, 0x01, 0x2C]);
+
+        // A masked ping carrying "hi", then the start of another frame.
+        let ping = [0x89, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2, 0x88];
+        assert_eq!(client_frame(&ping), Ok(Some((PING, b"hi".to_vec(), 8))));
+        assert_eq!(client_frame(&ping[8..]), Ok(None));
+        assert_eq!(client_frame(&[0x89, 0x02, b'h', b'i']), Err(()));
+
+        let upgrade = "GET /ws HTTP/1.1\r\nUpgrade: WebSocket\r\nSec-WebSocket-Key: abc\r\n\r\n";
+        assert_eq!(upgrade_key(upgrade).as_deref(), Some("abc"));
+        assert_eq!(upgrade_key(&upgrade.replace("GET", "POST")), None);
+        assert_eq!(upgrade_key(&upgrade.replace("WebSocket", "h2c")), None);
+        assert_eq!(
+            upgrade_key("GET / HTTP/1.1\r\nSec-WebSocket-Key: abc\r\n\r\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn silent_and_slow_clients_do_not_block() {
+        let mut server = WsBroadcaster::bind("127.0.0.1:0")
+            .unwrap()
+            .with_handshake_timeout(Duration::from_millis(200));
+        let address = server.local_addr().unwrap();
+        let mut silent = TcpStream::connect(address).unwrap();
+        let mut slow = TcpStream::connect(address).unwrap();
+        slow.write_all(b"GET / HTTP/1.1\r\nUpgrade: websocket\r\n")
+            .unwrap();
+
+        let started = Instant::now();
+        for _ in 0..5 {
+            assert_eq!(server.accept(), 0);
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        // The rest of the slow request completes its handshake on a later call.
+        slow.write_all(b"Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n")
+            .unwrap();
+        for _ in 0..100 {
+            if server.accept() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(server.clients(), 1);
+
+        // The silent one is closed once the timeout passes.
+        std::thread::sleep(Duration::from_millis(250));
+        server.accept();
+        silent
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(silent.read(&mut [0u8; 16]).unwrap(), 0);
+    }
+
+    #[test]
+    fn broadcasts_steps_to_clients() {
+        let mut server = WsBroadcaster::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                  Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                  Sec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        // The connection may take a moment to reach the listener's queue.
+        for _ in 0..100 {
+            if server.accept() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.clients(), 1);
+
+        server.on_step(&StepEvent {
+            track: 1,
+            step: 4,
+            cycle: 0,
+            tick: 4,
+            note: 38,
+            velocity: 90,
+            ratchet: 1,
+            fill: false,
+        });
+        client
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 512];
+        let expected = r#"{"type":"step","track":1,"step":4,"cycle":0,"tick":4,"note":38,"velocity":90,"ratchet":1,"fill":false}"#;
+        while !received.ends_with(expected.as_bytes()) {
+            let len = client.read(&mut buf).unwrap();
+            assert!(len > 0);
+            received.extend_from_slice(&buf[..len]);
+        }
+        let response = String::from_utf8_lossy(&received);
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+        // Pings are answered before the next event goes out.
+        client
+            .write_all(&[0x89, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2])
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        server.send_transport(true, 120.0, 4);
+        let mut received = Vec::new();
+        while !received.ends_with(b"}") {
+            let len = client.read(&mut buf).unwrap();
+            assert!(len > 0);
+            received.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(&received[..4], &[0x8A, 2, b'h', b'i']);
+
+        // A close is echoed and the client dropped.
+        client
+            .write_all(&[0x88, 0x82, 0, 0, 0, 0, 0x03, 0xE8])
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        server.send_transport(false, 120.0, 4);
+        assert_eq!(server.clients(), 0);
+        let len = client.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[0x88, 2, 0x03, 0xE8]);
+    }
+
+    #[test]
+    fn limits_waiting_connections() {
+        let mut server = WsBroadcaster::bind("127.0.0.1:0")
+            .unwrap()
+            .with_max_pending(1);
+        let address = server.local_addr().unwrap();
+        let _waiting = TcpStream::connect(address).unwrap();
+        let mut refused = TcpStream::connect(address).unwrap();
+        refused
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        // Both connections may take a moment to reach the listener's queue.
+        for _ in 0..100 {
+            server.accept();
+            if server.pending.len() == 1 && refused.read(&mut [0u8; 16]).is_ok() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the second connection was not closed");
+    }
+}